@@ -0,0 +1,52 @@
+//! Times a `for` loop the interpreter counts with an `f64` against the same
+//! loop written with `while`, which it evaluates as expressions.
+//!
+//! Run with `cargo bench --bench counting_loop`.
+
+use std::time::{Duration, Instant};
+
+use rustlox::test_lox::{Outcome, TestLox};
+
+const ITERATIONS: usize = 1_000_000;
+const RUNS: usize = 5;
+
+/// The median time `source` takes to run in a fresh interpreter.
+fn time(source: &str) -> Duration {
+    let mut times = (0..RUNS)
+        .map(|_| {
+            let mut lox = TestLox::new();
+            let start = Instant::now();
+            let result = lox.run(source);
+            let elapsed = start.elapsed();
+            assert!(
+                matches!(result.outcome, Outcome::Finished(_)),
+                "{:?}",
+                result.outcome
+            );
+            elapsed
+        })
+        .collect::<Vec<_>>();
+    times.sort();
+    times[RUNS / 2]
+}
+
+fn main() {
+    let counting = format!(
+        "var sum = 0; for (var i = 0; i < {}; i = i + 1) {{ sum = sum + i; }}",
+        ITERATIONS
+    );
+    let generic = format!(
+        "var sum = 0; var i = 0; while (i < {}) {{ sum = sum + i; i = i + 1; }}",
+        ITERATIONS
+    );
+
+    let counting = time(&counting);
+    let generic = time(&generic);
+    println!("{} iterations, median of {} runs:", ITERATIONS, RUNS);
+    println!("  counting for loop: {:?}", counting);
+    println!("  while loop:        {:?}", generic);
+    println!(
+        "  speedup:           {:.2}x",
+        generic.as_secs_f64() / counting.as_secs_f64()
+    );
+}
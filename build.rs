@@ -0,0 +1,25 @@
+//! Records which commit and day the interpreter was built from, for
+//! `rustlox --version --verbose` and the `version()` native.
+
+use std::process::Command;
+
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map_or_else(|| String::from("unknown"), |hash| hash.trim().to_string());
+    println!("cargo:rustc-env=RUSTLOX_COMMIT={}", commit);
+    println!(
+        "cargo:rustc-env=RUSTLOX_BUILD_DATE={}",
+        chrono::Utc::now().format("%Y-%m-%d")
+    );
+
+    // A new commit moves HEAD or the branch it points at; the sources are
+    // listed too so the date keeps up with rebuilds.
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-changed=src");
+}
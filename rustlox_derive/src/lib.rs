@@ -0,0 +1,93 @@
+//! Derives for rustlox's syntax trees.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident};
+
+/// Generates a `Visitor<T>` trait and an `accept` method for an enum whose
+/// variants each wrap one node type, like `Expr::Binary(Binary)`.
+///
+/// Each variant gets a `visit_<variant>_<enum>` method, in snake case, taking
+/// its node by reference, so adding a variant adds a method every visitor
+/// must implement. `accept` calls the method for the variant it finds.
+#[proc_macro_derive(Visitor)]
+pub fn derive_visitor(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match visitor(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn visitor(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "Visitor can only be derived for enums",
+            ))
+        }
+    };
+
+    // Nodes are passed as `expr` for `Expr`, `stmt` for `Stmt` and so on.
+    let suffix = snake_case(&name.to_string());
+    let param = Ident::new(&suffix, Span::call_site());
+
+    let mut methods = vec![];
+    let mut arms = vec![];
+    for variant in &data.variants {
+        let node = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0].ty,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "Visitor variants must wrap exactly one node",
+                ))
+            }
+        };
+        let variant_name = &variant.ident;
+        let method = format_ident!("visit_{}_{}", snake_case(&variant_name.to_string()), suffix);
+        methods.push(quote! {
+            fn #method(&mut self, #param: &#node) -> T;
+        });
+        arms.push(quote! {
+            #name::#variant_name(node) => visitor.#method(node),
+        });
+    }
+
+    let vis = &input.vis;
+    let doc = format!("Walks `{}` nodes, one method per kind.", name);
+    Ok(quote! {
+        #[doc = #doc]
+        #vis trait Visitor<T> {
+            #(#methods)*
+        }
+
+        impl #name {
+            pub fn accept<T>(&self, visitor: &mut impl Visitor<T>) -> T {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+/// `StringBuilder` becomes `string_builder`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
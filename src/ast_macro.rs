@@ -0,0 +1,474 @@
+//! Builds ASTs in Rust without going through the scanner and parser, so
+//! interpreter and optimizer code can be exercised on exact trees.
+//!
+//! `lox_ast!` takes one node written as an s-expression, in the same shape
+//! `AstPrinter` prints:
+//!
+//! ```text
+//! lox_ast!((fun add (a b) (print (+ a b))))
+//! lox_ast!((if (< x 10) (expr (= x (+ x 1))) (print "done")))
+//! lox_ast!((call clock))
+//! ```
+//!
+//! Statements are `print`, `var`, `block`, `if`, `while`, `break`, `continue`,
+//! `return`, `fun` with a name, `async`, `class` and `expr`; anything else is
+//! an expression, and `fun` without a name is a lambda. A class's methods are
+//! written `(name (params) statements...)`, after `< Superclass` if it has
+//! one. Properties are read with `(get object name)` and written with
+//! `(set object name value)`, `(super method)` is `super.method`, and `this`
+//! is `this`. `(list elements...)` is a list literal, and elements are read
+//! with `(index object index)` and written with `(index= object index
+//! value)`. `(map (key value)...)` is a map literal. Literals are Rust
+//! numbers, strings, byte strings and booleans, or `nil`, and any other name
+//! is a variable.
+//!
+//! The macro is exported, so an embedder's tests can build trees with
+//! `rustlox::lox_ast!` too.
+
+use crate::{
+    node::Meta,
+    object::{LoxObject, Object},
+    token::{Lexeme, Span, Token, TokenKind},
+};
+
+/// Converts a Rust literal in `lox_ast!` to a Lox value.
+pub trait ToLox {
+    fn to_lox(self) -> LoxObject;
+}
+
+impl ToLox for f64 {
+    fn to_lox(self) -> LoxObject {
+        Object::new_number(self)
+    }
+}
+
+impl ToLox for i32 {
+    fn to_lox(self) -> LoxObject {
+        Object::new_number(self.into())
+    }
+}
+
+impl ToLox for bool {
+    fn to_lox(self) -> LoxObject {
+        Object::new_bool(self)
+    }
+}
+
+impl ToLox for &str {
+    fn to_lox(self) -> LoxObject {
+        Object::new_string(self.to_owned())
+    }
+}
+
+impl<const N: usize> ToLox for &[u8; N] {
+    fn to_lox(self) -> LoxObject {
+        Object::new_bytes(self.to_vec())
+    }
+}
+
+/// A token for `lexeme` at the start of line 1, as the scanner would produce
+/// it.
+pub fn token(lexeme: &str) -> Token {
+    let kind = match lexeme {
+        "(" => TokenKind::LParen,
+        "," => TokenKind::Comma,
+        "[" => TokenKind::LBracket,
+        "{" => TokenKind::LBrace,
+        "-" => TokenKind::Minus,
+        "+" => TokenKind::Plus,
+        "/" => TokenKind::Slash,
+        "*" => TokenKind::Star,
+        "**" => TokenKind::StarStar,
+        "!" => TokenKind::Bang,
+        "!=" => TokenKind::BangEqual,
+        "==" => TokenKind::EqualEqual,
+        ">" => TokenKind::Greater,
+        ">=" => TokenKind::GreaterEqual,
+        "<" => TokenKind::Less,
+        "<=" => TokenKind::LessEqual,
+        "and" => TokenKind::And,
+        "await" => TokenKind::Await,
+        "break" => TokenKind::Break,
+        "continue" => TokenKind::Continue,
+        "fun" => TokenKind::Fun,
+        "or" => TokenKind::Or,
+        "return" => TokenKind::Return,
+        "super" => TokenKind::Super,
+        "this" => TokenKind::This,
+        _ => TokenKind::Identifier,
+    };
+    let span = Span {
+        start: 0,
+        end: lexeme.len(),
+    };
+    Token::new(kind, Lexeme::from(lexeme), Object::nil(), 1, 1, span)
+}
+
+/// Metadata for a node with no source behind it: a new id, and an empty span.
+pub fn meta() -> Meta {
+    Meta::new(Span::default())
+}
+
+#[macro_export]
+macro_rules! lox_ast {
+    ((print $e:tt)) => {
+        $crate::stmt::Stmt::Print($crate::stmt::Print {
+            expression: $crate::lox_ast!($e),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((expr $e:tt)) => {
+        $crate::stmt::Stmt::Expression($crate::stmt::Expression {
+            expression: $crate::lox_ast!($e),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((var $name:ident)) => {
+        $crate::stmt::Stmt::Var($crate::stmt::Var {
+            name: $crate::ast_macro::token(stringify!($name)),
+            annotation: None,
+            initializer: None,
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((var $name:ident $init:tt)) => {
+        $crate::stmt::Stmt::Var($crate::stmt::Var {
+            name: $crate::ast_macro::token(stringify!($name)),
+            annotation: None,
+            initializer: Some($crate::lox_ast!($init)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((block $($s:tt)*)) => {
+        $crate::stmt::Stmt::Block($crate::stmt::Block {
+            statements: vec![$($crate::lox_ast!($s)),*],
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((if $c:tt $then:tt)) => {
+        $crate::stmt::Stmt::If($crate::stmt::If {
+            condition: $crate::lox_ast!($c),
+            then_branch: Box::new($crate::lox_ast!($then)),
+            else_branch: None,
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((if $c:tt $then:tt $else:tt)) => {
+        $crate::stmt::Stmt::If($crate::stmt::If {
+            condition: $crate::lox_ast!($c),
+            then_branch: Box::new($crate::lox_ast!($then)),
+            else_branch: Some(Box::new($crate::lox_ast!($else))),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((while $c:tt $body:tt)) => {
+        $crate::stmt::Stmt::While($crate::stmt::While {
+            keyword: $crate::ast_macro::token("while"),
+            condition: $crate::lox_ast!($c),
+            body: Box::new($crate::lox_ast!($body)),
+            increment: None,
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((break)) => {
+        $crate::stmt::Stmt::Break($crate::stmt::Break {
+            keyword: $crate::ast_macro::token("break"),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((continue)) => {
+        $crate::stmt::Stmt::Continue($crate::stmt::Continue {
+            keyword: $crate::ast_macro::token("continue"),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((return)) => {
+        $crate::stmt::Stmt::Return($crate::stmt::Return {
+            keyword: $crate::ast_macro::token("return"),
+            value: None,
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((return $e:tt)) => {
+        $crate::stmt::Stmt::Return($crate::stmt::Return {
+            keyword: $crate::ast_macro::token("return"),
+            value: Some($crate::lox_ast!($e)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((fun $name:ident ($($param:ident)*) $($s:tt)*)) => {
+        $crate::lox_ast!(@function false $name ($($param)*) $($s)*)
+    };
+    ((async $name:ident ($($param:ident)*) $($s:tt)*)) => {
+        $crate::lox_ast!(@function true $name ($($param)*) $($s)*)
+    };
+    (@function $is_async:literal $name:ident ($($param:ident)*) $($s:tt)*) => {
+        $crate::stmt::Stmt::Function($crate::stmt::Function {
+            name: $crate::ast_macro::token(stringify!($name)),
+            is_async: $is_async,
+            is_getter: false,
+            params: vec![$($crate::stmt::Param {
+                name: $crate::ast_macro::token(stringify!($param)),
+                annotation: None,
+            }),*],
+            return_annotation: None,
+            body: vec![$($crate::lox_ast!($s)),*],
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((class $name:ident < $superclass:ident $($method:tt)*)) => {
+        $crate::lox_ast!(@class $name Some($crate::expr::Variable {
+            name: $crate::ast_macro::token(stringify!($superclass)),
+            meta: $crate::ast_macro::meta(),
+        }); $($method)*)
+    };
+    ((class $name:ident $($method:tt)*)) => {
+        $crate::lox_ast!(@class $name None; $($method)*)
+    };
+    (@class $name:ident $superclass:expr; $(($method:ident ($($param:ident)*) $($s:tt)*))*) => {
+        $crate::stmt::Stmt::Class($crate::stmt::Class {
+            name: $crate::ast_macro::token(stringify!($name)),
+            superclass: $superclass,
+            methods: vec![$($crate::stmt::Function {
+                name: $crate::ast_macro::token(stringify!($method)),
+                is_async: false,
+                is_getter: false,
+                params: vec![$($crate::stmt::Param {
+                    name: $crate::ast_macro::token(stringify!($param)),
+                    annotation: None,
+                }),*],
+                return_annotation: None,
+                body: vec![$($crate::lox_ast!($s)),*],
+                meta: $crate::ast_macro::meta(),
+            }),*],
+            class_methods: vec![],
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+
+    ((= $name:ident $value:tt)) => {
+        $crate::expr::Expr::Assign($crate::expr::Assign {
+            name: $crate::ast_macro::token(stringify!($name)),
+            value: Box::new($crate::lox_ast!($value)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((await $e:tt)) => {
+        $crate::expr::Expr::Await($crate::expr::Await {
+            keyword: $crate::ast_macro::token("await"),
+            expression: Box::new($crate::lox_ast!($e)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((call $callee:tt $($arg:tt)*)) => {
+        $crate::expr::Expr::Call($crate::expr::Call {
+            callee: Box::new($crate::lox_ast!($callee)),
+            paren: $crate::ast_macro::token("("),
+            arguments: vec![$($crate::lox_ast!($arg)),*],
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((fun ($($param:ident)*) $($s:tt)*)) => {
+        $crate::expr::Expr::Lambda($crate::expr::Lambda {
+            keyword: $crate::ast_macro::token("fun"),
+            params: vec![$($crate::stmt::Param {
+                name: $crate::ast_macro::token(stringify!($param)),
+                annotation: None,
+            }),*],
+            return_annotation: None,
+            body: vec![$($crate::lox_ast!($s)),*],
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((get $object:tt $name:ident)) => {
+        $crate::expr::Expr::Get($crate::expr::Get {
+            object: Box::new($crate::lox_ast!($object)),
+            name: $crate::ast_macro::token(stringify!($name)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((set $object:tt $name:ident $value:tt)) => {
+        $crate::expr::Expr::Set($crate::expr::Set {
+            object: Box::new($crate::lox_ast!($object)),
+            name: $crate::ast_macro::token(stringify!($name)),
+            value: Box::new($crate::lox_ast!($value)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((super $method:ident)) => {
+        $crate::expr::Expr::Super($crate::expr::Super {
+            keyword: $crate::ast_macro::token("super"),
+            method: $crate::ast_macro::token(stringify!($method)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((group $e:tt)) => {
+        $crate::expr::Expr::Grouping($crate::expr::Grouping {
+            expression: Box::new($crate::lox_ast!($e)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((index $object:tt $index:tt)) => {
+        $crate::expr::Expr::Index($crate::expr::Index {
+            object: Box::new($crate::lox_ast!($object)),
+            bracket: $crate::ast_macro::token("["),
+            index: Box::new($crate::lox_ast!($index)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((index= $object:tt $index:tt $value:tt)) => {
+        $crate::expr::Expr::IndexSet($crate::expr::IndexSet {
+            object: Box::new($crate::lox_ast!($object)),
+            bracket: $crate::ast_macro::token("["),
+            index: Box::new($crate::lox_ast!($index)),
+            value: Box::new($crate::lox_ast!($value)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((list $($element:tt)*)) => {
+        $crate::expr::Expr::List($crate::expr::List {
+            bracket: $crate::ast_macro::token("["),
+            elements: vec![$($crate::lox_ast!($element)),*],
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((map $(($key:tt $value:tt))*)) => {
+        $crate::expr::Expr::Map($crate::expr::Map {
+            brace: $crate::ast_macro::token("{"),
+            entries: vec![$(($crate::lox_ast!($key), $crate::lox_ast!($value))),*],
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((and $l:tt $r:tt)) => {
+        $crate::lox_ast!(@logical and $l $r)
+    };
+    ((or $l:tt $r:tt)) => {
+        $crate::lox_ast!(@logical or $l $r)
+    };
+    (@logical $op:tt $l:tt $r:tt) => {
+        $crate::expr::Expr::Logical($crate::expr::Logical {
+            left: Box::new($crate::lox_ast!($l)),
+            operator: $crate::ast_macro::token(stringify!($op)),
+            right: Box::new($crate::lox_ast!($r)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ((? $c:tt $then:tt $else:tt)) => {
+        $crate::expr::Expr::Ternary($crate::expr::Ternary {
+            condition: Box::new($crate::lox_ast!($c)),
+            then_branch: Box::new($crate::lox_ast!($then)),
+            else_branch: Box::new($crate::lox_ast!($else)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    (($op:tt $r:tt)) => {
+        $crate::expr::Expr::Unary($crate::expr::Unary {
+            operator: $crate::ast_macro::token(stringify!($op)),
+            right: Box::new($crate::lox_ast!($r)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    (($op:tt $l:tt $r:tt)) => {
+        $crate::expr::Expr::Binary($crate::expr::Binary {
+            left: Box::new($crate::lox_ast!($l)),
+            operator: $crate::ast_macro::token(stringify!($op)),
+            right: Box::new($crate::lox_ast!($r)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    (nil) => {
+        $crate::expr::Expr::Literal($crate::expr::Literal {
+            value: $crate::object::Object::nil(),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    (this) => {
+        $crate::expr::Expr::This($crate::expr::This {
+            keyword: $crate::ast_macro::token("this"),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ($value:literal) => {
+        $crate::expr::Expr::Literal($crate::expr::Literal {
+            value: $crate::ast_macro::ToLox::to_lox($value),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+    ($name:ident) => {
+        $crate::expr::Expr::Variable($crate::expr::Variable {
+            name: $crate::ast_macro::token(stringify!($name)),
+            meta: $crate::ast_macro::meta(),
+        })
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast_printer::AstPrinter, interpreter::Interpreter, parser::Parser, scanner::Scanner,
+        stmt::Stmt,
+    };
+
+    fn run(statements: &[Stmt]) -> String {
+        let value = Interpreter::new().run(statements).unwrap().unwrap();
+        let value = value.read().unwrap().to_string();
+        value
+    }
+
+    #[test]
+    fn builds_the_expression_the_parser_would() {
+        let source = "-a + 2 * (3 - b) < 10 == !c;";
+        let parsed = match &Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap()[0]
+        {
+            Stmt::Expression(statement) => AstPrinter {}.print(statement.expression.clone()),
+            statement => panic!("unexpected statement {:?}", statement),
+        };
+        let built = lox_ast!((== (< (+ (- a) (* 2 (group (- 3 b)))) 10) (! c)));
+        assert_eq!(AstPrinter {}.print(built), parsed);
+    }
+
+    #[test]
+    fn builds_functions_and_closures() {
+        let statements = [
+            lox_ast!((fun adder (n) (return (fun (x) (return (+ x n)))))),
+            lox_ast!((var add2 (call adder 2))),
+            lox_ast!((expr (call add2 40))),
+        ];
+        assert_eq!(run(&statements), "42");
+    }
+
+    #[test]
+    fn builds_classes() {
+        let statements = [
+            lox_ast!((class Counter
+                (init () (expr (set this n 0)))
+                (bump () (expr (set this n (+ (get this n) 1))) (return (get this n))))),
+            lox_ast!((class Twice < Counter
+                (bump () (expr (call (super bump))) (return (call (super bump)))))),
+            lox_ast!((var counter (call Twice))),
+            lox_ast!((expr (call (get counter bump)))),
+            lox_ast!((expr (call (get counter bump)))),
+        ];
+        assert_eq!(run(&statements), "4");
+    }
+
+    #[test]
+    fn builds_loops_lists_and_maps() {
+        let statements = [
+            lox_ast!((var total 0)),
+            lox_ast!((var items (list 1 2 3 "skip" 4))),
+            lox_ast!((var i 0)),
+            lox_ast!((while (< i 5) (block
+                (var item (index items i))
+                (expr (= i (+ i 1)))
+                (if (== item "skip") (continue))
+                (expr (= total (+ total item)))))),
+            lox_ast!((var m (map ("total" total) ("done" true)))),
+            lox_ast!((expr (index= m "total" (? (index m "done") (* (index m "total") 10) nil)))),
+            lox_ast!((expr (index m "total"))),
+        ];
+        assert_eq!(run(&statements), "100");
+    }
+}
@@ -1,7 +1,9 @@
 use crate::expr::{self, Expr};
 
+#[allow(dead_code)]
 pub struct AstPrinter {}
 
+#[allow(dead_code)]
 impl AstPrinter {
     pub fn print(&mut self, expr: Expr) -> String {
         expr.accept(self)
@@ -25,10 +27,53 @@ impl expr::Visitor<String> for AstPrinter {
         self.parenthesize(&binary.operator.lexeme, &[&binary.left, &binary.right])
     }
 
+    fn visit_get_expr(&mut self, expr: &expr::Get) -> String {
+        self.parenthesize(&format!(".{}", expr.name.lexeme), &[&expr.object])
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) -> String {
+        self.parenthesize(
+            &format!(".{}=", expr.name.lexeme),
+            &[&expr.object, &expr.value],
+        )
+    }
+
+    fn visit_super_expr(&mut self, expr: &expr::Super) -> String {
+        format!("(super {})", expr.method.lexeme)
+    }
+
+    fn visit_this_expr(&mut self, _expr: &expr::This) -> String {
+        String::from("this")
+    }
+
     fn visit_grouping_expr(&mut self, grouping: &crate::expr::Grouping) -> String {
         self.parenthesize("group", &[&grouping.expression])
     }
 
+    fn visit_index_expr(&mut self, expr: &expr::Index) -> String {
+        self.parenthesize("[]", &[&expr.object, &expr.index])
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) -> String {
+        self.parenthesize("[]=", &[&expr.object, &expr.index, &expr.value])
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) -> String {
+        let elements: Vec<&Expr> = expr.elements.iter().collect();
+        self.parenthesize("list", &elements)
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) -> String {
+        let params = expr
+            .params
+            .iter()
+            .map(|p| p.name.lexeme.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        // Statements have no s-expression form here.
+        format!("(fun ({}) ...)", params)
+    }
+
     fn visit_literal_expr(&mut self, literal: &crate::expr::Literal) -> String {
         literal.value.read().unwrap().to_string()
     }
@@ -38,17 +83,33 @@ impl expr::Visitor<String> for AstPrinter {
     }
 
     fn visit_variable_expr(&mut self, variable: &expr::Variable) -> String {
-        variable.name.lexeme.clone()
+        variable.name.lexeme.to_string()
     }
 
     fn visit_assign_expr(&mut self, expr: &expr::Assign) -> String {
         self.parenthesize(&format!("{}=", expr.name.lexeme), &[&expr.value])
     }
 
+    fn visit_await_expr(&mut self, expr: &expr::Await) -> String {
+        self.parenthesize("await", &[&expr.expression])
+    }
+
     fn visit_logical_expr(&mut self, expr: &expr::Logical) -> String {
         self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right])
     }
 
+    fn visit_map_expr(&mut self, expr: &expr::Map) -> String {
+        let entries: Vec<&Expr> = expr.entries.iter().flat_map(|(k, v)| [k, v]).collect();
+        self.parenthesize("map", &entries)
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) -> String {
+        self.parenthesize(
+            "?",
+            &[&expr.condition, &expr.then_branch, &expr.else_branch],
+        )
+    }
+
     fn visit_call_expr(&mut self, expr: &expr::Call) -> String {
         format!(
             "({} {})",
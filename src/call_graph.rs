@@ -0,0 +1,270 @@
+use std::collections::HashSet;
+
+use crate::{
+    expr::{self, Expr},
+    stmt::{self, Stmt},
+    token::Token,
+    walk::{walk_function_stmt, Walk},
+};
+
+/// What a call site's callee turned out to be.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Callee {
+    /// A function or class declared in the script.
+    Function(String),
+    /// A native function from the standard library.
+    Native(String),
+    /// A variable which isn't a known function, such as a parameter.
+    Variable(String),
+    /// A callee which isn't a plain name, like `handlers[0]()`.
+    Dynamic,
+}
+
+impl Callee {
+    fn name(&self) -> String {
+        match self {
+            Callee::Function(name) => name.clone(),
+            Callee::Native(name) => format!("{} (native)", name),
+            Callee::Variable(name) => format!("{} (variable)", name),
+            Callee::Dynamic => String::from("<dynamic>"),
+        }
+    }
+}
+
+/// Which functions call which, found statically from the AST. Top-level code
+/// is the caller `<script>`, and a class is the caller for its methods.
+pub struct CallGraph {
+    /// Callers in the order they were declared, each with their callees in
+    /// the order they are first called.
+    pub calls: Vec<(String, Vec<Callee>)>,
+    /// Every name each caller mentions, called or not, such as a function
+    /// passed to `spawn`.
+    pub references: Vec<(String, HashSet<String>)>,
+}
+
+/// Records call sites per caller. A callee is its name, or `None` if the
+/// call isn't through a plain name.
+struct Builder {
+    calls: Vec<(String, Vec<Option<String>>)>,
+    references: Vec<HashSet<String>>,
+    current: Vec<usize>,
+}
+
+impl CallGraph {
+    /// Builds the call graph of a script. `natives` are the names of the
+    /// global native functions.
+    pub fn build(statements: &[Stmt], natives: &HashSet<String>) -> Self {
+        let functions = declarations(statements)
+            .into_iter()
+            .map(|declaration| declaration.name.lexeme.to_string())
+            .collect::<HashSet<_>>();
+
+        let mut builder = Builder {
+            calls: vec![(String::from("<script>"), vec![])],
+            references: vec![HashSet::new()],
+            current: vec![0],
+        };
+        for statement in statements {
+            builder.visit_stmt(statement);
+        }
+
+        let references = builder
+            .calls
+            .iter()
+            .map(|(caller, _)| caller.clone())
+            .zip(builder.references)
+            .collect();
+        let calls = builder
+            .calls
+            .into_iter()
+            .map(|(caller, callees)| {
+                let callees = callees
+                    .into_iter()
+                    .map(|name| match name {
+                        None => Callee::Dynamic,
+                        Some(name) if functions.contains(&name) => Callee::Function(name),
+                        Some(name) if natives.contains(&name) => Callee::Native(name),
+                        Some(name) => Callee::Variable(name),
+                    })
+                    .collect();
+                (caller, callees)
+            })
+            .collect();
+        CallGraph { calls, references }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (caller, callees) in &self.calls {
+            out.push_str(caller);
+            out.push_str(" -> ");
+            if callees.is_empty() {
+                out.push_str("(nothing)");
+            } else {
+                out.push_str(
+                    &callees
+                        .iter()
+                        .map(Callee::name)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph calls {\n");
+        for (caller, callees) in &self.calls {
+            out.push_str(&format!("  {:?};\n", caller));
+            for callee in callees {
+                out.push_str(&format!("  {:?} -> {:?};\n", caller, callee.name()));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl Builder {
+    /// Makes `name` the caller until `current` is popped.
+    fn enter(&mut self, name: &str) {
+        self.calls.push((name.to_owned(), vec![]));
+        self.references.push(HashSet::new());
+        self.current.push(self.calls.len() - 1);
+    }
+
+    fn record(&mut self, callee: Option<String>) {
+        let caller = *self.current.last().unwrap();
+        let callees = &mut self.calls[caller].1;
+        if !callees.contains(&callee) {
+            callees.push(callee);
+        }
+    }
+
+    fn reference(&mut self, name: &str) {
+        let caller = *self.current.last().unwrap();
+        self.references[caller].insert(name.to_owned());
+    }
+}
+
+impl Walk for Builder {
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        self.enter(&stmt.name.lexeme);
+        walk_function_stmt(self, stmt);
+        self.current.pop();
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        self.enter(&stmt.name.lexeme);
+        if let Some(superclass) = &stmt.superclass {
+            self.visit_variable_expr(superclass);
+        }
+        for method in stmt.methods.iter().chain(&stmt.class_methods) {
+            walk_function_stmt(self, method);
+        }
+        self.current.pop();
+    }
+
+    fn visit_call_expr(&mut self, expr: &expr::Call) {
+        match &*expr.callee {
+            Expr::Variable(v) => {
+                self.record(Some(v.name.lexeme.to_string()));
+                self.reference(&v.name.lexeme);
+            }
+            callee => {
+                self.record(None);
+                self.visit_expr(callee);
+            }
+        }
+        for argument in &expr.arguments {
+            self.visit_expr(argument);
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) {
+        self.reference(&expr.name.lexeme);
+    }
+}
+
+/// A function or class declared in the script.
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    /// `"Function"` or `"Class"`.
+    pub kind: &'static str,
+    pub name: Token,
+}
+
+/// Every function and class declared anywhere in the script, including
+/// inside methods and lambdas, in the order they appear.
+pub fn declarations(statements: &[Stmt]) -> Vec<Declaration> {
+    let mut declarations = Declarations { found: vec![] };
+    statements.iter().for_each(|s| declarations.visit_stmt(s));
+    declarations.found
+}
+
+struct Declarations {
+    found: Vec<Declaration>,
+}
+
+impl Walk for Declarations {
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        self.found.push(Declaration {
+            kind: "Function",
+            name: stmt.name.clone(),
+        });
+        walk_function_stmt(self, stmt);
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        self.found.push(Declaration {
+            kind: "Class",
+            name: stmt.name.clone(),
+        });
+        // Methods are only called through an instance or the class, never
+        // by their name alone.
+        for method in stmt.methods.iter().chain(&stmt.class_methods) {
+            walk_function_stmt(self, method);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn graph(source: &str) -> String {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap();
+        let natives = HashSet::from([String::from("clock")]);
+        CallGraph::build(&statements, &natives).to_text()
+    }
+
+    #[test]
+    fn finds_calls_in_functions_and_classes() {
+        let text = graph(
+            "class Point < Base {
+                init() { helper(clock()); }
+                length() { fun square(x) { return x * x; } return square(this.x); }
+            }
+            var make = fun () {
+                fun inner() {}
+                inner();
+                return Point();
+            };
+            make();
+            handlers[0]();",
+        );
+        assert_eq!(
+            text,
+            "<script> -> inner, Point, make (variable), <dynamic>
+Point -> helper (variable), clock (native), square
+square -> (nothing)
+inner -> (nothing)
+"
+        );
+    }
+}
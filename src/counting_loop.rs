@@ -0,0 +1,196 @@
+use crate::{
+    expr::{self, Expr},
+    object::Object,
+    stmt::Stmt,
+    token::{Token, TokenKind},
+    walk::{walk_assign_expr, Walk},
+};
+
+/// A loop written `for (var i = start; i < end; i = i + step) body`, where
+/// neither `body` nor `end` assigns `i`. The interpreter can count these with
+/// an `f64` instead of evaluating the condition and increment as expressions.
+///
+/// The comparison can be any of `<`, `<=`, `>` and `>=`, and the increment can
+/// subtract instead. `end` is still evaluated before every iteration.
+pub struct CountingLoop<'a> {
+    pub keyword: &'a Token,
+    pub name: &'a Token,
+    pub start: f64,
+    pub operator: &'a Token,
+    pub end: &'a Expr,
+    pub step: f64,
+    pub body: &'a Stmt,
+}
+
+fn number(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Literal(l) => match *l.value.read().unwrap() {
+            Object::Number(n) => Some(n),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_variable(expr: &Expr, name: &str) -> bool {
+    matches!(expr, Expr::Variable(v) if v.name.lexeme == name)
+}
+
+impl<'a> CountingLoop<'a> {
+    /// Matches the statements of the block a `for` loop is lowered to.
+    pub fn find(statements: &'a [Stmt]) -> Option<Self> {
+        let (declaration, while_stmt) = match statements {
+            [Stmt::Var(v), Stmt::While(w)] => (v, w),
+            _ => return None,
+        };
+        let name = &declaration.name;
+        let start = number(declaration.initializer.as_ref()?)?;
+
+        let (operator, end) = match &while_stmt.condition {
+            Expr::Binary(b) if is_variable(&b.left, &name.lexeme) => (&b.operator, &*b.right),
+            _ => return None,
+        };
+        if !matches!(
+            operator.kind,
+            TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual
+        ) {
+            return None;
+        }
+
+        let body = &*while_stmt.body;
+        let increment = while_stmt.increment.as_ref()?;
+        let (step, value) = match increment {
+            Expr::Assign(a) if a.name.lexeme == name.lexeme => match &*a.value {
+                Expr::Binary(b) if is_variable(&b.left, &name.lexeme) => {
+                    let step = number(&b.right)?;
+                    match b.operator.kind {
+                        TokenKind::Plus => (step, &*a.value),
+                        TokenKind::Minus => (-step, &*a.value),
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let mut assigns = AssignsTo {
+            name: &name.lexeme,
+            found: false,
+        };
+        // The fast path only reads `end` and counts the increment itself, so
+        // `i` can't be assigned anywhere else it'd run.
+        assigns.visit_stmt(body);
+        assigns.visit_expr(end);
+        assigns.visit_expr(value);
+        if assigns.found {
+            return None;
+        }
+
+        Some(CountingLoop {
+            keyword: &while_stmt.keyword,
+            name,
+            start,
+            operator,
+            end,
+            step,
+            body,
+        })
+    }
+
+    pub fn compare(&self, i: f64, end: f64) -> bool {
+        match self.operator.kind {
+            TokenKind::Less => i < end,
+            TokenKind::LessEqual => i <= end,
+            TokenKind::Greater => i > end,
+            _ => i >= end,
+        }
+    }
+}
+
+/// Looks for any assignment to `name`, in any scope.
+struct AssignsTo<'a> {
+    name: &'a str,
+    found: bool,
+}
+
+impl Walk for AssignsTo<'_> {
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) {
+        self.found |= expr.name.lexeme == self.name;
+        walk_assign_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner, test_lox::TestLox};
+
+    /// Whether the `for` loop `source` starts with takes the fast path.
+    fn is_counting(source: &str) -> bool {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap();
+        match &statements[0] {
+            Stmt::Block(block) => CountingLoop::find(&block.statements).is_some(),
+            _ => false,
+        }
+    }
+
+    fn run(source: &str) -> String {
+        TestLox::new().run(source).stdout
+    }
+
+    #[test]
+    fn finds_canonical_loops() {
+        assert!(is_counting("for (var i = 0; i < 3; i = i + 1) print i;"));
+        assert!(is_counting("for (var i = 3; i >= 0; i = i - 1) print i;"));
+        assert!(!is_counting("for (var i = 0; i < 3; i = i * 2) print i;"));
+    }
+
+    #[test]
+    fn counts_like_the_generic_loop() {
+        assert_eq!(
+            run("for (var i = 0; i < 5; i = i + 2) print i;"),
+            "0\n2\n4\n"
+        );
+        assert_eq!(
+            run("for (var i = 3; i >= 1; i = i - 1) print i;"),
+            "3\n2\n1\n"
+        );
+    }
+
+    #[test]
+    fn body_assigning_the_counter_runs_generically() {
+        let source = "for (var i = 0; i < 10; i = i + 1) { print i; i = i + 3; }";
+        assert!(!is_counting(source));
+        assert_eq!(run(source), "0\n4\n8\n");
+    }
+
+    #[test]
+    fn condition_assigning_the_counter_runs_generically() {
+        let source = "for (var i = 0; i < 10 - (i = i * 2); i = i + 1) print i;";
+        assert!(!is_counting(source));
+        assert_eq!(run(source), "0\n2\n6\n");
+        assert_eq!(
+            run(source),
+            run("var i = 0; while (i < 10 - (i = i * 2)) { print i; i = i + 1; }")
+        );
+    }
+
+    #[test]
+    fn compares_with_bigint_and_decimal_bounds() {
+        assert_eq!(
+            run("for (var i = 0; i < 3n; i = i + 1) print i;"),
+            "0\n1\n2\n"
+        );
+        assert_eq!(
+            run("for (var i = 0; i < 3n; i = i + 1) print i;"),
+            run("var i = 0; while (i < 3n) { print i; i = i + 1; }")
+        );
+        assert_eq!(
+            run("for (var i = 0; i <= decimal(\"1.5\"); i = i + 1) print i;"),
+            "0\n1\n"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Writes a report if the interpreter itself panics, so a bug report can
+//! say what was running when it did.
+
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    panic::PanicHookInfo,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::SystemTime,
+};
+
+use crate::{token::Span, version};
+
+/// How many of the statements executed last a report lists.
+const TRACE_LENGTH: usize = 50;
+
+/// The spans of the statements executed last, oldest first, shared by an
+/// interpreter and its forks.
+#[derive(Debug, Clone)]
+pub struct Trace(Arc<Mutex<VecDeque<Span>>>);
+
+impl Trace {
+    pub fn new() -> Self {
+        Trace(Arc::new(Mutex::new(VecDeque::with_capacity(TRACE_LENGTH))))
+    }
+
+    pub fn record(&self, span: Span) {
+        let mut spans = self.0.lock().unwrap();
+        if spans.len() == TRACE_LENGTH {
+            spans.pop_front();
+        }
+        spans.push_back(span);
+    }
+
+    /// Forgets the statements so far, since their spans are into a source
+    /// which is about to be replaced.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    /// The spans, unless the panic happened while they were being recorded.
+    fn spans(&self) -> Vec<Span> {
+        match self.0.try_lock() {
+            Ok(spans) => spans.iter().copied().collect(),
+            Err(_) => vec![],
+        }
+    }
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Installs a panic hook which, after the usual message, writes a report of
+/// `script`, the interpreter's version, a backtrace and the statements in
+/// `trace`, quoted from `source`, then says where the report is.
+pub fn install(script: Option<String>, trace: Trace, source: &'static RwLock<String>) {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default(info);
+        let source = match source.try_read() {
+            Ok(source) => source.clone(),
+            Err(_) => String::new(),
+        };
+        let report = report(info, script.as_deref(), &trace.spans(), &source);
+        match write(&report) {
+            Ok(path) => eprintln!(
+                "rustlox crashed. Please attach the report written to {} to a bug report.",
+                path.display()
+            ),
+            Err(e) => eprintln!("rustlox crashed, and could not write a report: {}", e),
+        }
+    }));
+}
+
+fn report(info: &PanicHookInfo, script: Option<&str>, spans: &[Span], source: &str) -> String {
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "{} ({}, built {})",
+        version::short(),
+        version::COMMIT,
+        version::BUILD_DATE
+    );
+    let _ = writeln!(report, "Script: {}", script.unwrap_or("<repl>"));
+    let _ = writeln!(report, "Panic: {}", info);
+    if let Some(&last) = spans.last() {
+        let _ = writeln!(report, "Line: {}", line(source, last));
+    }
+
+    let _ = writeln!(report, "\nLast statements executed, oldest first:");
+    for &span in spans {
+        let _ = writeln!(
+            report,
+            "[line {}] {}",
+            line(source, span),
+            excerpt(source, span)
+        );
+    }
+
+    let _ = writeln!(
+        report,
+        "\nBacktrace:\n{}",
+        std::backtrace::Backtrace::force_capture()
+    );
+    report
+}
+
+/// The line `span` starts on, or 0 if it isn't in `source`.
+fn line(source: &str, span: Span) -> usize {
+    match source.get(..span.start) {
+        Some(before) => before.matches('\n').count() + 1,
+        None => 0,
+    }
+}
+
+/// The first line of the statement at `span`, shortened if it's long.
+fn excerpt(source: &str, span: Span) -> String {
+    let text = source.get(span.start..span.end).unwrap_or("");
+    let first = text.lines().next().unwrap_or("").trim();
+    if first.chars().count() > 80 {
+        first.chars().take(77).collect::<String>() + "..."
+    } else {
+        first.to_string()
+    }
+}
+
+/// Writes `report` to a new file in the system's temporary directory,
+/// returning its path.
+fn write(report: &str) -> std::io::Result<PathBuf> {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let path = std::env::temp_dir().join(format!(
+        "rustlox-crash-{}-{}.txt",
+        std::process::id(),
+        seconds
+    ));
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
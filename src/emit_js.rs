@@ -0,0 +1,637 @@
+use crate::{
+    expr::{self, Expr},
+    object::Object,
+    stmt::{self, Param, Stmt},
+    token::TokenKind,
+};
+
+/// Helpers for the parts of Lox which JavaScript does differently: only `nil`
+/// and `false` are falsey, `and` and `or` return an operand, and values print
+/// the way the interpreter prints them.
+const PRELUDE: &str = r#""use strict";
+
+function $truthy(value) {
+    return value !== null && value !== false;
+}
+
+// Numbers print in full, as Rust's `{}` prints an f64: never in exponent
+// form, and with `inf` and `-0` spelled the way the interpreter spells them.
+function $num(value) {
+    if (Number.isNaN(value)) return "NaN";
+    if (!Number.isFinite(value)) return value > 0 ? "inf" : "-inf";
+    if (Object.is(value, -0)) return "-0";
+    const text = String(value);
+    if (!text.includes("e")) return text;
+    const [mantissa, exponent] = value.toExponential().split("e");
+    const sign = value < 0 ? "-" : "";
+    const digits = mantissa.replace("-", "").replace(".", "");
+    const point = Number(exponent) + 1;
+    if (point <= 0) return sign + "0." + "0".repeat(-point) + digits;
+    return sign + digits.padEnd(point, "0");
+}
+
+function $str(value, nested) {
+    if (value === null) return "nil";
+    if (typeof value === "number") return $num(value);
+    if (typeof value === "string") return nested ? JSON.stringify(value) : value;
+    if (value.$class) return value.$class.$name + " instance";
+    if (value.$name) return value.$name;
+    if (typeof value === "function") return value.name ? "<fn " + value.name + ">" : "<fn>";
+    if (value instanceof Uint8Array) {
+        let out = 'b"';
+        for (const byte of value) {
+            if (byte === 0x22 || byte === 0x5c) out += "\\" + String.fromCharCode(byte);
+            else if (byte >= 0x20 && byte <= 0x7e) out += String.fromCharCode(byte);
+            else out += "\\x" + byte.toString(16).padStart(2, "0");
+        }
+        return out + '"';
+    }
+    if (Array.isArray(value)) return "[" + value.map((v) => $str(v, true)).join(", ") + "]";
+    if (value instanceof Map) {
+        const entries = [...value].map(([k, v]) => $str(k, true) + ": " + $str(v, true));
+        return "{" + entries.join(", ") + "}";
+    }
+    return String(value);
+}
+
+// Classes are called like functions. Methods live on a prototype without
+// JavaScript's own methods, so any field name is safe to use, and inherit
+// from the superclass's prototype. Class methods live on a similar `$static`
+// object. `methods` and `classMethods` take the superclass, which `super`
+// refers to, and give object literals whose getters are copied as getters.
+function $class(name, superclass, methods, classMethods) {
+    if (superclass !== null && superclass.$prototype === undefined) {
+        throw new Error("Superclass must be a class.");
+    }
+    const prototype = Object.create(superclass === null ? null : superclass.$prototype);
+    Object.defineProperties(prototype, Object.getOwnPropertyDescriptors(methods(superclass)));
+    const statics = Object.create(superclass === null ? null : superclass.$static);
+    Object.defineProperties(statics, Object.getOwnPropertyDescriptors(classMethods(superclass)));
+    const klass = function (...args) {
+        const instance = Object.create(prototype);
+        if (prototype.init) prototype.init.apply(instance, args);
+        return instance;
+    };
+    klass.$name = name;
+    klass.$prototype = prototype;
+    klass.$static = statics;
+    prototype.$class = klass;
+    return klass;
+}
+
+// Methods are bound to the instance they're accessed on, keeping their name.
+function $bind(method, instance) {
+    const bound = method.bind(instance);
+    Object.defineProperty(bound, "name", { value: method.name });
+    return bound;
+}
+
+// Looks `name` up in `object` for `receiver`, which getters run on and
+// methods are bound to.
+function $property(object, name, receiver) {
+    if (!(name in object)) throw new Error("Undefined property '" + name + "'.");
+    const value = Reflect.get(object, name, receiver);
+    if (typeof value === "function" && !Object.hasOwn(receiver, name)) return $bind(value, receiver);
+    return value;
+}
+
+function $get(object, name) {
+    if (typeof object === "function" && object.$static !== undefined) {
+        return $property(object.$static, name, object);
+    }
+    return $property(object, name, object);
+}
+
+// Defined rather than assigned, so a field can shadow a getter.
+function $set(object, name, value) {
+    Object.defineProperty(object, name, { value, writable: true, enumerable: true, configurable: true });
+    return value;
+}
+
+// Maps are JavaScript `Map`s, which keep keys in insertion order and don't
+// turn them into strings, so they're indexed through these.
+function $index(object, index) {
+    if (object instanceof Map) return object.has(index) ? object.get(index) : null;
+    return object[index];
+}
+
+function $setIndex(object, index, value) {
+    if (object instanceof Map) object.set(index, value);
+    else object[index] = value;
+    return value;
+}
+
+function clock() {
+    return Date.now() / 1000;
+}
+
+function random() {
+    return Math.random();
+}
+"#;
+
+/// JavaScript reserved words which are valid Lox identifiers. Lox names can't
+/// contain `$`, so appending one can't clash with another name.
+const RESERVED: &[&str] = &[
+    "arguments",
+    "break",
+    "case",
+    "catch",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "finally",
+    "function",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+    "switch",
+    "throw",
+    "try",
+    "typeof",
+    "undefined",
+    "void",
+    "with",
+    "yield",
+];
+
+/// Lowers a script to JavaScript which runs under Node or in a browser.
+///
+/// Correct programs behave the same, but runtime errors aren't reproduced:
+/// `1 + "a"` is `"1a"` rather than an error. Of the natives, only `clock` and
+/// `random` are provided.
+pub struct JsEmitter {
+    out: String,
+    depth: usize,
+}
+
+impl JsEmitter {
+    pub fn emit(statements: &[Stmt]) -> String {
+        let mut emitter = JsEmitter {
+            out: String::from(PRELUDE),
+            depth: 0,
+        };
+        emitter.out.push('\n');
+        for statement in statements {
+            statement.accept(&mut emitter);
+        }
+        emitter.out
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Emits the statements inside a braced body, whether or not the
+    /// statement is a block itself.
+    fn body(&mut self, body: &Stmt) {
+        self.depth += 1;
+        match body {
+            Stmt::Block(b) => b.statements.iter().for_each(|s| s.accept(self)),
+            _ => body.accept(self),
+        }
+        self.depth -= 1;
+    }
+
+    fn name(name: &str) -> String {
+        if RESERVED.contains(&name) {
+            format!("{}$", name)
+        } else {
+            name.to_owned()
+        }
+    }
+
+    /// A function's parameters and body, the `(a, b) {...}` after
+    /// `function name`. A method saves JavaScript's `this` as `$this`, so
+    /// functions nested in it see the same `this`.
+    fn function(&mut self, params: &[Param], body: &[Stmt], is_method: bool) -> String {
+        let params = params
+            .iter()
+            .map(|p| Self::name(&p.name.lexeme))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut emitter = JsEmitter {
+            out: String::new(),
+            depth: self.depth + 1,
+        };
+        if is_method {
+            emitter.line("const $this = this;");
+        }
+        body.iter().for_each(|s| s.accept(&mut emitter));
+        emitter.line("return null;");
+        format!(
+            "({}) {{\n{}{}}}",
+            params,
+            emitter.out,
+            "    ".repeat(self.depth)
+        )
+    }
+
+    /// The methods in a class's object literal, one per line.
+    fn methods(&mut self, methods: &[stmt::Function]) {
+        self.depth += 1;
+        for method in methods {
+            let function = self.function(&method.params, &method.body, true);
+            if method.is_getter {
+                self.line(&format!("get {}{},", method.name.lexeme, function));
+            } else {
+                self.line(&format!(
+                    "{}: function {}{},",
+                    method.name.lexeme,
+                    Self::name(&method.name.lexeme),
+                    function
+                ));
+            }
+        }
+        self.depth -= 1;
+    }
+
+    fn condition(&mut self, expr: &Expr) -> String {
+        format!("$truthy({})", expr.accept(self))
+    }
+}
+
+/// `n` as a JavaScript number literal. A negative number is parenthesized,
+/// since `-2 ** 2` isn't valid JavaScript.
+fn number_literal(n: f64) -> String {
+    if n.is_nan() {
+        String::from("NaN")
+    } else if n.is_infinite() {
+        String::from(if n > 0.0 { "Infinity" } else { "(-Infinity)" })
+    } else if n.is_sign_negative() {
+        format!("(-{})", -n)
+    } else {
+        n.to_string()
+    }
+}
+
+fn string_literal(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\u{2028}' || c == '\u{2029}' => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl expr::Visitor<String> for JsEmitter {
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) -> String {
+        format!(
+            "{} = {}",
+            Self::name(&expr.name.lexeme),
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_await_expr(&mut self, expr: &expr::Await) -> String {
+        format!("await {}", expr.expression.accept(self))
+    }
+
+    fn visit_binary_expr(&mut self, expr: &expr::Binary) -> String {
+        let operator = match expr.operator.kind {
+            // Parenthesized so it isn't taken for separate arguments to a
+            // call like `$truthy`.
+            TokenKind::Comma => {
+                return format!("({}, {})", expr.left.accept(self), expr.right.accept(self))
+            }
+            // Parenthesized since JavaScript rejects `-2 ** 2`, which Lox
+            // reads as `-(2 ** 2)`.
+            TokenKind::StarStar => {
+                return format!(
+                    "({} ** {})",
+                    expr.left.accept(self),
+                    expr.right.accept(self)
+                )
+            }
+            TokenKind::EqualEqual => "===",
+            TokenKind::BangEqual => "!==",
+            _ => &expr.operator.lexeme,
+        };
+        format!(
+            "{} {} {}",
+            expr.left.accept(self),
+            operator,
+            expr.right.accept(self)
+        )
+    }
+
+    fn visit_call_expr(&mut self, expr: &expr::Call) -> String {
+        let arguments = expr
+            .arguments
+            .iter()
+            .map(|a| a.accept(self))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{}({})", expr.callee.accept(self), arguments)
+    }
+
+    fn visit_get_expr(&mut self, expr: &expr::Get) -> String {
+        format!(
+            "$get({}, \"{}\")",
+            expr.object.accept(self),
+            expr.name.lexeme
+        )
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) -> String {
+        format!("({})", expr.expression.accept(self))
+    }
+
+    fn visit_index_expr(&mut self, expr: &expr::Index) -> String {
+        format!(
+            "$index({}, {})",
+            expr.object.accept(self),
+            expr.index.accept(self)
+        )
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) -> String {
+        format!(
+            "$setIndex({}, {}, {})",
+            expr.object.accept(self),
+            expr.index.accept(self),
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) -> String {
+        // Parenthesized so it isn't taken for a declaration at the start of a
+        // statement.
+        format!(
+            "(function {})",
+            self.function(&expr.params, &expr.body, false)
+        )
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) -> String {
+        let elements: Vec<String> = expr.elements.iter().map(|e| e.accept(self)).collect();
+        format!("[{}]", elements.join(", "))
+    }
+
+    fn visit_literal_expr(&mut self, expr: &expr::Literal) -> String {
+        match &*expr.value.read().unwrap() {
+            Object::Nil => String::from("null"),
+            Object::String(s) => string_literal(s),
+            Object::Bytes(b) => format!(
+                "Uint8Array.of({})",
+                b.iter().map(u8::to_string).collect::<Vec<_>>().join(", ")
+            ),
+            Object::BigInt(b) => format!("{}n", b),
+            Object::Number(n) => number_literal(*n),
+            value => value.to_string(),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &expr::Logical) -> String {
+        let left = expr.left.accept(self);
+        let right = expr.right.accept(self);
+        if expr.operator.kind == TokenKind::Or {
+            format!("(($l) => $truthy($l) ? $l : {})({})", right, left)
+        } else {
+            format!("(($l) => $truthy($l) ? {} : $l)({})", right, left)
+        }
+    }
+
+    fn visit_map_expr(&mut self, expr: &expr::Map) -> String {
+        let entries: Vec<String> = expr
+            .entries
+            .iter()
+            .map(|(k, v)| format!("[{}, {}]", k.accept(self), v.accept(self)))
+            .collect();
+        format!("new Map([{}])", entries.join(", "))
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) -> String {
+        format!(
+            "$set({}, \"{}\", {})",
+            expr.object.accept(self),
+            expr.name.lexeme,
+            expr.value.accept(self)
+        )
+    }
+
+    fn visit_super_expr(&mut self, expr: &expr::Super) -> String {
+        // In a class method, `this` is the class.
+        format!(
+            "$property(typeof $this === \"function\" ? $super.$static : $super.$prototype, \"{}\", $this)",
+            expr.method.lexeme
+        )
+    }
+
+    fn visit_this_expr(&mut self, _expr: &expr::This) -> String {
+        String::from("$this")
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) -> String {
+        format!(
+            "({} ? {} : {})",
+            self.condition(&expr.condition),
+            expr.then_branch.accept(self),
+            expr.else_branch.accept(self)
+        )
+    }
+
+    fn visit_unary_expr(&mut self, expr: &expr::Unary) -> String {
+        if expr.operator.kind == TokenKind::Bang {
+            format!("!{}", self.condition(&expr.right))
+        } else {
+            let right = expr.right.accept(self);
+            // Keep `- -x` from becoming `--x`.
+            if right.starts_with('-') {
+                format!("-({})", right)
+            } else {
+                format!("-{}", right)
+            }
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) -> String {
+        Self::name(&expr.name.lexeme)
+    }
+}
+
+impl stmt::Visitor<()> for JsEmitter {
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) {
+        self.line("{");
+        self.depth += 1;
+        stmt.statements.iter().for_each(|s| s.accept(self));
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &stmt::Break) {
+        self.line("break;");
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        let keyword = if self.depth == 0 { "var" } else { "let" };
+        let name = &stmt.name.lexeme;
+        let superclass = match &stmt.superclass {
+            Some(superclass) => Self::name(&superclass.name.lexeme),
+            None => String::from("null"),
+        };
+        self.line(&format!(
+            "{} {} = $class({}, {}, ($super) => ({{",
+            keyword,
+            Self::name(name),
+            string_literal(name),
+            superclass
+        ));
+        self.methods(&stmt.methods);
+        self.line("}), ($super) => ({");
+        self.methods(&stmt.class_methods);
+        self.line("}));");
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &stmt::Continue) {
+        self.line("continue;");
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) {
+        let expression = stmt.expression.accept(self);
+        self.line(&format!("{};", expression));
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        let keyword = if stmt.is_async {
+            "async function"
+        } else {
+            "function"
+        };
+        let function = self.function(&stmt.params, &stmt.body, false);
+        self.line(&format!(
+            "{} {}{}",
+            keyword,
+            Self::name(&stmt.name.lexeme),
+            function
+        ));
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &stmt::If) {
+        let condition = self.condition(&stmt.condition);
+        self.line(&format!("if ({}) {{", condition));
+        self.body(&stmt.then_branch);
+
+        let mut else_branch = &stmt.else_branch;
+        while let Some(branch) = else_branch {
+            if let Stmt::If(i) = &**branch {
+                let condition = self.condition(&i.condition);
+                self.line(&format!("}} else if ({}) {{", condition));
+                self.body(&i.then_branch);
+                else_branch = &i.else_branch;
+            } else {
+                self.line("} else {");
+                self.body(branch);
+                break;
+            }
+        }
+        self.line("}");
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &stmt::Print) {
+        let expression = stmt.expression.accept(self);
+        self.line(&format!("console.log($str({}));", expression));
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &stmt::Return) {
+        let value = match &stmt.value {
+            Some(value) => value.accept(self),
+            None => String::from("null"),
+        };
+        self.line(&format!("return {};", value));
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) {
+        let initializer = match &stmt.initializer {
+            Some(initializer) => initializer.accept(self),
+            None => String::from("null"),
+        };
+        // Globals may be redeclared in Lox, which `let` doesn't allow.
+        let keyword = if self.depth == 0 { "var" } else { "let" };
+        self.line(&format!(
+            "{} {} = {};",
+            keyword,
+            Self::name(&stmt.name.lexeme),
+            initializer
+        ));
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &stmt::While) {
+        let condition = self.condition(&stmt.condition);
+        match &stmt.increment {
+            Some(increment) => {
+                let increment = increment.accept(self);
+                self.line(&format!("for (; {}; {}) {{", condition, increment));
+            }
+            None => self.line(&format!("while ({}) {{", condition)),
+        }
+        self.body(&stmt.body);
+        self.line("}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_macro::{meta, token};
+
+    #[test]
+    fn writes_numbers_javascript_can_read() {
+        let cases = [
+            (0.5, "0.5"),
+            (1e21, "1000000000000000000000"),
+            (-2.0, "(-2)"),
+            (-0.0, "(-0)"),
+            (f64::INFINITY, "Infinity"),
+            (-f64::INFINITY, "(-Infinity)"),
+            (f64::NAN, "NaN"),
+        ];
+        for (n, literal) in cases {
+            let square = Stmt::Expression(stmt::Expression {
+                expression: Expr::Binary(expr::Binary {
+                    left: Box::new(Expr::Literal(expr::Literal {
+                        value: Object::new_number(n),
+                        meta: meta(),
+                    })),
+                    operator: token("**"),
+                    right: Box::new(lox_ast!(2)),
+                    meta: meta(),
+                }),
+                meta: meta(),
+            });
+            let js = JsEmitter::emit(&[square]);
+            assert!(js.ends_with(&format!("({} ** 2);\n", literal)), "{}", js);
+        }
+    }
+}
@@ -22,30 +22,77 @@ impl Environment {
         }
     }
 
+    pub fn enclosing(&self) -> Option<Arc<RwLock<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = (&String, &LoxObject)> {
+        self.values.iter()
+    }
+
     pub fn define(&mut self, name: &str, value: LoxObject) {
         self.values.insert(name.to_owned(), value);
     }
 
+    pub fn remove(&mut self, name: &str) {
+        self.values.remove(name);
+    }
+
+    /// The value of `name` in the environment `distance` out from this one,
+    /// as worked out by the resolver.
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<LoxObject, RuntimeError> {
+        if distance == 0 {
+            return self
+                .values
+                .get(&*name.lexeme)
+                .cloned()
+                .ok_or_else(|| undefined(name));
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.read().unwrap().get_at(distance - 1, name),
+            None => Err(undefined(name)),
+        }
+    }
+
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: LoxObject,
+    ) -> Result<(), RuntimeError> {
+        if distance == 0 {
+            return match self.values.get_mut(&*name.lexeme) {
+                Some(v) => {
+                    *v = value;
+                    Ok(())
+                }
+                None => Err(undefined(name)),
+            };
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing
+                .write()
+                .unwrap()
+                .assign_at(distance - 1, name, value),
+            None => Err(undefined(name)),
+        }
+    }
+
     fn try_get(&self, name: &Token) -> Option<LoxObject> {
-        self.values.get(&name.lexeme).cloned().or_else(|| {
+        self.values.get(&*name.lexeme).cloned().or_else(|| {
             self.enclosing
                 .as_ref()
-                .and_then(|e| e.write().unwrap().try_get(name))
+                .and_then(|e| e.read().unwrap().try_get(name))
         })
     }
 
     pub fn get(&self, name: &Token) -> Result<LoxObject, RuntimeError> {
-        self.try_get(name).ok_or_else(|| {
-            RuntimeError::new(
-                name.clone(),
-                format!("Undefined variable '{}'.", name.lexeme),
-            )
-        })
+        self.try_get(name).ok_or_else(|| undefined(name))
     }
 
     fn try_assign(&mut self, name: &Token, value: LoxObject) -> Option<()> {
         self.values
-            .get_mut(&name.lexeme)
+            .get_mut(&*name.lexeme)
             .map(|v| *v = value.clone())
             .or_else(|| {
                 self.enclosing
@@ -55,11 +102,19 @@ impl Environment {
     }
 
     pub fn assign(&mut self, name: &Token, value: LoxObject) -> Result<(), RuntimeError> {
-        self.try_assign(name, value).ok_or_else(|| {
-            RuntimeError::new(
-                name.clone(),
-                format!("Undefined variable '{}'.", name.lexeme),
-            )
-        })
+        self.try_assign(name, value).ok_or_else(|| undefined(name))
     }
 }
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn undefined(name: &Token) -> RuntimeError {
+    RuntimeError::new(
+        name.clone(),
+        format!("Undefined variable '{}'.", name.lexeme),
+    )
+}
@@ -1,47 +1,81 @@
-use crate::{object::LoxObject, token::Token};
+use rustlox_derive::Visitor;
 
-pub trait Visitor<T> {
-    fn visit_assign_expr(&mut self, expr: &Assign) -> T;
-    fn visit_binary_expr(&mut self, expr: &Binary) -> T;
-    fn visit_call_expr(&mut self, expr: &Call) -> T;
-    fn visit_grouping_expr(&mut self, expr: &Grouping) -> T;
-    fn visit_literal_expr(&mut self, expr: &Literal) -> T;
-    fn visit_logical_expr(&mut self, expr: &Logical) -> T;
-    fn visit_unary_expr(&mut self, expr: &Unary) -> T;
-    fn visit_variable_expr(&mut self, expr: &Variable) -> T;
-}
+use crate::{
+    node::{Meta, NodeId},
+    object::LoxObject,
+    stmt::{Param, Stmt},
+    token::{Span, Token},
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Visitor)]
 pub enum Expr {
     Assign(Assign),
+    Await(Await),
     Binary(Binary),
     Call(Call),
+    Get(Get),
     Grouping(Grouping),
+    Index(Index),
+    IndexSet(IndexSet),
+    Lambda(Lambda),
+    List(List),
     Literal(Literal),
     Logical(Logical),
+    Map(Map),
+    Set(Set),
+    Super(Super),
+    Ternary(Ternary),
+    This(This),
     Unary(Unary),
     Variable(Variable),
 }
 
 impl Expr {
-    pub fn accept<T>(&self, visitor: &mut impl Visitor<T>) -> T {
+    pub fn meta(&self) -> Meta {
         match self {
-            Expr::Assign(a) => visitor.visit_assign_expr(a),
-            Expr::Binary(b) => visitor.visit_binary_expr(b),
-            Expr::Call(c) => visitor.visit_call_expr(c),
-            Expr::Grouping(g) => visitor.visit_grouping_expr(g),
-            Expr::Literal(l) => visitor.visit_literal_expr(l),
-            Expr::Logical(l) => visitor.visit_logical_expr(l),
-            Expr::Unary(u) => visitor.visit_unary_expr(u),
-            Expr::Variable(v) => visitor.visit_variable_expr(v),
+            Expr::Assign(node) => node.meta,
+            Expr::Await(node) => node.meta,
+            Expr::Binary(node) => node.meta,
+            Expr::Call(node) => node.meta,
+            Expr::Get(node) => node.meta,
+            Expr::Grouping(node) => node.meta,
+            Expr::Index(node) => node.meta,
+            Expr::IndexSet(node) => node.meta,
+            Expr::Lambda(node) => node.meta,
+            Expr::List(node) => node.meta,
+            Expr::Literal(node) => node.meta,
+            Expr::Logical(node) => node.meta,
+            Expr::Map(node) => node.meta,
+            Expr::Set(node) => node.meta,
+            Expr::Super(node) => node.meta,
+            Expr::Ternary(node) => node.meta,
+            Expr::This(node) => node.meta,
+            Expr::Unary(node) => node.meta,
+            Expr::Variable(node) => node.meta,
         }
     }
+
+    pub fn id(&self) -> NodeId {
+        self.meta().id
+    }
+
+    pub fn span(&self) -> Span {
+        self.meta().span
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Assign {
     pub name: Token,
     pub value: Box<Expr>,
+    pub meta: Meta,
+}
+
+#[derive(Debug, Clone)]
+pub struct Await {
+    pub keyword: Token,
+    pub expression: Box<Expr>,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
@@ -49,6 +83,7 @@ pub struct Binary {
     pub left: Box<Expr>,
     pub operator: Token,
     pub right: Box<Expr>,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
@@ -56,16 +91,65 @@ pub struct Call {
     pub callee: Box<Expr>,
     pub paren: Token,
     pub arguments: Vec<Expr>,
+    pub meta: Meta,
+}
+
+/// `object.name`, reading a property.
+#[derive(Debug, Clone)]
+pub struct Get {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
 pub struct Grouping {
     pub expression: Box<Expr>,
+    pub meta: Meta,
+}
+
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub meta: Meta,
+}
+
+/// `object[index] = value`, setting an element of a list or an entry of a
+/// map.
+#[derive(Debug, Clone)]
+pub struct IndexSet {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
+    pub meta: Meta,
+}
+
+/// `fun (params) { body }`, an anonymous function.
+#[derive(Debug, Clone)]
+pub struct Lambda {
+    pub keyword: Token,
+    pub params: Vec<Param>,
+    /// The type after `->`, if any.
+    pub return_annotation: Option<Token>,
+    pub body: Vec<Stmt>,
+    pub meta: Meta,
+}
+
+/// `[elements]`, a new list.
+#[derive(Debug, Clone)]
+pub struct List {
+    pub bracket: Token,
+    pub elements: Vec<Expr>,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
 pub struct Literal {
     pub value: LoxObject,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
@@ -73,15 +157,60 @@ pub struct Logical {
     pub left: Box<Expr>,
     pub operator: Token,
     pub right: Box<Expr>,
+    pub meta: Meta,
+}
+
+/// `{key: value, ...}`, a new map. A `{` starting a statement always begins
+/// a block, so a map there must be wrapped in parentheses.
+#[derive(Debug, Clone)]
+pub struct Map {
+    pub brace: Token,
+    pub entries: Vec<(Expr, Expr)>,
+    pub meta: Meta,
+}
+
+/// `object.name = value`, setting a field.
+#[derive(Debug, Clone)]
+pub struct Set {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub value: Box<Expr>,
+    pub meta: Meta,
+}
+
+/// `super.method`, a method of the superclass of the class it's written in.
+#[derive(Debug, Clone)]
+pub struct Super {
+    pub keyword: Token,
+    pub method: Token,
+    pub meta: Meta,
+}
+
+/// `condition ? then_branch : else_branch`.
+#[derive(Debug, Clone)]
+pub struct Ternary {
+    pub condition: Box<Expr>,
+    pub then_branch: Box<Expr>,
+    pub else_branch: Box<Expr>,
+    pub meta: Meta,
+}
+
+/// `this`, the instance a method was accessed on.
+#[derive(Debug, Clone)]
+pub struct This {
+    pub keyword: Token,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
 pub struct Unary {
     pub operator: Token,
     pub right: Box<Expr>,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
 pub struct Variable {
     pub name: Token,
+    pub meta: Meta,
 }
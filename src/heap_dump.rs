@@ -0,0 +1,260 @@
+use std::{collections::HashSet, fmt::Write, sync::Arc};
+
+use crate::object::{LoxObject, Object};
+
+struct Node {
+    id: usize,
+    kind: &'static str,
+    size: usize,
+    preview: String,
+}
+
+struct Edge {
+    from: Option<usize>,
+    to: usize,
+    label: String,
+}
+
+/// A snapshot of every object reachable from a set of named roots. Objects
+/// are identified by their address, so shared objects appear once.
+pub struct HeapGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+fn id(object: &LoxObject) -> usize {
+    Arc::as_ptr(object) as *const () as usize
+}
+
+/// Roughly how many bytes an object owns, counting its own allocation and
+/// any buffer it owns directly, but not the objects it refers to.
+fn size_of(object: &Object) -> usize {
+    let own = std::mem::size_of::<std::sync::RwLock<Object>>();
+    own + match object {
+        Object::String(s) => s.capacity(),
+        Object::Bytes(b) => b.capacity(),
+        Object::StringBuilder(s) => s.capacity(),
+        Object::List(values) => values.capacity() * std::mem::size_of::<LoxObject>(),
+        Object::Map(map) => map.len() * 2 * std::mem::size_of::<LoxObject>(),
+        // Persistent collections share their nodes, so count only the
+        // references each one holds.
+        Object::PersistentVector(values) => values.len() * std::mem::size_of::<LoxObject>(),
+        Object::PersistentMap(map) => map.len() * 2 * std::mem::size_of::<LoxObject>(),
+        Object::SortedMap(map) => map.len() * 2 * std::mem::size_of::<LoxObject>(),
+        Object::Heap(heap) => heap.items.capacity() * std::mem::size_of::<LoxObject>(),
+        Object::Deque(values) => values.capacity() * std::mem::size_of::<LoxObject>(),
+        Object::Instance(instance) => instance.fields.len() * 2 * std::mem::size_of::<LoxObject>(),
+        _ => 0,
+    }
+}
+
+/// The objects an object refers to, with a label for each reference.
+fn children(object: &Object) -> Vec<(String, LoxObject)> {
+    match object {
+        Object::List(values) => values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("[{}]", i), v.clone()))
+            .collect(),
+        Object::Map(map) => map
+            .iter()
+            .map(|(k, v)| (format!("[{}]", k.to_object().read().unwrap()), v.clone()))
+            .collect(),
+        Object::PersistentVector(values) => values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("[{}]", i), v.clone()))
+            .collect(),
+        Object::PersistentMap(map) => map
+            .iter()
+            .map(|(k, v)| (format!("[{}]", k.to_object().read().unwrap()), v.clone()))
+            .collect(),
+        Object::SortedMap(map) => map
+            .iter()
+            .map(|(k, v)| (format!("[{}]", k.to_object().read().unwrap()), v.clone()))
+            .collect(),
+        Object::Heap(heap) => heap
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("[{}]", i), v.clone()))
+            .chain(heap.less.iter().map(|l| (String::from("less"), l.clone())))
+            .collect(),
+        Object::Deque(values) => values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("[{}]", i), v.clone()))
+            .collect(),
+        Object::Task(Some(result)) => vec![(String::from("result"), result.clone())],
+        Object::Function(f) => f.captured(),
+        Object::Class(class) => class
+            .methods
+            .values()
+            .chain(class.class_methods.values())
+            .flat_map(|m| m.captured())
+            .collect(),
+        Object::Instance(instance) => instance
+            .fields
+            .iter()
+            .map(|(name, v)| (format!(".{}", name), v.clone()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+impl HeapGraph {
+    pub fn collect(roots: Vec<(String, LoxObject)>) -> Self {
+        let mut graph = HeapGraph {
+            nodes: vec![],
+            edges: vec![],
+        };
+        let mut seen = HashSet::new();
+        let mut pending = roots
+            .into_iter()
+            .map(|(name, object)| (None, name, object))
+            .collect::<Vec<_>>();
+
+        while let Some((from, label, object)) = pending.pop() {
+            let to = id(&object);
+            graph.edges.push(Edge { from, to, label });
+            if !seen.insert(to) {
+                continue;
+            }
+
+            let object = object.read().unwrap();
+            let mut preview = object.to_string();
+            if preview.chars().count() > 40 {
+                preview = preview.chars().take(37).collect::<String>() + "...";
+            }
+            graph.nodes.push(Node {
+                id: to,
+                kind: object.type_name(),
+                size: size_of(&object),
+                preview,
+            });
+            for (label, child) in children(&object).into_iter().rev() {
+                pending.push((Some(to), label, child));
+            }
+        }
+
+        graph
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n  \"objects\": [\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let _ = write!(
+                out,
+                "    {{\"id\": {}, \"type\": {}, \"size\": {}, \"value\": {}}}",
+                node.id,
+                json_string(node.kind),
+                node.size,
+                json_string(&node.preview)
+            );
+            out.push_str(if i + 1 < self.nodes.len() {
+                ",\n"
+            } else {
+                "\n"
+            });
+        }
+        out.push_str("  ],\n  \"references\": [\n");
+        for (i, edge) in self.edges.iter().enumerate() {
+            let from = edge
+                .from
+                .map(|f| f.to_string())
+                .unwrap_or_else(|| String::from("null"));
+            let _ = write!(
+                out,
+                "    {{\"from\": {}, \"to\": {}, \"label\": {}}}",
+                from,
+                edge.to,
+                json_string(&edge.label)
+            );
+            out.push_str(if i + 1 < self.edges.len() {
+                ",\n"
+            } else {
+                "\n"
+            });
+        }
+        out.push_str("  ]\n}\n");
+        out
+    }
+
+    /// Formats the graph for Graphviz. Roots hang off a single `roots` node.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph heap {\n  roots [shape=box];\n");
+        for node in &self.nodes {
+            let _ = writeln!(
+                out,
+                "  o{} [label={}];",
+                node.id,
+                json_string(&format!(
+                    "{} ({} bytes)\n{}",
+                    node.kind, node.size, node.preview
+                ))
+            );
+        }
+        for edge in &self.edges {
+            let from = edge
+                .from
+                .map(|f| format!("o{}", f))
+                .unwrap_or_else(|| String::from("roots"));
+            let _ = writeln!(
+                out,
+                "  {} -> o{} [label={}];",
+                from,
+                edge.to,
+                json_string(&edge.label)
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Writes the graph to `path`, as DOT if it ends in `.dot` and as JSON
+    /// otherwise.
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let contents = if path.ends_with(".dot") {
+            self.to_dot()
+        } else {
+            self.to_json()
+        };
+        std::fs::write(path, contents)
+    }
+}
+
+/// Quotes a string for JSON, which DOT also accepts for labels.
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_collections_that_contain_themselves() {
+        let list = Object::new_list(vec![Object::new_number(1.0)]);
+        let element = list.clone();
+        if let Object::List(values) = &mut *list.write().unwrap() {
+            values.push(element);
+        }
+        let graph = HeapGraph::collect(vec![(String::from("l"), list)]);
+        assert_eq!(graph.nodes[0].preview, "[1, [...]]");
+    }
+}
@@ -1,15 +1,44 @@
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_traits::{ToPrimitive, Zero};
+use rust_decimal::{Decimal, MathematicalOps};
+
 use std::{
-    sync::{Arc, RwLock},
-    time::SystemTime,
+    collections::HashMap,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
 };
 
 use crate::{
+    counting_loop::CountingLoop,
+    crash_report::Trace,
     environment::Environment,
     expr::{self, Expr},
+    node::NodeId,
+    object::LoxClass,
+    object::LoxFunction,
+    object::LoxMap,
     object::LoxObject,
+    object::MapKey,
     object::Object,
-    runtime_error::RuntimeError,
+    replay::Recorder,
+    resolver::Resolver,
+    runtime_error::{RuntimeError, Unwind},
+    sandbox::{self, Limits},
+    stdlib::{
+        self,
+        log::Logger,
+        random::Rng,
+        temp::TempPaths,
+        timer::{self, TimerQueue},
+        Clock,
+    },
     stmt,
+    token::Lexeme,
     token::Token,
     token::TokenKind,
 };
@@ -40,40 +69,522 @@ fn check_number_operands(
     }
 }
 
+/// Both operands as bigints, if either of them is one.
+fn bigint_operands(
+    left: &LoxObject,
+    operator: &Token,
+    right: &LoxObject,
+) -> Result<Option<(BigInt, BigInt)>, RuntimeError> {
+    let (left, right) = (left.read().unwrap(), right.read().unwrap());
+    if !left.is_bigint() && !right.is_bigint() {
+        return Ok(None);
+    }
+    match (left.as_bigint(), right.as_bigint()) {
+        (Some(left), Some(right)) => Ok(Some((left, right))),
+        _ => Err(RuntimeError::new(
+            operator.clone(),
+            String::from("Operands must be bigints or integers."),
+        )),
+    }
+}
+
+fn bigint_binary(operator: &Token, left: BigInt, right: BigInt) -> Result<LoxObject, RuntimeError> {
+    Ok(match operator.kind {
+        TokenKind::Minus => Object::new_bigint(left - right),
+        TokenKind::Plus => Object::new_bigint(left + right),
+        TokenKind::Star => Object::new_bigint(left * right),
+        // Division truncates, as integer division does in Rust.
+        TokenKind::Slash if right.is_zero() => {
+            return Err(RuntimeError::new(
+                operator.clone(),
+                String::from("Division by zero."),
+            ))
+        }
+        TokenKind::Slash => Object::new_bigint(left / right),
+        TokenKind::StarStar => match right.to_u32() {
+            Some(exponent) => Object::new_bigint(left.pow(exponent)),
+            None => {
+                return Err(RuntimeError::new(
+                    operator.clone(),
+                    String::from("Bigint exponent must be a non-negative integer."),
+                ))
+            }
+        },
+        TokenKind::Greater => Object::new_bool(left > right),
+        TokenKind::GreaterEqual => Object::new_bool(left >= right),
+        TokenKind::Less => Object::new_bool(left < right),
+        TokenKind::LessEqual => Object::new_bool(left <= right),
+        _ => unreachable!(),
+    })
+}
+
+/// Both operands as complex numbers, if either of them is one.
+fn complex_operands(
+    left: &LoxObject,
+    operator: &Token,
+    right: &LoxObject,
+) -> Result<Option<(Complex64, Complex64)>, RuntimeError> {
+    let (left, right) = (left.read().unwrap(), right.read().unwrap());
+    if !left.is_complex() && !right.is_complex() {
+        return Ok(None);
+    }
+    match (left.as_complex(), right.as_complex()) {
+        (Some(left), Some(right)) => Ok(Some((left, right))),
+        _ => Err(RuntimeError::new(
+            operator.clone(),
+            String::from("Operands must be complex numbers or numbers."),
+        )),
+    }
+}
+
+fn complex_binary(
+    operator: &Token,
+    left: Complex64,
+    right: Complex64,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_complex(match operator.kind {
+        TokenKind::Minus => left - right,
+        TokenKind::Plus => left + right,
+        TokenKind::Star => left * right,
+        TokenKind::Slash => left / right,
+        // Exact for integer powers, where `powc` would leave `i ** 2` a hair
+        // off -1.
+        TokenKind::StarStar if right.im == 0.0 && right.re.fract() == 0.0 => {
+            left.powi(right.re as i32)
+        }
+        TokenKind::StarStar => left.powc(right),
+        _ => {
+            return Err(RuntimeError::new(
+                operator.clone(),
+                String::from("Complex numbers can't be ordered."),
+            ))
+        }
+    }))
+}
+
+/// Both operands as decimals, if either of them is one.
+fn decimal_operands(
+    left: &LoxObject,
+    operator: &Token,
+    right: &LoxObject,
+) -> Result<Option<(Decimal, Decimal)>, RuntimeError> {
+    let (left, right) = (left.read().unwrap(), right.read().unwrap());
+    if !left.is_decimal() && !right.is_decimal() {
+        return Ok(None);
+    }
+    match (left.as_decimal(), right.as_decimal()) {
+        (Some(left), Some(right)) => Ok(Some((left, right))),
+        _ => Err(RuntimeError::new(
+            operator.clone(),
+            String::from("Operands must be decimals or integers."),
+        )),
+    }
+}
+
+fn decimal_binary(
+    operator: &Token,
+    left: Decimal,
+    right: Decimal,
+) -> Result<LoxObject, RuntimeError> {
+    let result = match operator.kind {
+        TokenKind::Minus => left.checked_sub(right),
+        TokenKind::Plus => left.checked_add(right),
+        TokenKind::Star => left.checked_mul(right),
+        TokenKind::Slash if right.is_zero() => {
+            return Err(RuntimeError::new(
+                operator.clone(),
+                String::from("Division by zero."),
+            ))
+        }
+        TokenKind::Slash => left.checked_div(right),
+        TokenKind::StarStar => left.checked_powd(right),
+        TokenKind::Greater => return Ok(Object::new_bool(left > right)),
+        TokenKind::GreaterEqual => return Ok(Object::new_bool(left >= right)),
+        TokenKind::Less => return Ok(Object::new_bool(left < right)),
+        TokenKind::LessEqual => return Ok(Object::new_bool(left <= right)),
+        _ => unreachable!(),
+    };
+    result
+        .map(Object::new_decimal)
+        .ok_or_else(|| RuntimeError::new(operator.clone(), String::from("Decimal overflow.")))
+}
+
+/// Applies a binary operator to operands already evaluated.
+fn binary(operator: &Token, left: LoxObject, right: LoxObject) -> Result<LoxObject, RuntimeError> {
+    if operator.kind == TokenKind::Comma {
+        return Ok(right);
+    }
+
+    let equality = matches!(operator.kind, TokenKind::EqualEqual | TokenKind::BangEqual);
+    if !equality {
+        if let Some((left, right)) = complex_operands(&left, operator, &right)? {
+            return complex_binary(operator, left, right);
+        }
+        if let Some((left, right)) = decimal_operands(&left, operator, &right)? {
+            return decimal_binary(operator, left, right);
+        }
+        if let Some((left, right)) = bigint_operands(&left, operator, &right)? {
+            return bigint_binary(operator, left, right);
+        }
+    }
+
+    Ok(match operator.kind {
+        TokenKind::Minus => {
+            check_number_operands(left.clone(), operator, right.clone())?;
+            Object::new_number(left.read().unwrap().as_number() - right.read().unwrap().as_number())
+        }
+        TokenKind::Slash => {
+            check_number_operands(left.clone(), operator, right.clone())?;
+            Object::new_number(left.read().unwrap().as_number() / right.read().unwrap().as_number())
+        }
+        TokenKind::Star => {
+            check_number_operands(left.clone(), operator, right.clone())?;
+            Object::new_number(left.read().unwrap().as_number() * right.read().unwrap().as_number())
+        }
+        TokenKind::StarStar => {
+            check_number_operands(left.clone(), operator, right.clone())?;
+            Object::new_number(
+                left.read()
+                    .unwrap()
+                    .as_number()
+                    .powf(right.read().unwrap().as_number()),
+            )
+        }
+        TokenKind::Plus => {
+            if left.read().unwrap().is_number() && right.read().unwrap().is_number() {
+                Object::new_number(
+                    left.read().unwrap().as_number() + right.read().unwrap().as_number(),
+                )
+            } else if left.read().unwrap().is_string() && right.read().unwrap().is_string() {
+                Object::new_string(
+                    left.read().unwrap().to_string() + right.read().unwrap().as_string().as_ref(),
+                )
+            } else if let (Object::Bytes(l), Object::Bytes(r)) =
+                (&*left.read().unwrap(), &*right.read().unwrap())
+            {
+                Object::new_bytes([l.as_slice(), r.as_slice()].concat())
+            } else {
+                return Err(RuntimeError::new(
+                    operator.clone(),
+                    String::from("Operands must be two numbers, two strings or two bytes."),
+                ));
+            }
+        }
+        TokenKind::Greater => {
+            check_number_operands(left.clone(), operator, right.clone())?;
+            Object::new_bool(left.read().unwrap().as_number() > right.read().unwrap().as_number())
+        }
+        TokenKind::GreaterEqual => {
+            check_number_operands(left.clone(), operator, right.clone())?;
+            Object::new_bool(left.read().unwrap().as_number() >= right.read().unwrap().as_number())
+        }
+        TokenKind::Less => {
+            check_number_operands(left.clone(), operator, right.clone())?;
+            Object::new_bool(left.read().unwrap().as_number() < right.read().unwrap().as_number())
+        }
+        TokenKind::LessEqual => {
+            check_number_operands(left.clone(), operator, right.clone())?;
+            Object::new_bool(left.read().unwrap().as_number() <= right.read().unwrap().as_number())
+        }
+        TokenKind::EqualEqual => Object::new_bool(left.read().unwrap().eq(&right.read().unwrap())),
+        TokenKind::BangEqual => Object::new_bool(left.read().unwrap().ne(&right.read().unwrap())),
+        _ => unreachable!(),
+    })
+}
+
+/// Checks that `index` is an integer within `0..len`, and returns it as one.
+pub fn check_index(bracket: &Token, index: &LoxObject, len: usize) -> Result<usize, RuntimeError> {
+    check_in_range(bracket, index_number(bracket, index)?, len)
+}
+
+/// Checks that `index` is an integer, without knowing what it indexes.
+fn index_number(bracket: &Token, index: &LoxObject) -> Result<f64, RuntimeError> {
+    match &*index.read().unwrap() {
+        Object::Number(i) if i.fract() != 0.0 => Err(RuntimeError::new(
+            bracket.clone(),
+            String::from("Index must be an integer."),
+        )),
+        Object::Number(i) => Ok(*i),
+        _ => Err(RuntimeError::new(
+            bracket.clone(),
+            String::from("Index must be a number."),
+        )),
+    }
+}
+
+fn check_in_range(bracket: &Token, i: f64, len: usize) -> Result<usize, RuntimeError> {
+    if i >= 0.0 && (i as usize) < len {
+        Ok(i as usize)
+    } else {
+        Err(RuntimeError::new(
+            bracket.clone(),
+            String::from("Index out of range."),
+        ))
+    }
+}
+
+/// Checks that `key` can be used as a map key, and converts it to one.
+pub fn check_key(bracket: &Token, key: &LoxObject) -> Result<MapKey, RuntimeError> {
+    MapKey::from_object(&key.read().unwrap()).ok_or_else(|| {
+        RuntimeError::new(
+            bracket.clone(),
+            String::from("Map keys must be nil, booleans, numbers or strings."),
+        )
+    })
+}
+
+/// Somewhere the interpreter writes output, shared with forked interpreters.
+pub type Sink = Arc<Mutex<dyn Write + Send>>;
+
+/// Asks a running script to stop from another thread, such as a timeout's or
+/// a Ctrl-C handler's. The interpreter checks for it before each loop
+/// iteration and call, and stops with a runtime error naming the reason and
+/// where it was. Natives which block wait with `sleep`, which the interrupt
+/// cuts short.
+#[derive(Debug, Clone)]
+pub struct Interrupt {
+    raised: Arc<AtomicBool>,
+    reason: Arc<Mutex<&'static str>>,
+    /// Notified when the interrupt is raised, to wake `sleep`.
+    wake: Arc<Condvar>,
+}
+
+impl Interrupt {
+    fn new() -> Self {
+        Self {
+            raised: Arc::new(AtomicBool::new(false)),
+            reason: Arc::new(Mutex::new("")),
+            wake: Arc::new(Condvar::new()),
+        }
+    }
+
+    pub fn raise(&self, reason: &'static str) {
+        // Set under the lock `sleep` waits with, so it can't miss the wake.
+        *self.reason.lock().unwrap() = reason;
+        self.raised.store(true, Ordering::SeqCst);
+        self.wake.notify_all();
+    }
+
+    pub fn clear(&self) {
+        self.raised.store(false, Ordering::SeqCst);
+    }
+
+    /// The reason given if the interrupt has been raised.
+    pub fn raised(&self) -> Option<&'static str> {
+        if self.raised.load(Ordering::Relaxed) {
+            Some(*self.reason.lock().unwrap())
+        } else {
+            None
+        }
+    }
+
+    /// Waits for `duration`, or until the interrupt is raised if that's
+    /// sooner.
+    pub fn sleep(&self, duration: Duration) {
+        let deadline = Instant::now().checked_add(duration);
+        let mut reason = self.reason.lock().unwrap();
+        while !self.raised.load(Ordering::SeqCst) {
+            let now = Instant::now();
+            reason = match deadline {
+                Some(deadline) if deadline <= now => break,
+                Some(deadline) => self.wake.wait_timeout(reason, deadline - now).unwrap().0,
+                None => self.wake.wait(reason).unwrap(),
+            };
+        }
+    }
+}
+
 pub struct Interpreter {
     pub globals: Arc<RwLock<Environment>>,
     environment: Arc<RwLock<Environment>>,
+    /// How many environments out from the current one each reference to a
+    /// local variable finds it, by the id of the `Variable` or `Assign`
+    /// expression. Filled in by the resolver; references it leaves out are
+    /// to globals. Shared with forks, which may run functions from any line.
+    locals: Arc<RwLock<HashMap<NodeId, usize>>>,
+    pub timers: TimerQueue,
+    pub stdout: Sink,
+    pub stderr: Sink,
+    pub logger: Logger,
+    pub recorder: Arc<Mutex<Recorder>>,
+    pub interrupt: Interrupt,
+    pub temp_paths: TempPaths,
+    pub clock: Clock,
+    pub rng: Rng,
+    /// The functions being called, outermost first, and the lines they were
+    /// called from.
+    frames: Vec<(LoxObject, usize)>,
+    pub limits: Limits,
+    /// Whether calls check arguments and return values against the types
+    /// their function is annotated with.
+    pub check_contracts: bool,
+    /// Loop iterations and calls so far, for `limits.steps`.
+    steps: u64,
+    /// The statements executed last, for a crash report.
+    pub trace: Trace,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
         let globals = Arc::new(RwLock::new(Environment::new()));
 
-        globals.write().unwrap().define(
-            "clock",
-            Object::new_builtin_function(0, |_args| {
-                Object::new_number(
-                    SystemTime::now()
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs_f64(),
-                )
-            }),
-        );
+        stdlib::define_natives(&mut globals.write().unwrap());
 
         Self {
             globals: globals.clone(),
             environment: globals,
+            locals: Arc::new(RwLock::new(HashMap::new())),
+            timers: TimerQueue::new(),
+            stdout: Arc::new(Mutex::new(std::io::stdout())),
+            stderr: Arc::new(Mutex::new(std::io::stderr())),
+            logger: Logger::new(),
+            recorder: Arc::new(Mutex::new(Recorder::Live)),
+            interrupt: Interrupt::new(),
+            temp_paths: TempPaths::default(),
+            clock: Clock::default(),
+            rng: Rng::default(),
+            frames: vec![],
+            limits: Limits::default(),
+            check_contracts: false,
+            steps: 0,
+            trace: Trace::new(),
         }
     }
 
-    pub fn interpret(&mut self, statements: &[stmt::Stmt]) {
-        if let Some(e) = statements.iter().find_map(|s| self.execute(s).err()) {
+    /// Creates an interpreter for another thread which shares this
+    /// interpreter's globals, but starts out at global scope with no timers.
+    pub fn fork(&self) -> Self {
+        Self {
+            globals: self.globals.clone(),
+            environment: self.globals.clone(),
+            locals: self.locals.clone(),
+            timers: TimerQueue::new(),
+            stdout: self.stdout.clone(),
+            stderr: self.stderr.clone(),
+            logger: self.logger.clone(),
+            recorder: self.recorder.clone(),
+            interrupt: self.interrupt.clone(),
+            temp_paths: self.temp_paths.clone(),
+            clock: self.clock.clone(),
+            rng: self.rng.clone(),
+            frames: vec![],
+            limits: self.limits.clone(),
+            check_contracts: self.check_contracts,
+            steps: 0,
+            trace: self.trace.clone(),
+        }
+    }
+
+    /// Runs a script, reporting any runtime error. If its last statement is
+    /// an expression statement, returns that expression's value, for the REPL
+    /// to show.
+    pub fn interpret(&mut self, statements: &[stmt::Stmt]) -> Option<LoxObject> {
+        self.run(statements).unwrap_or_else(|e| {
             crate::runtime_error(e);
+            None
+        })
+    }
+
+    /// Like `interpret`, but returns a runtime error instead of reporting it.
+    pub fn run(&mut self, statements: &[stmt::Stmt]) -> Result<Option<LoxObject>, RuntimeError> {
+        Resolver::resolve(self, statements);
+        statements.split_last().map_or(Ok(None), |(last, rest)| {
+            rest.iter()
+                .try_for_each(|s| self.execute(s))
+                .map_err(Unwind::into_error)?;
+            match last {
+                stmt::Stmt::Expression(e) => self.evaluate(&e.expression).map(Some),
+                _ => self.execute(last).map(|_| None).map_err(Unwind::into_error),
+            }
+        })
+    }
+
+    /// Records that the reference `id` is to a local variable `depth`
+    /// environments out from the one it runs in.
+    pub fn resolve(&mut self, id: NodeId, depth: usize) {
+        self.locals.write().unwrap().insert(id, depth);
+    }
+
+    /// The value of a property `name` found as `value`: what it returns if
+    /// it's a getter, or else `value` itself.
+    fn run_getter(&mut self, value: LoxObject, name: &Token) -> Result<LoxObject, RuntimeError> {
+        let getter = match &*value.read().unwrap() {
+            Object::Function(f) if f.is_getter => Some(f.clone()),
+            _ => None,
+        };
+        match getter {
+            Some(getter) => getter.call(self, name, vec![]),
+            None => Ok(value),
+        }
+    }
+
+    fn look_up_variable(&self, id: NodeId, name: &Token) -> Result<LoxObject, RuntimeError> {
+        match self.locals.read().unwrap().get(&id) {
+            Some(&distance) => self.environment.read().unwrap().get_at(distance, name),
+            None => self.globals.read().unwrap().get(name),
         }
     }
 
-    fn execute(&mut self, stmt: &stmt::Stmt) -> Result<(), RuntimeError> {
+    /// Every variable visible from the current scope, innermost first. These
+    /// are the roots for a heap dump.
+    pub fn roots(&self) -> Vec<(String, LoxObject)> {
+        let mut roots = vec![];
+        let mut environment = Some(self.environment.clone());
+        while let Some(env) = environment {
+            let env = env.read().unwrap();
+            roots.extend(
+                env.values()
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            );
+            environment = env.enclosing();
+        }
+        roots
+    }
+
+    /// Called before each loop iteration and call, and by natives that wait
+    /// or run callbacks, where a script can be stopped if it has been
+    /// interrupted or has gone over its limits.
+    pub fn step(&mut self, token: &Token) -> Result<(), RuntimeError> {
+        self.steps += 1;
+        let reason = self.interrupt.raised().or_else(|| {
+            if self.limits.steps.is_some_and(|max| self.steps > max) {
+                Some("step limit")
+            } else if self
+                .limits
+                .memory
+                .is_some_and(|max| sandbox::allocated() > max)
+            {
+                Some("memory limit")
+            } else {
+                None
+            }
+        });
+        match reason {
+            Some(reason) => Err(RuntimeError::new(
+                token.clone(),
+                format!("Interrupted ({}).{}", reason, self.stack_trace()),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// A line for each call in progress, innermost first.
+    fn stack_trace(&self) -> String {
+        self.frames
+            .iter()
+            .rev()
+            .map(|(callee, line)| {
+                format!(
+                    "\n    in {} called from line {}",
+                    callee.read().unwrap(),
+                    line
+                )
+            })
+            .collect()
+    }
+
+    fn execute(&mut self, stmt: &stmt::Stmt) -> Result<(), Unwind> {
+        self.trace.record(stmt.meta().span);
         stmt.accept(self)
     }
 
@@ -81,7 +592,7 @@ impl Interpreter {
         &mut self,
         statements: &[stmt::Stmt],
         environment: Environment,
-    ) -> Result<(), RuntimeError> {
+    ) -> Result<(), Unwind> {
         let previous = self.environment.clone();
 
         self.environment = Arc::new(RwLock::new(environment));
@@ -99,20 +610,81 @@ impl Interpreter {
     fn evaluate(&mut self, expr: &Expr) -> Result<LoxObject, RuntimeError> {
         expr.accept(self)
     }
+
+    /// Runs one iteration of a loop's body, returning whether the loop goes
+    /// on.
+    fn run_loop_body(&mut self, body: &stmt::Stmt) -> Result<bool, Unwind> {
+        match self.execute(body) {
+            Ok(()) | Err(Unwind::Continue) => Ok(true),
+            Err(Unwind::Break) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Runs a counting `for` loop in the current environment, with the same
+    /// results and errors as running it as written.
+    fn run_counting_loop(&mut self, counting: &CountingLoop) -> Result<(), Unwind> {
+        let mut i = counting.start;
+        loop {
+            self.environment
+                .write()
+                .unwrap()
+                .define(&counting.name.lexeme, Object::new_number(i));
+
+            let end = self.evaluate(counting.end)?;
+            let number = match *end.read().unwrap() {
+                Object::Number(end) => Some(end),
+                _ => None,
+            };
+            let goes_on = match number {
+                Some(end) => counting.compare(i, end),
+                // Bigints, decimals and anything else compare as the
+                // condition as written would.
+                None => binary(counting.operator, Object::new_number(i), end)?
+                    .read()
+                    .unwrap()
+                    .as_bool(),
+            };
+            if !goes_on {
+                return Ok(());
+            }
+
+            self.step(counting.keyword)?;
+            if !self.run_loop_body(counting.body)? {
+                return Ok(());
+            }
+            i += counting.step;
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl stmt::Visitor<Result<(), RuntimeError>> for Interpreter {
-    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) -> Result<(), RuntimeError> {
-        self.evaluate(&stmt.expression).map(|_| ())
+impl stmt::Visitor<Result<(), Unwind>> for Interpreter {
+    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) -> Result<(), Unwind> {
+        self.evaluate(&stmt.expression)?;
+        Ok(())
     }
 
-    fn visit_print_stmt(&mut self, stmt: &stmt::Print) -> Result<(), RuntimeError> {
+    fn visit_print_stmt(&mut self, stmt: &stmt::Print) -> Result<(), Unwind> {
         let value = self.evaluate(&stmt.expression)?;
-        println!("{}", value.read().unwrap());
+        let _ = writeln!(self.stdout.lock().unwrap(), "{}", value.read().unwrap());
         Ok(())
     }
 
-    fn visit_var_stmt(&mut self, stmt: &stmt::Var) -> Result<(), RuntimeError> {
+    fn visit_return_stmt(&mut self, stmt: &stmt::Return) -> Result<(), Unwind> {
+        let value = match &stmt.value {
+            Some(value) => self.evaluate(value)?,
+            None => Object::nil(),
+        };
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) -> Result<(), Unwind> {
         let value = if let Some(initializer) = &stmt.initializer {
             Some(self.evaluate(initializer)?)
         } else {
@@ -125,14 +697,21 @@ impl stmt::Visitor<Result<(), RuntimeError>> for Interpreter {
         Ok(())
     }
 
-    fn visit_block_stmt(&mut self, stmt: &stmt::Block) -> Result<(), RuntimeError> {
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) -> Result<(), Unwind> {
+        if let Some(counting) = CountingLoop::find(&stmt.statements) {
+            let previous = self.environment.clone();
+            self.environment = Arc::new(RwLock::new(Environment::new_enclosed(previous.clone())));
+            let result = self.run_counting_loop(&counting);
+            self.environment = previous;
+            return result;
+        }
         self.execute_block(
             &stmt.statements,
             Environment::new_enclosed(self.environment.clone()),
         )
     }
 
-    fn visit_if_stmt(&mut self, stmt: &stmt::If) -> Result<(), RuntimeError> {
+    fn visit_if_stmt(&mut self, stmt: &stmt::If) -> Result<(), Unwind> {
         if self.evaluate(&stmt.condition)?.read().unwrap().as_bool() {
             self.execute(&stmt.then_branch)?;
         } else if let Some(else_branch) = &stmt.else_branch {
@@ -141,15 +720,80 @@ impl stmt::Visitor<Result<(), RuntimeError>> for Interpreter {
         Ok(())
     }
 
-    fn visit_while_stmt(&mut self, stmt: &stmt::While) -> Result<(), RuntimeError> {
+    fn visit_while_stmt(&mut self, stmt: &stmt::While) -> Result<(), Unwind> {
         while self.evaluate(&stmt.condition)?.read().unwrap().as_bool() {
-            self.execute(&stmt.body)?;
+            self.step(&stmt.keyword)?;
+            if !self.run_loop_body(&stmt.body)? {
+                break;
+            }
+            if let Some(increment) = &stmt.increment {
+                self.evaluate(increment)?;
+            }
         }
         Ok(())
     }
 
-    fn visit_function_stmt(&mut self, stmt: &stmt::Function) -> Result<(), RuntimeError> {
-        let function = Object::new_function(stmt.clone());
+    fn visit_break_stmt(&mut self, _stmt: &stmt::Break) -> Result<(), Unwind> {
+        Err(Unwind::Break)
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &stmt::Continue) -> Result<(), Unwind> {
+        Err(Unwind::Continue)
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) -> Result<(), Unwind> {
+        let superclass = match &stmt.superclass {
+            Some(superclass) => {
+                let value = self.look_up_variable(superclass.meta.id, &superclass.name)?;
+                let class = match &*value.read().unwrap() {
+                    Object::Class(class) => class.clone(),
+                    _ => {
+                        return Err(RuntimeError::new(
+                            superclass.name.clone(),
+                            String::from("Superclass must be a class."),
+                        )
+                        .into())
+                    }
+                };
+                Some((class, value))
+            }
+            None => None,
+        };
+
+        // Methods of a subclass close over an environment holding its
+        // superclass, where `super` finds it.
+        let closure = match &superclass {
+            Some((_, value)) => {
+                let mut environment = Environment::new_enclosed(self.environment.clone());
+                environment.define("super", value.clone());
+                Arc::new(RwLock::new(environment))
+            }
+            None => self.environment.clone(),
+        };
+        let methods = |methods: &[stmt::Function]| {
+            methods
+                .iter()
+                .map(|m| {
+                    let method = LoxFunction::new(m, closure.clone());
+                    (m.name.lexeme.to_string(), method)
+                })
+                .collect()
+        };
+        let class = Object::new_class(LoxClass {
+            name: stmt.name.clone(),
+            superclass: superclass.map(|(class, _)| class),
+            methods: methods(&stmt.methods),
+            class_methods: methods(&stmt.class_methods),
+        });
+        self.environment
+            .write()
+            .unwrap()
+            .define(&stmt.name.lexeme, class);
+        Ok(())
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) -> Result<(), Unwind> {
+        let function = Object::new_function(LoxFunction::new(stmt, self.environment.clone()));
         self.environment
             .write()
             .unwrap()
@@ -162,79 +806,168 @@ impl expr::Visitor<Result<LoxObject, RuntimeError>> for Interpreter {
     fn visit_binary_expr(&mut self, expr: &expr::Binary) -> Result<LoxObject, RuntimeError> {
         let left = self.evaluate(&expr.left)?;
         let right = self.evaluate(&expr.right)?;
+        binary(&expr.operator, left, right)
+    }
 
-        Ok(match expr.operator.kind {
-            TokenKind::Minus => {
-                check_number_operands(left.clone(), &expr.operator, right.clone())?;
-                Object::new_number(
-                    left.read().unwrap().as_number() - right.read().unwrap().as_number(),
-                )
+    fn visit_get_expr(&mut self, expr: &expr::Get) -> Result<LoxObject, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        let value = match &*object.read().unwrap() {
+            Object::Instance(instance) => instance.get(&expr.name, &object),
+            Object::Class(class) => class.get(&expr.name, &object),
+            _ => Err(RuntimeError::new(
+                expr.name.clone(),
+                String::from("Only instances and classes have properties."),
+            )),
+        }?;
+        self.run_getter(value, &expr.name)
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) -> Result<LoxObject, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        if !matches!(*object.read().unwrap(), Object::Instance(_)) {
+            return Err(RuntimeError::new(
+                expr.name.clone(),
+                String::from("Only instances have fields."),
+            ));
+        }
+
+        let value = self.evaluate(&expr.value)?;
+        if let Object::Instance(instance) = &mut *object.write().unwrap() {
+            instance.set(&expr.name, value.clone());
+        }
+        Ok(value)
+    }
+
+    fn visit_super_expr(&mut self, expr: &expr::Super) -> Result<LoxObject, RuntimeError> {
+        let distance = self.locals.read().unwrap()[&expr.meta.id];
+        let environment = self.environment.read().unwrap();
+        let superclass = environment.get_at(distance, &expr.keyword)?;
+        // `this` is bound in the environment just inside the one holding
+        // `super`.
+        let this = Token {
+            kind: TokenKind::This,
+            lexeme: Lexeme::from("this"),
+            ..expr.keyword.clone()
+        };
+        let this = environment.get_at(distance - 1, &this)?;
+        drop(environment);
+        // In a class method, `this` is the class.
+        let is_class = matches!(*this.read().unwrap(), Object::Class(_));
+        let method = match &*superclass.read().unwrap() {
+            Object::Class(superclass) if is_class => {
+                superclass.find_class_method(&expr.method.lexeme).cloned()
             }
-            TokenKind::Slash => {
-                check_number_operands(left.clone(), &expr.operator, right.clone())?;
-                Object::new_number(
-                    left.read().unwrap().as_number() / right.read().unwrap().as_number(),
-                )
+            Object::Class(superclass) => superclass.find_method(&expr.method.lexeme).cloned(),
+            _ => unreachable!(),
+        };
+        match method {
+            Some(method) => {
+                let method = Object::new_function(method.bind(this));
+                self.run_getter(method, &expr.method)
             }
-            TokenKind::Star => {
-                check_number_operands(left.clone(), &expr.operator, right.clone())?;
-                Object::new_number(
-                    left.read().unwrap().as_number() * right.read().unwrap().as_number(),
-                )
+            None => Err(RuntimeError::new(
+                expr.method.clone(),
+                format!("Undefined property '{}'.", expr.method.lexeme),
+            )),
+        }
+    }
+
+    fn visit_this_expr(&mut self, expr: &expr::This) -> Result<LoxObject, RuntimeError> {
+        self.look_up_variable(expr.meta.id, &expr.keyword)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) -> Result<LoxObject, RuntimeError> {
+        self.evaluate(&expr.expression)
+    }
+
+    fn visit_index_expr(&mut self, expr: &expr::Index) -> Result<LoxObject, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+
+        let result = match &*object.read().unwrap() {
+            Object::Bytes(b) => {
+                let i = check_index(&expr.bracket, &index, b.len())?;
+                Object::new_number(b[i] as f64)
             }
-            TokenKind::Plus => {
-                if left.read().unwrap().is_number() && right.read().unwrap().is_number() {
-                    Object::new_number(
-                        left.read().unwrap().as_number() + right.read().unwrap().as_number(),
-                    )
-                } else if left.read().unwrap().is_string() && right.read().unwrap().is_string() {
-                    Object::new_string(
-                        left.read().unwrap().to_string()
-                            + right.read().unwrap().as_string().as_ref(),
-                    )
-                } else {
-                    return Err(RuntimeError::new(
-                        expr.operator.clone(),
-                        String::from("Operands must be two numbers or two strings."),
-                    ));
-                }
+            Object::List(values) => {
+                let i = check_index(&expr.bracket, &index, values.len())?;
+                values[i].clone()
             }
-            TokenKind::Greater => {
-                check_number_operands(left.clone(), &expr.operator, right.clone())?;
-                Object::new_bool(
-                    left.read().unwrap().as_number() > right.read().unwrap().as_number(),
-                )
+            Object::Map(map) => {
+                let key = check_key(&expr.bracket, &index)?;
+                map.get(&key).cloned().unwrap_or_else(Object::nil)
             }
-            TokenKind::GreaterEqual => {
-                check_number_operands(left.clone(), &expr.operator, right.clone())?;
-                Object::new_bool(
-                    left.read().unwrap().as_number() >= right.read().unwrap().as_number(),
-                )
+            Object::PersistentVector(values) => {
+                let i = check_index(&expr.bracket, &index, values.len())?;
+                values[i].clone()
             }
-            TokenKind::Less => {
-                check_number_operands(left.clone(), &expr.operator, right.clone())?;
-                Object::new_bool(
-                    left.read().unwrap().as_number() < right.read().unwrap().as_number(),
-                )
+            Object::PersistentMap(map) => {
+                let key = check_key(&expr.bracket, &index)?;
+                map.get(&key).cloned().unwrap_or_else(Object::nil)
             }
-            TokenKind::LessEqual => {
-                check_number_operands(left.clone(), &expr.operator, right.clone())?;
-                Object::new_bool(
-                    left.read().unwrap().as_number() <= right.read().unwrap().as_number(),
-                )
+            Object::SortedMap(map) => {
+                let key = check_key(&expr.bracket, &index)?;
+                map.get(&key).cloned().unwrap_or_else(Object::nil)
             }
-            TokenKind::EqualEqual => {
-                Object::new_bool(left.read().unwrap().eq(&right.read().unwrap()))
+            Object::Deque(values) => {
+                let i = check_index(&expr.bracket, &index, values.len())?;
+                values[i].clone()
             }
-            TokenKind::BangEqual => {
-                Object::new_bool(left.read().unwrap().eq(&right.read().unwrap()))
+            _ => {
+                return Err(RuntimeError::new(
+                    expr.bracket.clone(),
+                    String::from("Can only index bytes, lists and maps."),
+                ))
             }
-            _ => unreachable!(),
-        })
+        };
+        Ok(result)
     }
 
-    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) -> Result<LoxObject, RuntimeError> {
-        self.evaluate(&expr.expression)
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) -> Result<LoxObject, RuntimeError> {
+        let object = self.evaluate(&expr.object)?;
+        let index = self.evaluate(&expr.index)?;
+        let value = self.evaluate(&expr.value)?;
+        // Read before locking `object` for writing, since `index` may be it.
+        let position = index_number(&expr.bracket, &index);
+        let key = check_key(&expr.bracket, &index);
+
+        match &mut *object.write().unwrap() {
+            Object::List(values) => {
+                let i = check_in_range(&expr.bracket, position?, values.len())?;
+                values[i] = value.clone();
+            }
+            Object::Map(map) => map.insert(key?, value.clone()),
+            Object::SortedMap(map) => {
+                map.insert(key?, value.clone());
+            }
+            Object::Deque(values) => {
+                let i = check_in_range(&expr.bracket, position?, values.len())?;
+                values[i] = value.clone();
+            }
+            _ => {
+                return Err(RuntimeError::new(
+                    expr.bracket.clone(),
+                    String::from("Can only assign into lists and maps."),
+                ))
+            }
+        }
+        Ok(value)
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) -> Result<LoxObject, RuntimeError> {
+        Ok(Object::new_function(LoxFunction::lambda(
+            expr,
+            self.environment.clone(),
+        )))
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) -> Result<LoxObject, RuntimeError> {
+        let elements = expr
+            .elements
+            .iter()
+            .map(|e| self.evaluate(e))
+            .collect::<Result<_, _>>()?;
+        Ok(Object::new_list(elements))
     }
 
     fn visit_literal_expr(&mut self, expr: &expr::Literal) -> Result<LoxObject, RuntimeError> {
@@ -246,6 +979,15 @@ impl expr::Visitor<Result<LoxObject, RuntimeError>> for Interpreter {
 
         Ok(match expr.operator.kind {
             TokenKind::Bang => Object::new_bool(!right.read().unwrap().as_bool()),
+            TokenKind::Minus if right.read().unwrap().is_bigint() => {
+                Object::new_bigint(-right.read().unwrap().as_bigint().unwrap())
+            }
+            TokenKind::Minus if right.read().unwrap().is_complex() => {
+                Object::new_complex(-right.read().unwrap().as_complex().unwrap())
+            }
+            TokenKind::Minus if right.read().unwrap().is_decimal() => {
+                Object::new_decimal(-right.read().unwrap().as_decimal().unwrap())
+            }
             TokenKind::Minus => {
                 check_number_operand(&expr.operator, right.clone())?;
                 Object::new_number(-right.read().unwrap().as_number())
@@ -255,19 +997,51 @@ impl expr::Visitor<Result<LoxObject, RuntimeError>> for Interpreter {
     }
 
     fn visit_variable_expr(&mut self, expr: &expr::Variable) -> Result<LoxObject, RuntimeError> {
-        self.environment.read().unwrap().get(&expr.name)
+        self.look_up_variable(expr.meta.id, &expr.name)
     }
 
     fn visit_assign_expr(&mut self, expr: &expr::Assign) -> Result<LoxObject, RuntimeError> {
         let value = self.evaluate(&expr.value)?;
 
-        self.environment
-            .write()
-            .unwrap()
-            .assign(&expr.name, value.clone())?;
+        match self.locals.read().unwrap().get(&expr.meta.id) {
+            Some(&distance) => {
+                self.environment
+                    .write()
+                    .unwrap()
+                    .assign_at(distance, &expr.name, value.clone())?
+            }
+            None => self
+                .globals
+                .write()
+                .unwrap()
+                .assign(&expr.name, value.clone())?,
+        }
         Ok(value)
     }
 
+    fn visit_await_expr(&mut self, expr: &expr::Await) -> Result<LoxObject, RuntimeError> {
+        let value = self.evaluate(&expr.expression)?;
+
+        // Awaiting drives the event loop until the task settles; anything
+        // other than a task is already settled.
+        loop {
+            if let Object::Task(result) = &*value.read().unwrap() {
+                if let Some(result) = result {
+                    return Ok(result.clone());
+                }
+            } else {
+                return Ok(value.clone());
+            }
+
+            if !timer::run_next(self, &expr.keyword)? {
+                return Err(RuntimeError::new(
+                    expr.keyword.clone(),
+                    String::from("Awaited task can never complete."),
+                ));
+            }
+        }
+    }
+
     fn visit_logical_expr(&mut self, expr: &expr::Logical) -> Result<LoxObject, RuntimeError> {
         let left = self.evaluate(&expr.left)?;
 
@@ -288,8 +1062,27 @@ impl expr::Visitor<Result<LoxObject, RuntimeError>> for Interpreter {
         self.evaluate(&expr.right)
     }
 
+    fn visit_map_expr(&mut self, expr: &expr::Map) -> Result<LoxObject, RuntimeError> {
+        let mut map = LoxMap::new();
+        for (key, value) in &expr.entries {
+            let key = check_key(&expr.brace, &self.evaluate(key)?)?;
+            map.insert(key, self.evaluate(value)?);
+        }
+        Ok(Object::new_map(map))
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) -> Result<LoxObject, RuntimeError> {
+        let condition = self.evaluate(&expr.condition)?;
+        if condition.read().unwrap().as_bool() {
+            self.evaluate(&expr.then_branch)
+        } else {
+            self.evaluate(&expr.else_branch)
+        }
+    }
+
     fn visit_call_expr(&mut self, expr: &expr::Call) -> Result<LoxObject, RuntimeError> {
         let callee = self.evaluate(&expr.callee)?;
+        self.step(&expr.paren)?;
 
         let mut arguments = vec![];
         for arg in &expr.arguments {
@@ -314,7 +1107,58 @@ impl expr::Visitor<Result<LoxObject, RuntimeError>> for Interpreter {
             ));
         }
 
-        let ret = callee.write().unwrap().call(self, arguments)?;
-        Ok(ret)
+        if self
+            .limits
+            .depth
+            .is_some_and(|max| self.frames.len() >= max)
+        {
+            return Err(RuntimeError::new(
+                expr.paren.clone(),
+                String::from("Stack overflow."),
+            ));
+        }
+        self.frames.push((callee.clone(), expr.paren.line));
+        let ret = callee.read().unwrap().call(self, &expr.paren, arguments);
+        self.frames.pop();
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_lox::{Outcome, TestLox};
+
+    #[test]
+    fn not_equal_is_the_negation_of_equal() {
+        let result = TestLox::new().run(
+            "print 1 != 2;
+            print 1 != 1;
+            print \"a\" != \"a\";
+            print nil != false;
+            print 0/0 != 0/0;",
+        );
+        assert_eq!(result.stdout, "true\nfalse\nfalse\ntrue\ntrue\n");
+    }
+
+    #[test]
+    fn rejects_a_list_indexing_itself() {
+        let result = TestLox::new().run("var l = [1]; l[l] = 1;");
+        match result.outcome {
+            Outcome::RuntimeError(error) => {
+                assert!(error.to_string().contains("Index must be a number."))
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn rejects_a_map_keyed_by_itself() {
+        let result = TestLox::new().run("var m = {}; m[m] = 1;");
+        match result.outcome {
+            Outcome::RuntimeError(error) => {
+                assert!(error.to_string().contains("Map keys must be"))
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
     }
 }
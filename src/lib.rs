@@ -0,0 +1,150 @@
+//! A tree-walking interpreter for Lox, along with the static checks and
+//! translators the `rustlox` command line runs on scripts.
+//!
+//! Embedders can run Lox code with `interpreter::Interpreter`, or with
+//! `test_lox::TestLox` in tests.
+
+#[macro_use]
+pub mod ast_macro;
+pub mod ast_printer;
+pub mod call_graph;
+pub mod counting_loop;
+pub mod crash_report;
+pub mod emit_js;
+pub mod environment;
+pub mod expr;
+pub mod heap_dump;
+pub mod interpreter;
+pub mod lox_printer;
+pub mod minify;
+pub mod nil_check;
+pub mod node;
+pub mod object;
+pub mod options;
+pub mod parser;
+pub mod purity;
+pub mod replay;
+pub mod resolver;
+pub mod runtime_error;
+pub mod sandbox;
+pub mod scanner;
+pub mod stdlib;
+pub mod stmt;
+pub mod strict;
+pub mod test_lox;
+pub mod token;
+pub mod typecheck;
+pub mod unused;
+pub mod version;
+pub mod walk;
+
+use lazy_static::lazy_static;
+use token::{Span, Token, TokenKind};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use std::sync::RwLock;
+
+lazy_static! {
+    pub static ref HAD_ERROR: RwLock<bool> = RwLock::new(false);
+    pub static ref HAD_RUNTIME_ERROR: RwLock<bool> = RwLock::new(false);
+    /// The source most recently scanned, so errors can quote it.
+    pub static ref SOURCE: RwLock<String> = RwLock::new(String::new());
+}
+
+/// Reports a syntax error at `lexeme`, the source text at fault.
+pub fn error_at(line: usize, lexeme: &str, span: Span, message: &str) {
+    report(line, &(String::from(" at '") + lexeme + "'"), message);
+    excerpt(span, lexeme);
+}
+
+pub fn error_at_token(token: &Token, message: &str) {
+    if token.kind == TokenKind::Eof {
+        report(token.line, " at end", message);
+        excerpt(token.span, "");
+    } else {
+        error_at(token.line, &token.lexeme, token.span, message);
+    }
+}
+
+/// Reports an error at the source `span` covers, such as a whole expression.
+pub fn error_at_span(span: Span, message: &str) {
+    let source = SOURCE.read().unwrap().clone();
+    match locate(&source, span) {
+        Some((line, text)) => error_at(line, text, span, message),
+        None => {
+            eprintln!("Error: {}", message);
+            *HAD_ERROR.write().unwrap() = true;
+        }
+    }
+}
+
+/// Reports something which isn't an error, like a warning, at the source
+/// `span` covers.
+pub fn diagnostic_at_span(kind: &str, span: Span, message: &str) {
+    let source = SOURCE.read().unwrap().clone();
+    match locate(&source, span) {
+        Some((line, text)) => {
+            eprintln!("[line {}] {} at '{}': {}", line, kind, text, message);
+            excerpt(span, text);
+        }
+        None => eprintln!("{}: {}", kind, message),
+    }
+}
+
+/// The line `span` starts on in `source`, and the text it covers. `None` if
+/// the span doesn't fall on character boundaries in `source`, as when it
+/// came from an earlier REPL line.
+fn locate(source: &str, span: Span) -> Option<(usize, &str)> {
+    let line = source.get(..span.start)?.matches('\n').count() + 1;
+    Some((line, source.get(span.start..span.end)?))
+}
+
+pub fn runtime_error(error: runtime_error::RuntimeError) {
+    eprintln!("{}", error);
+    excerpt(error.token().span, &error.token().lexeme);
+    *HAD_RUNTIME_ERROR.write().unwrap() = true;
+}
+
+fn report(line: usize, whence: &str, message: &str) {
+    eprintln!("[line {}] Error{}: {}", line, whence, message);
+    *HAD_ERROR.write().unwrap() = true;
+}
+
+/// Quotes the line of source `span` starts on, underlining the span. Nothing
+/// is quoted if the span isn't `lexeme` in the current source, as when a
+/// function from an earlier REPL line fails.
+fn excerpt(span: Span, lexeme: &str) {
+    if let Some((line, underline)) = underline(&SOURCE.read().unwrap(), span, lexeme) {
+        eprintln!("    {}", line);
+        eprintln!("    {}", underline);
+    }
+}
+
+/// The line of `source` that `span` starts on, and a line of carets that
+/// sits under the span when printed below it.
+pub fn underline(source: &str, span: Span, lexeme: &str) -> Option<(String, String)> {
+    let text = match source.get(span.start..span.end) {
+        Some(text) if text.starts_with(lexeme) => text,
+        _ => return None,
+    };
+    let before = source.get(..span.start)?;
+    let after = source.get(span.start..)?;
+
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line_end = after.find('\n').map_or(source.len(), |i| span.start + i);
+    // Keep tabs, and count wide characters twice, so the underline lines up
+    // under any text.
+    let indent = before[line_start..]
+        .chars()
+        .map(|c| match c {
+            '\t' => String::from("\t"),
+            c => " ".repeat(c.width().unwrap_or(0)),
+        })
+        .collect::<String>();
+    let width = text.lines().next().unwrap_or("").width().max(1);
+
+    Some((
+        source[line_start..line_end].to_string(),
+        indent + &"^".repeat(width),
+    ))
+}
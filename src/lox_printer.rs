@@ -0,0 +1,464 @@
+use crate::{
+    expr::{self, Expr},
+    object::Object,
+    stmt::{self, Param, Stmt},
+    token::{Token, TokenKind, TOKEN_INFO},
+};
+
+fn operator_precedence(kind: TokenKind) -> u8 {
+    TOKEN_INFO[kind].precedence.unwrap()
+}
+
+/// How tightly an expression binds, following the parser's grammar from
+/// `comma` up to `primary`.
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Assign(_) | Expr::IndexSet(_) | Expr::Set(_) => 1,
+        Expr::Ternary(_) => 2,
+        Expr::Logical(l) => operator_precedence(l.operator.kind),
+        Expr::Binary(b) => operator_precedence(b.operator.kind),
+        Expr::Unary(_) | Expr::Await(_) => 9,
+        Expr::Call(_) | Expr::Get(_) | Expr::Index(_) => 11,
+        Expr::Grouping(_)
+        | Expr::Lambda(_)
+        | Expr::List(_)
+        | Expr::Literal(_)
+        | Expr::Map(_)
+        | Expr::Super(_)
+        | Expr::This(_)
+        | Expr::Variable(_) => 12,
+    }
+}
+
+/// `name: type`, or just `name` if it has no annotation.
+fn annotated(name: &Token, annotation: &Option<Token>) -> String {
+    match annotation {
+        Some(annotation) => format!("{}: {}", name.lexeme, annotation.lexeme),
+        None => name.lexeme.to_string(),
+    }
+}
+
+/// A function's parameter list, followed by its return type if it has one.
+fn signature(params: &[Param], return_annotation: &Option<Token>) -> String {
+    let params = params
+        .iter()
+        .map(|p| annotated(&p.name, &p.annotation))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match return_annotation {
+        Some(annotation) => format!("({}) -> {}", params, annotation.lexeme),
+        None => format!("({})", params),
+    }
+}
+
+/// `n` written so it reads back as the same number, in the digits `print`
+/// shows. Lox has no literals for infinity and NaN, so they're written as
+/// the divisions that make them, and a negative number is parenthesized so
+/// it stays one operand.
+fn number_literal(n: f64) -> String {
+    if n.is_nan() {
+        String::from("(0/0)")
+    } else if n.is_infinite() {
+        String::from(if n > 0.0 { "(1/0)" } else { "(-1/0)" })
+    } else if n.is_sign_negative() {
+        format!("(-{})", -n)
+    } else {
+        n.to_string()
+    }
+}
+
+fn string_literal(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Turns an AST back into Lox source which runs the same way, adding
+/// parentheses where a tree built by hand needs them. Comments and the
+/// original layout are lost, every `if` and `while` body is braced, and `for`
+/// loops come back as the `while` loops the parser lowers them to.
+pub struct LoxPrinter {
+    out: String,
+    depth: usize,
+}
+
+impl LoxPrinter {
+    pub fn print(statements: &[Stmt]) -> String {
+        let mut printer = LoxPrinter {
+            out: String::new(),
+            depth: 0,
+        };
+        for statement in statements {
+            statement.accept(&mut printer);
+        }
+        printer.out
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Prints `expr`, parenthesized if it binds more loosely than `min`.
+    fn operand(&mut self, expr: &Expr, min: u8) -> String {
+        let text = expr.accept(self);
+        if precedence(expr) < min {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+
+    fn binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        let precedence = operator_precedence(operator.kind);
+        // `**` is right-associative, and its right operand can be a prefix
+        // operator.
+        let (left_min, right_min) = if operator.kind == TokenKind::StarStar {
+            (precedence + 1, precedence - 1)
+        } else {
+            (precedence, precedence + 1)
+        };
+        // A comma hugs its left operand, like one between arguments.
+        let space = if operator.kind == TokenKind::Comma {
+            ""
+        } else {
+            " "
+        };
+        format!(
+            "{}{}{} {}",
+            self.operand(left, left_min),
+            space,
+            operator.lexeme,
+            self.operand(right, right_min)
+        )
+    }
+
+    /// Prints a function declaration or method, where `header` is what comes
+    /// before its parameters.
+    fn function(&mut self, header: &str, function: &stmt::Function) {
+        let signature = match &function.return_annotation {
+            // A getter has no parameter list.
+            Some(annotation) if function.is_getter => format!(" -> {}", annotation.lexeme),
+            None if function.is_getter => String::new(),
+            _ => signature(&function.params, &function.return_annotation),
+        };
+        self.line(&format!("{}{} {{", header, signature));
+        self.depth += 1;
+        function.body.iter().for_each(|s| s.accept(self));
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    /// Prints the body of an `if` or `while` inside the braces around it.
+    fn body(&mut self, body: &Stmt) {
+        self.depth += 1;
+        match body {
+            Stmt::Block(b) => b.statements.iter().for_each(|s| s.accept(self)),
+            _ => body.accept(self),
+        }
+        self.depth -= 1;
+    }
+}
+
+impl expr::Visitor<String> for LoxPrinter {
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) -> String {
+        format!("{} = {}", expr.name.lexeme, self.operand(&expr.value, 1))
+    }
+
+    fn visit_await_expr(&mut self, expr: &expr::Await) -> String {
+        format!("await {}", self.operand(&expr.expression, 9))
+    }
+
+    fn visit_binary_expr(&mut self, expr: &expr::Binary) -> String {
+        self.binary(&expr.left, &expr.operator, &expr.right)
+    }
+
+    fn visit_call_expr(&mut self, expr: &expr::Call) -> String {
+        let callee = self.operand(&expr.callee, 11);
+        let arguments = expr
+            .arguments
+            .iter()
+            .map(|a| self.operand(a, 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{}({})", callee, arguments)
+    }
+
+    fn visit_get_expr(&mut self, expr: &expr::Get) -> String {
+        format!("{}.{}", self.operand(&expr.object, 11), expr.name.lexeme)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) -> String {
+        format!("({})", expr.expression.accept(self))
+    }
+
+    fn visit_index_expr(&mut self, expr: &expr::Index) -> String {
+        format!(
+            "{}[{}]",
+            self.operand(&expr.object, 11),
+            expr.index.accept(self)
+        )
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) -> String {
+        format!(
+            "{}[{}] = {}",
+            self.operand(&expr.object, 11),
+            expr.index.accept(self),
+            self.operand(&expr.value, 1)
+        )
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) -> String {
+        let signature = signature(&expr.params, &expr.return_annotation);
+        let mut body = LoxPrinter {
+            out: String::new(),
+            depth: self.depth + 1,
+        };
+        expr.body.iter().for_each(|s| s.accept(&mut body));
+        format!(
+            "fun {} {{\n{}{}}}",
+            signature,
+            body.out,
+            "    ".repeat(self.depth)
+        )
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) -> String {
+        let elements: Vec<String> = expr.elements.iter().map(|e| self.operand(e, 1)).collect();
+        format!("[{}]", elements.join(", "))
+    }
+
+    fn visit_literal_expr(&mut self, expr: &expr::Literal) -> String {
+        match &*expr.value.read().unwrap() {
+            Object::String(s) => string_literal(s),
+            Object::Bytes(b) => format!("b{}", string_literal(&String::from_utf8_lossy(b))),
+            Object::BigInt(b) => format!("{}n", b),
+            Object::Number(n) => number_literal(*n),
+            value => value.to_string(),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &expr::Logical) -> String {
+        self.binary(&expr.left, &expr.operator, &expr.right)
+    }
+
+    fn visit_map_expr(&mut self, expr: &expr::Map) -> String {
+        let entries: Vec<String> = expr
+            .entries
+            .iter()
+            .map(|(k, v)| format!("{}: {}", self.operand(k, 1), self.operand(v, 1)))
+            .collect();
+        format!("{{{}}}", entries.join(", "))
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) -> String {
+        format!(
+            "{}.{} = {}",
+            self.operand(&expr.object, 11),
+            expr.name.lexeme,
+            self.operand(&expr.value, 1)
+        )
+    }
+
+    fn visit_super_expr(&mut self, expr: &expr::Super) -> String {
+        format!("super.{}", expr.method.lexeme)
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) -> String {
+        format!(
+            "{} ? {} : {}",
+            self.operand(&expr.condition, 3),
+            self.operand(&expr.then_branch, 1),
+            self.operand(&expr.else_branch, 2)
+        )
+    }
+
+    fn visit_this_expr(&mut self, _expr: &expr::This) -> String {
+        String::from("this")
+    }
+
+    fn visit_unary_expr(&mut self, expr: &expr::Unary) -> String {
+        let right = self.operand(&expr.right, 9);
+        // Keep `- -x` from becoming `--x`.
+        if right.starts_with('-') {
+            format!("{} {}", expr.operator.lexeme, right)
+        } else {
+            format!("{}{}", expr.operator.lexeme, right)
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) -> String {
+        expr.name.lexeme.to_string()
+    }
+}
+
+impl stmt::Visitor<()> for LoxPrinter {
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) {
+        self.line("{");
+        self.depth += 1;
+        stmt.statements.iter().for_each(|s| s.accept(self));
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &stmt::Break) {
+        self.line("break;");
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        match &stmt.superclass {
+            Some(superclass) => self.line(&format!(
+                "class {} < {} {{",
+                stmt.name.lexeme, superclass.name.lexeme
+            )),
+            None => self.line(&format!("class {} {{", stmt.name.lexeme)),
+        }
+        self.depth += 1;
+        for method in &stmt.methods {
+            self.function(&method.name.lexeme, method);
+        }
+        for method in &stmt.class_methods {
+            self.function(&format!("class {}", method.name.lexeme), method);
+        }
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &stmt::Continue) {
+        self.line("continue;");
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) {
+        let expression = stmt.expression.accept(self);
+        // Parenthesized so a map at the start isn't taken for a block.
+        if expression.starts_with('{') {
+            self.line(&format!("({});", expression));
+        } else {
+            self.line(&format!("{};", expression));
+        }
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        let keyword = if stmt.is_async { "async fun" } else { "fun" };
+        self.function(&format!("{} {}", keyword, stmt.name.lexeme), stmt);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &stmt::If) {
+        let condition = stmt.condition.accept(self);
+        self.line(&format!("if ({}) {{", condition));
+        self.body(&stmt.then_branch);
+
+        let mut else_branch = &stmt.else_branch;
+        while let Some(branch) = else_branch {
+            if let Stmt::If(i) = &**branch {
+                let condition = i.condition.accept(self);
+                self.line(&format!("}} else if ({}) {{", condition));
+                self.body(&i.then_branch);
+                else_branch = &i.else_branch;
+            } else {
+                self.line("} else {");
+                self.body(branch);
+                break;
+            }
+        }
+        self.line("}");
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &stmt::Print) {
+        let expression = stmt.expression.accept(self);
+        self.line(&format!("print {};", expression));
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &stmt::Return) {
+        match &stmt.value {
+            Some(value) => {
+                let value = value.accept(self);
+                self.line(&format!("return {};", value));
+            }
+            None => self.line("return;"),
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) {
+        let name = annotated(&stmt.name, &stmt.annotation);
+        match &stmt.initializer {
+            Some(initializer) => {
+                let initializer = initializer.accept(self);
+                self.line(&format!("var {} = {};", name, initializer));
+            }
+            None => self.line(&format!("var {};", name)),
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &stmt::While) {
+        let condition = stmt.condition.accept(self);
+        match &stmt.increment {
+            Some(increment) => {
+                let increment = increment.accept(self);
+                self.line(&format!("for (; {}; {}) {{", condition, increment));
+            }
+            None => self.line(&format!("while ({}) {{", condition)),
+        }
+        self.body(&stmt.body);
+        self.line("}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast_macro::{meta, token},
+        test_lox::TestLox,
+    };
+
+    /// `print n ** 2;`, where a negative `n` needs its parentheses.
+    fn print_square(n: f64) -> Stmt {
+        Stmt::Print(stmt::Print {
+            expression: Expr::Binary(expr::Binary {
+                left: Box::new(Expr::Literal(expr::Literal {
+                    value: Object::new_number(n),
+                    meta: meta(),
+                })),
+                operator: token("**"),
+                right: Box::new(lox_ast!(2)),
+                meta: meta(),
+            }),
+            meta: meta(),
+        })
+    }
+
+    #[test]
+    fn writes_numbers_that_read_back_the_same() {
+        let numbers = [
+            0.5,
+            1e21,
+            1e-7,
+            -2.0,
+            -0.0,
+            f64::INFINITY,
+            -f64::INFINITY,
+            f64::NAN,
+        ];
+        let source = LoxPrinter::print(&numbers.map(print_square));
+        assert!(source.contains("print (1/0) ** 2;"), "{}", source);
+        assert!(source.contains("print (-2) ** 2;"), "{}", source);
+
+        let expected = numbers.map(|n| format!("{}\n", n.powf(2.0))).concat();
+        assert_eq!(TestLox::new().run(&source).stdout, expected);
+    }
+}
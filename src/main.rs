@@ -1,48 +1,238 @@
-mod ast_printer;
-mod environment;
-mod expr;
-mod interpreter;
-mod object;
-mod parser;
-mod runtime_error;
-mod scanner;
-mod stmt;
-mod token;
-
 use lazy_static::lazy_static;
-use parser::Parser;
-use scanner::Scanner;
-use token::{Token, TokenKind};
+use options::{Command, Options};
+use rustlox::{
+    call_graph, crash_report, diagnostic_at_span, emit_js, heap_dump, interpreter, lox_printer,
+    minify, nil_check, object, options, sandbox, stdlib, stmt, strict, typecheck, unused, version,
+    HAD_ERROR, HAD_RUNTIME_ERROR, SOURCE,
+};
+use rustlox::{
+    parser::Parser,
+    replay::Recorder,
+    scanner::{LexError, Scanner},
+    token::Token,
+};
 
 use std::{
-    io::{BufRead, Write},
-    sync::RwLock,
+    collections::HashSet,
+    io::{BufRead, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::RecvTimeoutError,
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
 };
 
+#[global_allocator]
+static ALLOCATOR: sandbox::CountingAllocator = sandbox::CountingAllocator;
+
+/// Whether a script is running, so Ctrl-C should interrupt it rather than
+/// exit.
+static EVALUATING: AtomicBool = AtomicBool::new(false);
+
 lazy_static! {
-    static ref HAD_ERROR: RwLock<bool> = RwLock::new(false);
-    static ref HAD_RUNTIME_ERROR: RwLock<bool> = RwLock::new(false);
     static ref INTERPRETER: RwLock<interpreter::Interpreter> =
         RwLock::new(interpreter::Interpreter::new());
 }
 
 fn main() {
-    let args = std::env::args().collect::<Vec<_>>();
-
-    match args.len() {
-        1 => run_prompt().unwrap(),
-        2 => run_file(&args[1]).unwrap(),
-        _ => {
-            println!("Usage: jlox [script]");
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            println!("{}", options::USAGE);
             std::process::exit(64);
         }
+    };
+
+    if options.version {
+        if options.verbose {
+            println!("{}", version::verbose());
+        } else {
+            println!("{}", version::short());
+        }
+        return;
+    }
+
+    crash_report::install(
+        options.script.clone(),
+        INTERPRETER.read().unwrap().trace.clone(),
+        &SOURCE,
+    );
+
+    let recorder = match (&options.record, &options.replay) {
+        (Some(path), _) => Recorder::record_to(path),
+        (_, Some(path)) => Recorder::replay_from(path),
+        _ => Ok(Recorder::Live),
+    };
+    match recorder {
+        Ok(recorder) => *INTERPRETER.write().unwrap().recorder.lock().unwrap() = recorder,
+        Err(e) => {
+            eprintln!("Could not open trace: {}", e);
+            std::process::exit(66);
+        }
+    }
+
+    if options.sandbox {
+        sandbox::apply(&mut INTERPRETER.write().unwrap());
+    }
+    INTERPRETER.write().unwrap().check_contracts = options.check_contracts;
+
+    let interrupt = INTERPRETER.read().unwrap().interrupt.clone();
+    let temp_paths = INTERPRETER.read().unwrap().temp_paths.clone();
+    ctrlc::set_handler(move || {
+        // A second Ctrl-C exits even if the script never checks.
+        if EVALUATING.load(Ordering::SeqCst) && interrupt.raised().is_none() {
+            interrupt.raise("SIGINT");
+        } else {
+            temp_paths.clean_up();
+            std::process::exit(130);
+        }
+    })
+    .expect("Could not install Ctrl-C handler.");
+
+    match (&options.command, &options.script) {
+        (Command::Run, None) => run_prompt(&options).unwrap(),
+        (Command::Run, Some(script)) => run_file(script, &options).unwrap(),
+        (Command::CallGraph, Some(script)) => call_graph(script, &options).unwrap(),
+        (Command::Unused, Some(script)) => unused(script, &options).unwrap(),
+        (Command::TypeCheck, Some(script)) => typecheck(script, &options).unwrap(),
+        (Command::NilCheck, Some(script)) => nilcheck(script, &options).unwrap(),
+        (Command::Tokens, Some(script)) => tokens(script, &options).unwrap(),
+        (Command::Minify, Some(script)) => minify(script, &options).unwrap(),
+        (Command::EmitJs, Some(script)) => {
+            print!(
+                "{}",
+                emit_js::JsEmitter::emit(&parse_file(script, &options).unwrap())
+            )
+        }
+        (Command::EmitLox, Some(script)) => {
+            print!(
+                "{}",
+                lox_printer::LoxPrinter::print(&parse_file(script, &options).unwrap())
+            )
+        }
+        (_, None) => unreachable!(),
+    }
+}
+
+/// Scans and parses a script for the static analysis commands, exiting if
+/// it has syntax errors.
+fn parse_file(name: &str, options: &Options) -> Result<Vec<stmt::Stmt>, std::io::Error> {
+    let source = std::fs::read_to_string(name)?;
+    let tokens = scan(&source, options);
+    let statements = Parser::new(tokens).parse();
+
+    if *HAD_ERROR.read().unwrap() {
+        std::process::exit(65);
+    }
+    Ok(statements.unwrap())
+}
+
+fn natives() -> HashSet<String> {
+    INTERPRETER
+        .read()
+        .unwrap()
+        .globals
+        .read()
+        .unwrap()
+        .values()
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+fn call_graph(name: &str, options: &Options) -> Result<(), std::io::Error> {
+    let statements = parse_file(name, options)?;
+    let graph = call_graph::CallGraph::build(&statements, &natives());
+    if options.dot {
+        print!("{}", graph.to_dot());
+    } else {
+        print!("{}", graph.to_text());
+    }
+    Ok(())
+}
+
+fn unused(name: &str, options: &Options) -> Result<(), std::io::Error> {
+    let statements = parse_file(name, options)?;
+    let graph = call_graph::CallGraph::build(&statements, &natives());
+    for declaration in unused::unused_declarations(&statements, &graph) {
+        eprintln!(
+            "[line {}] Warning: {} '{}' is never used.",
+            declaration.name.line, declaration.kind, declaration.name.lexeme
+        );
+    }
+    Ok(())
+}
+
+fn typecheck(name: &str, options: &Options) -> Result<(), std::io::Error> {
+    let statements = parse_file(name, options)?;
+    typecheck::TypeChecker::check(&statements);
+
+    if *HAD_ERROR.read().unwrap() {
+        std::process::exit(65);
+    }
+    Ok(())
+}
+
+fn nilcheck(name: &str, options: &Options) -> Result<(), std::io::Error> {
+    let statements = parse_file(name, options)?;
+    for warning in nil_check::NilChecker::check(&statements) {
+        diagnostic_at_span("Warning", warning.span, &warning.message);
+        diagnostic_at_span("Note", warning.origin.span, &warning.origin.message);
+    }
+    Ok(())
+}
+
+fn tokens(name: &str, options: &Options) -> Result<(), std::io::Error> {
+    let source = std::fs::read_to_string(name)?;
+    // Errors come after the tokens rather than interleaved with them.
+    let (tokens, errors) = scanner(&source, options).scan_all();
+    for token in tokens {
+        println!(
+            "{}:{} {:?} {}",
+            token.line, token.column, token.kind, token.lexeme
+        );
+    }
+    errors.iter().for_each(LexError::report);
+
+    if *HAD_ERROR.read().unwrap() {
+        std::process::exit(65);
     }
+    Ok(())
 }
 
-fn run_file(name: &str) -> Result<(), std::io::Error> {
+fn minify(name: &str, options: &Options) -> Result<(), std::io::Error> {
     let source = std::fs::read_to_string(name)?;
-    run(&source);
+    let tokens = scan(&source, options);
+    let statements = Parser::new(tokens.clone()).parse();
 
+    if *HAD_ERROR.read().unwrap() {
+        std::process::exit(65);
+    }
+    println!(
+        "{}",
+        minify::minify(&tokens, &statements.unwrap(), &options.keywords)
+    );
+    Ok(())
+}
+
+fn run_file(name: &str, options: &Options) -> Result<(), std::io::Error> {
+    let source = std::fs::read_to_string(name)?;
+    run_with_timeout(&source, options, options.timeout, true);
+
+    if let Some(path) = &options.heap_dump_on_exit {
+        let roots = INTERPRETER.read().unwrap().roots();
+        if let Err(e) = heap_dump::HeapGraph::collect(roots).write(path) {
+            eprintln!("Could not write heap dump: {}", e);
+        }
+    }
+    INTERPRETER.read().unwrap().temp_paths.clean_up();
+
+    match INTERPRETER.read().unwrap().interrupt.raised() {
+        Some("timeout") => std::process::exit(124),
+        Some(_) => std::process::exit(130),
+        None => {}
+    }
     if *HAD_ERROR.read().unwrap() {
         std::process::exit(65);
     }
@@ -52,9 +242,30 @@ fn run_file(name: &str) -> Result<(), std::io::Error> {
     Ok(())
 }
 
-fn run_prompt() -> Result<(), std::io::Error> {
+/// Writes to stdout while keeping a copy, so the REPL can save what a line
+/// printed.
+struct Tee(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        std::io::stdout().write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stdout().flush()
+    }
+}
+
+fn run_prompt(options: &Options) -> Result<(), std::io::Error> {
     let stdin = std::io::stdin();
     let mut reader = std::io::BufReader::new(stdin);
+    let mut results = 0;
+    let printed = Arc::new(Mutex::new(vec![]));
+    INTERPRETER.write().unwrap().stdout = Arc::new(Mutex::new(Tee(printed.clone())));
+    // What the last line printed, then its value, for `:copy` and `:write`.
+    let mut last = String::new();
     loop {
         print!("> ");
         std::io::stdout().flush()?;
@@ -62,50 +273,137 @@ fn run_prompt() -> Result<(), std::io::Error> {
         if let Ok(0) = reader.read_line(&mut line) {
             break;
         }
-        run(&line);
+        if let Some(command) = line.trim().strip_prefix(':') {
+            repl_command(command, &last);
+            continue;
+        }
+        let value = run_with_timeout(&line, options, options.repl_timeout, false);
+        last = String::from_utf8_lossy(&std::mem::take(&mut *printed.lock().unwrap())).into_owned();
+        if let Some(value) = value {
+            // Like Python, nil results are neither shown nor kept.
+            if !value.read().unwrap().is_nil() {
+                results += 1;
+                let name = format!("_{}", results);
+                println!("{} = {}", name, value.read().unwrap());
+                last += &format!("{}\n", value.read().unwrap());
+
+                let interpreter = INTERPRETER.read().unwrap();
+                let mut globals = interpreter.globals.write().unwrap();
+                globals.define(&name, value.clone());
+                globals.define("_", value);
+            }
+        }
         *HAD_ERROR.write().unwrap() = false;
+        INTERPRETER.read().unwrap().interrupt.clear();
     }
+    INTERPRETER.read().unwrap().temp_paths.clean_up();
     Ok(())
 }
 
-fn run(source: &str) {
-    let mut scanner = Scanner::new(source);
-    let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(tokens);
-    let statements = parser.parse();
-
-    if *HAD_ERROR.read().unwrap() {
-        return;
+/// Runs a REPL command, the text after the `:`, on `last`, what the last line
+/// printed and evaluated to.
+fn repl_command(command: &str, last: &str) {
+    let (name, argument) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+    match (name, argument) {
+        ("copy", "") if std::io::stdout().is_terminal() => {
+            // OSC 52 asks the terminal to set the clipboard, which works over
+            // SSH too. Terminals which don't support it ignore it.
+            print!(
+                "\x1b]52;c;{}\x07",
+                stdlib::encoding::base64(last.as_bytes())
+            );
+            let _ = std::io::stdout().flush();
+        }
+        ("copy", "") => eprintln!("Can only copy to the clipboard from a terminal."),
+        ("write", path) if !path.is_empty() => {
+            if let Err(e) = std::fs::write(path, last) {
+                eprintln!("Could not write '{}': {}.", path, e);
+            }
+        }
+        ("copy", _) | ("write", _) => eprintln!("Usage: :copy or :write file"),
+        _ => eprintln!("Unknown command ':{}'. Try :copy or :write file.", name),
     }
-
-    INTERPRETER
-        .write()
-        .unwrap()
-        .interpret(statements.as_ref().unwrap());
 }
 
-pub fn error(line: usize, message: &str) {
-    report(line, "", message);
-}
+/// How long a script has to stop once its timeout is up. Natives which wait
+/// check for the interrupt, but reading from the terminal can't be cut short.
+const TIMEOUT_GRACE: Duration = Duration::from_secs(1);
 
-pub fn error_at_token(token: &Token, message: &str) {
-    if token.kind == TokenKind::Eof {
-        report(token.line, " at end", message);
-    } else {
-        report(
-            token.line,
-            &(String::from(" at '") + &token.lexeme + "'"),
-            message,
-        );
+/// Runs `source`, interrupting it if it takes longer than `timeout`. If
+/// `exit_if_stuck` is set and the script doesn't stop within `TIMEOUT_GRACE`
+/// of being interrupted, the process exits, as it would have at the end of
+/// the script.
+fn run_with_timeout(
+    source: &str,
+    options: &Options,
+    timeout: Option<Duration>,
+    exit_if_stuck: bool,
+) -> Option<object::LoxObject> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return run(source, options),
+    };
+
+    let interrupt = INTERPRETER.read().unwrap().interrupt.clone();
+    let (done, finished) = std::sync::mpsc::channel::<()>();
+    let watchdog = {
+        let interrupt = interrupt.clone();
+        std::thread::spawn(move || {
+            if finished.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                interrupt.raise("timeout");
+                if exit_if_stuck
+                    && finished.recv_timeout(TIMEOUT_GRACE) == Err(RecvTimeoutError::Timeout)
+                {
+                    eprintln!("Interrupted (timeout).");
+                    std::process::exit(124);
+                }
+            }
+        })
+    };
+
+    let result = run(source, options);
+    // The watchdog may fire after the script finished but before it hears
+    // so, which isn't a timeout.
+    let interrupted = interrupt.raised().is_some();
+    drop(done);
+    watchdog.join().unwrap();
+    if !interrupted {
+        interrupt.clear();
     }
+    result
+}
+
+fn scanner(source: &str, options: &Options) -> Scanner {
+    *SOURCE.write().unwrap() = source.to_owned();
+    Scanner::new(source).with_keywords(&options.keywords)
 }
 
-pub fn runtime_error(error: runtime_error::RuntimeError) {
-    eprintln!("{}", error);
-    *HAD_RUNTIME_ERROR.write().unwrap() = true;
+fn scan(source: &str, options: &Options) -> Vec<Token> {
+    scanner(source, options).scan_tokens()
 }
 
-fn report(line: usize, whence: &str, message: &str) {
-    eprintln!("[line {}] Error{}: {}", line, whence, message);
-    *HAD_ERROR.write().unwrap() = true;
+/// Runs `source`, returning the value of its last statement if that's an
+/// expression statement.
+fn run(source: &str, options: &Options) -> Option<object::LoxObject> {
+    let tokens = scan(source, options);
+    let mut parser = Parser::new(tokens);
+    let statements = parser.parse();
+
+    if options.strict && !*HAD_ERROR.read().unwrap() {
+        strict::GlobalChecker::check(statements.as_ref().unwrap(), natives());
+    }
+    if *HAD_ERROR.read().unwrap() {
+        return None;
+    }
+
+    INTERPRETER.read().unwrap().trace.clear();
+    EVALUATING.store(true, Ordering::SeqCst);
+    let result = INTERPRETER
+        .write()
+        .unwrap()
+        .interpret(statements.as_ref().unwrap());
+    EVALUATING.store(false, Ordering::SeqCst);
+    result
 }
@@ -0,0 +1,277 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    expr,
+    scanner::{is_identifier_char, KeywordConfig},
+    stmt::{self, Param, Stmt},
+    token::{Token, TokenKind},
+    walk::{walk_assign_expr, Walk},
+};
+
+/// Whether `left` and `right` would scan differently if written without
+/// anything between them, like `var x` becoming `varx`, or `- -x` becoming
+/// `--x`.
+fn needs_space(left: &Token, right: &Token) -> bool {
+    let last = left.lexeme.chars().last().unwrap_or(' ');
+    let first = right.lexeme.chars().next().unwrap_or(' ');
+    (is_identifier_char(last) && is_identifier_char(first))
+        || (left.lexeme == "b" && first == '"')
+        || (left.kind == TokenKind::Number && first == '.')
+        || (matches!(last, '!' | '=' | '<' | '>') && first == '=')
+        || (last == '/' && first == '/')
+        || (matches!(last, '+' | '-') && first == last)
+        || (last == '-' && first == '>')
+}
+
+/// Writes the tokens back out with comments and all whitespace that doesn't
+/// separate tokens removed, and local variables and parameters given short
+/// names. `statements` are the tokens parsed, and `keywords` what they were
+/// scanned with, so no new name is a reserved word.
+///
+/// Globals keep their names, since other code may look them up, and so do
+/// functions and classes, since printing one shows its name. The result is
+/// a single line, so line numbers in error messages won't match the
+/// original script.
+pub fn minify(tokens: &[Token], statements: &[Stmt], keywords: &KeywordConfig) -> String {
+    let mut taken = tokens
+        .iter()
+        .filter(|t| t.kind == TokenKind::Identifier)
+        .map(|t| t.lexeme.to_string())
+        .collect::<HashSet<_>>();
+    taken.extend(keywords.reserved.iter().cloned());
+    let mut renamer = Renamer {
+        scopes: vec![],
+        taken,
+        next: 0,
+        renamed: HashMap::new(),
+    };
+    statements.iter().for_each(|s| renamer.visit_stmt(s));
+
+    let mut out = String::new();
+    let mut previous: Option<&Token> = None;
+    for token in tokens.iter().filter(|t| t.kind != TokenKind::Eof) {
+        if previous.is_some_and(|previous| needs_space(previous, token)) {
+            out.push(' ');
+        }
+        match renamer.renamed.get(&token.span.start) {
+            Some(name) if token.kind == TokenKind::Identifier => out.push_str(name),
+            _ => out.push_str(&token.lexeme),
+        }
+        previous = Some(token);
+    }
+    out
+}
+
+/// The `n`th short name: `a` to `Z`, then two letters, and so on.
+fn short_name(mut n: usize) -> String {
+    const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut name = String::new();
+    loop {
+        name.push(LETTERS[n % LETTERS.len()] as char);
+        n /= LETTERS.len();
+        if n == 0 {
+            return name;
+        }
+        n -= 1;
+    }
+}
+
+/// Picks new names for locals, scoping them as the resolver does. Every
+/// local gets a name of its own which appears nowhere in the script, so no
+/// renamed variable can capture or shadow another.
+struct Renamer {
+    /// The locals in each scope, innermost last, with their new names.
+    /// Functions and classes, which keep theirs, map to `None`.
+    scopes: Vec<HashMap<String, Option<String>>>,
+    /// Names which can't be handed out.
+    taken: HashSet<String>,
+    next: usize,
+    /// New names by where the token they replace starts in the source.
+    renamed: HashMap<usize, String>,
+}
+
+impl Renamer {
+    fn fresh(&mut self) -> String {
+        loop {
+            let name = short_name(self.next);
+            self.next += 1;
+            if !self.taken.contains(&name) && !KeywordConfig::is_keyword(&name) {
+                return name;
+            }
+        }
+    }
+
+    /// Declares `name` in the innermost scope, renaming it if `rename` is
+    /// set. Globals are left alone.
+    fn declare(&mut self, name: &Token, rename: bool) {
+        if self.scopes.is_empty() {
+            return;
+        }
+        let new = if rename {
+            let new = self.fresh();
+            self.renamed.insert(name.span.start, new.clone());
+            Some(new)
+        } else {
+            None
+        };
+        let scope = self.scopes.last_mut().unwrap();
+        scope.insert(name.lexeme.to_string(), new);
+    }
+
+    /// Renames a use of `name` if it refers to a renamed local.
+    fn reference(&mut self, name: &Token) {
+        let local = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&*name.lexeme));
+        if let Some(Some(new)) = local {
+            self.renamed.insert(name.span.start, new.clone());
+        }
+    }
+
+    fn function(&mut self, params: &[Param], body: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        params.iter().for_each(|p| self.declare(&p.name, true));
+        body.iter().for_each(|s| self.visit_stmt(s));
+        self.scopes.pop();
+    }
+}
+
+impl Walk for Renamer {
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) {
+        self.scopes.push(HashMap::new());
+        stmt.statements.iter().for_each(|s| self.visit_stmt(s));
+        self.scopes.pop();
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        self.declare(&stmt.name, false);
+        if let Some(superclass) = &stmt.superclass {
+            self.reference(&superclass.name);
+        }
+        // Methods are properties, not variables, so only their bodies are
+        // renamed.
+        for method in stmt.methods.iter().chain(&stmt.class_methods) {
+            self.function(&method.params, &method.body);
+        }
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        self.declare(&stmt.name, false);
+        self.function(&stmt.params, &stmt.body);
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) {
+        // As in the resolver, the initializer sees the outer variable.
+        if let Some(initializer) = &stmt.initializer {
+            self.visit_expr(initializer);
+        }
+        self.declare(&stmt.name, true);
+    }
+
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) {
+        walk_assign_expr(self, expr);
+        self.reference(&expr.name);
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) {
+        self.function(&expr.params, &expr.body);
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) {
+        self.reference(&expr.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner, test_lox::TestLox};
+
+    fn minified(source: &str) -> String {
+        let tokens = Scanner::new(source).scan_tokens();
+        let statements = Parser::new(tokens.clone()).parse().unwrap();
+        minify(&tokens, &statements, &KeywordConfig::default())
+    }
+
+    #[test]
+    fn shortens_locals() {
+        assert_eq!(
+            minified("var total = 0;\nfun add(amount) {\n  var next = total + amount;\n  total = next;\n}"),
+            "var total=0;fun add(a){var b=total+a;total=b;}"
+        );
+    }
+
+    #[test]
+    fn minified_scripts_print_the_same() {
+        let source = r#"
+            var a = "global";
+            {
+                var a = a + "!";
+                print a;
+            }
+            print a;
+
+            class Counter {
+                init(start) { this.count = start; }
+                bump(by) { var next = this.count + by; this.count = next; return next; }
+            }
+            {
+                var count = 10;
+                class Local { count() { return count; } }
+                var counter = Counter(count);
+                counter.bump(5);
+                print counter.count;
+                print Local().count();
+            }
+
+            fun makeAdder(n) {
+                fun adder(x) { return x + n; }
+                print adder;
+                return adder;
+            }
+            print makeAdder(1)(2);
+
+            {
+                var f = 1;
+                {
+                    fun f() { return 2; }
+                    print f();
+                }
+                print f;
+            }
+
+            var total = 0;
+            for (var i = 0; i < 5; i = i + 1) {
+                var square = fun (b) { return b * b; };
+                total = total + square(i);
+            }
+            print total;
+        "#;
+        let minified = minified(source);
+        assert!(minified.len() < source.len() / 2, "{}", minified);
+        assert_eq!(
+            TestLox::new().run(&minified).stdout,
+            TestLox::new().run(source).stdout
+        );
+    }
+
+    #[test]
+    fn skips_names_already_in_use_and_keywords() {
+        let names = (0..52 * 53).map(short_name).collect::<HashSet<_>>();
+        assert_eq!(names.len(), 52 * 53);
+
+        assert_eq!(minified("{ var x = a; var y = x; }"), "{var b=a;var c=b;}");
+        let keywords = KeywordConfig {
+            reserved: HashSet::from([String::from("a")]),
+            ..KeywordConfig::default()
+        };
+        let tokens = Scanner::new("fun f(x) { return x; }").scan_tokens();
+        let statements = Parser::new(tokens.clone()).parse().unwrap();
+        assert_eq!(
+            minify(&tokens, &statements, &keywords),
+            "fun f(b){return b;}"
+        );
+    }
+}
@@ -0,0 +1,512 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    expr::{self, Expr},
+    node::NodeId,
+    stmt::{self, Param, Stmt},
+    token::{Span, Token, TokenKind},
+};
+
+/// Natives which return nil when they have nothing else to give, like
+/// `pop(heap)` on an empty heap.
+const MAYBE_NIL_NATIVES: &[&str] = &[
+    "basename",
+    "dirname",
+    "extension",
+    "firstKey",
+    "lastKey",
+    "pop",
+    "popBack",
+    "popFront",
+    "receive",
+    "yamlParse",
+];
+
+/// Where a value which may be nil came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A value which may be nil being called or indexed.
+#[derive(Debug)]
+pub struct Warning {
+    pub span: Span,
+    pub message: String,
+    pub origin: Origin,
+}
+
+/// What the checker knows about each variable in scope: the origin of the
+/// nil it may hold, if any. The globals come first, then one scope per
+/// enclosing block or function.
+type Scopes = Vec<HashMap<String, Option<Origin>>>;
+
+/// Finds values which may be nil being called or indexed, for
+/// `rustlox nilcheck`. Values may be nil if they come from a `var` without an
+/// initializer, a `nil` literal, or one of the natives which return nil.
+///
+/// The check follows each function's control flow, so after
+/// `if (x == nil) return;` or inside `if (x) { ... }` it knows `x` isn't nil.
+/// Calls are assumed to leave variables as they were, and a function sees
+/// the variables around it as they were where it was declared.
+pub struct NilChecker {
+    scopes: Scopes,
+    /// False after a `return`, `break` or `continue`, until control flow
+    /// joins code which can be reached again.
+    reachable: bool,
+    /// The scopes at each `break` and `continue` of the innermost loop.
+    breaks: Vec<Scopes>,
+    continues: Vec<Scopes>,
+    /// Keyed by the use, since loop bodies are checked more than once.
+    warnings: BTreeMap<NodeId, Warning>,
+}
+
+impl NilChecker {
+    /// Checks a script, returning warnings in the order of their uses.
+    pub fn check(statements: &[Stmt]) -> Vec<Warning> {
+        let mut checker = NilChecker {
+            scopes: vec![HashMap::new()],
+            reachable: true,
+            breaks: vec![],
+            continues: vec![],
+            warnings: BTreeMap::new(),
+        };
+        statements.iter().for_each(|s| s.accept(&mut checker));
+
+        let mut warnings = checker.warnings.into_values().collect::<Vec<_>>();
+        warnings.sort_by_key(|w| w.span.start);
+        warnings
+    }
+
+    fn declare(&mut self, name: &Token, origin: Option<Origin>) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.lexeme.to_string(), origin);
+    }
+
+    /// The variable `name` refers to, if it was declared in the script.
+    fn lookup(&mut self, name: &str) -> Option<&mut Option<Origin>> {
+        self.scopes.iter_mut().rev().find_map(|s| s.get_mut(name))
+    }
+
+    /// Warns if `origin` says the value of `expr` may be nil. `how` says what
+    /// was done with it, like "called".
+    fn check_use(&mut self, expr: &Expr, origin: Option<Origin>, how: &str) {
+        if let Some(origin) = origin {
+            self.warnings.insert(
+                expr.id(),
+                Warning {
+                    span: expr.span(),
+                    message: format!("This may be nil when {}.", how),
+                    origin,
+                },
+            );
+        }
+    }
+
+    /// Joins the scopes of two paths which meet: a variable may be nil if it
+    /// may be on either path.
+    fn join(&mut self, mut other: Scopes, other_reachable: bool) {
+        if !other_reachable {
+            return;
+        }
+        // A `break` may come from blocks nested inside this one.
+        other.truncate(self.scopes.len());
+        if !self.reachable {
+            self.scopes = other;
+            self.reachable = true;
+            return;
+        }
+        for (scope, other) in self.scopes.iter_mut().zip(other) {
+            for (name, origin) in other {
+                if let Some(mine) = scope.get_mut(&name) {
+                    if mine.is_none() {
+                        *mine = origin;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Notes what `condition` being `truthy` says about the variables in it.
+    fn narrow(&mut self, condition: &Expr, truthy: bool) {
+        match condition {
+            Expr::Variable(v) if truthy => self.set(&v.name.lexeme, None),
+            Expr::Grouping(g) => self.narrow(&g.expression, truthy),
+            Expr::Unary(u) if u.operator.kind == TokenKind::Bang => self.narrow(&u.right, !truthy),
+            // `a and b` being true means both are; `a or b` being false
+            // means neither is.
+            Expr::Logical(l) if (l.operator.kind == TokenKind::And) == truthy => {
+                self.narrow(&l.left, truthy);
+                self.narrow(&l.right, truthy);
+            }
+            Expr::Binary(b) => {
+                let not_nil = match b.operator.kind {
+                    TokenKind::BangEqual => truthy,
+                    TokenKind::EqualEqual => !truthy,
+                    _ => return,
+                };
+                if !not_nil {
+                    return;
+                }
+                match (&*b.left, &*b.right) {
+                    (Expr::Variable(v), other) | (other, Expr::Variable(v)) if is_nil(other) => {
+                        self.set(&v.name.lexeme, None)
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn set(&mut self, name: &str, origin: Option<Origin>) {
+        if let Some(variable) = self.lookup(name) {
+            *variable = origin;
+        }
+    }
+
+    /// Checks `statement` once `condition` is known to be `truthy`, leaving
+    /// the scopes as they are afterwards.
+    fn check_branch(&mut self, condition: &Expr, truthy: bool, statement: Option<&Stmt>) {
+        self.narrow(condition, truthy);
+        if let Some(statement) = statement {
+            statement.accept(self);
+        }
+    }
+
+    fn check_function(&mut self, params: &[Param], body: &[Stmt]) {
+        let scopes = self.scopes.clone();
+        let reachable = std::mem::replace(&mut self.reachable, true);
+        let breaks = std::mem::take(&mut self.breaks);
+        let continues = std::mem::take(&mut self.continues);
+
+        self.scopes.push(HashMap::new());
+        for param in params {
+            self.declare(&param.name, None);
+        }
+        self.check_block(body);
+
+        self.scopes = scopes;
+        self.reachable = reachable;
+        self.breaks = breaks;
+        self.continues = continues;
+    }
+
+    fn check_block(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        statements.iter().for_each(|s| s.accept(self));
+        self.scopes.pop();
+    }
+}
+
+fn is_nil(expr: &Expr) -> bool {
+    matches!(expr, Expr::Literal(l) if l.value.read().unwrap().is_nil())
+}
+
+impl expr::Visitor<Option<Origin>> for NilChecker {
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) -> Option<Origin> {
+        let origin = expr.value.accept(self);
+        self.set(&expr.name.lexeme, origin.clone());
+        origin
+    }
+
+    fn visit_await_expr(&mut self, expr: &expr::Await) -> Option<Origin> {
+        expr.expression.accept(self);
+        None
+    }
+
+    fn visit_binary_expr(&mut self, expr: &expr::Binary) -> Option<Origin> {
+        expr.left.accept(self);
+        let right = expr.right.accept(self);
+        match expr.operator.kind {
+            TokenKind::Comma => right,
+            _ => None,
+        }
+    }
+
+    fn visit_call_expr(&mut self, expr: &expr::Call) -> Option<Origin> {
+        let callee = expr.callee.accept(self);
+        self.check_use(&expr.callee, callee, "called");
+        expr.arguments.iter().for_each(|a| {
+            a.accept(self);
+        });
+
+        match &*expr.callee {
+            Expr::Variable(v)
+                if MAYBE_NIL_NATIVES.contains(&&*v.name.lexeme)
+                    && self.lookup(&v.name.lexeme).is_none() =>
+            {
+                Some(Origin {
+                    span: expr.meta.span,
+                    message: format!("'{}' may return nil here.", v.name.lexeme),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn visit_get_expr(&mut self, expr: &expr::Get) -> Option<Origin> {
+        let object = expr.object.accept(self);
+        self.check_use(&expr.object, object, "its property is read");
+        None
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) -> Option<Origin> {
+        expr.expression.accept(self)
+    }
+
+    fn visit_index_expr(&mut self, expr: &expr::Index) -> Option<Origin> {
+        let object = expr.object.accept(self);
+        self.check_use(&expr.object, object, "indexed");
+        expr.index.accept(self);
+        None
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) -> Option<Origin> {
+        let object = expr.object.accept(self);
+        self.check_use(&expr.object, object, "indexed");
+        expr.index.accept(self);
+        expr.value.accept(self)
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) -> Option<Origin> {
+        self.check_function(&expr.params, &expr.body);
+        None
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) -> Option<Origin> {
+        expr.elements.iter().for_each(|e| {
+            e.accept(self);
+        });
+        None
+    }
+
+    fn visit_literal_expr(&mut self, expr: &expr::Literal) -> Option<Origin> {
+        if expr.value.read().unwrap().is_nil() {
+            Some(Origin {
+                span: expr.meta.span,
+                message: String::from("The value comes from this nil."),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: &expr::Logical) -> Option<Origin> {
+        let left = expr.left.accept(self);
+        let scopes = self.scopes.clone();
+        // The right side only runs when the left doesn't decide the result.
+        let and = expr.operator.kind == TokenKind::And;
+        self.narrow(&expr.left, and);
+        let right = expr.right.accept(self);
+        self.join(scopes, true);
+
+        // `a or b` is only `a` when it's truthy, so never nil.
+        if and {
+            left.or(right)
+        } else {
+            right
+        }
+    }
+
+    fn visit_map_expr(&mut self, expr: &expr::Map) -> Option<Origin> {
+        for (key, value) in &expr.entries {
+            key.accept(self);
+            value.accept(self);
+        }
+        None
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) -> Option<Origin> {
+        let object = expr.object.accept(self);
+        self.check_use(&expr.object, object, "its property is set");
+        expr.value.accept(self)
+    }
+
+    fn visit_super_expr(&mut self, _expr: &expr::Super) -> Option<Origin> {
+        None
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) -> Option<Origin> {
+        expr.condition.accept(self);
+        let scopes = self.scopes.clone();
+        self.narrow(&expr.condition, true);
+        let then_branch = expr.then_branch.accept(self);
+        let then_scopes = std::mem::replace(&mut self.scopes, scopes);
+        self.narrow(&expr.condition, false);
+        let else_branch = expr.else_branch.accept(self);
+        self.join(then_scopes, true);
+        then_branch.or(else_branch)
+    }
+
+    fn visit_this_expr(&mut self, _expr: &expr::This) -> Option<Origin> {
+        None
+    }
+
+    fn visit_unary_expr(&mut self, expr: &expr::Unary) -> Option<Origin> {
+        expr.right.accept(self);
+        None
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) -> Option<Origin> {
+        self.lookup(&expr.name.lexeme).cloned().flatten()
+    }
+}
+
+impl stmt::Visitor<()> for NilChecker {
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) {
+        self.check_block(&stmt.statements);
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &stmt::Break) {
+        if self.reachable {
+            self.breaks.push(self.scopes.clone());
+        }
+        self.reachable = false;
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        self.declare(&stmt.name, None);
+        for method in stmt.methods.iter().chain(&stmt.class_methods) {
+            self.check_function(&method.params, &method.body);
+        }
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &stmt::Continue) {
+        if self.reachable {
+            self.continues.push(self.scopes.clone());
+        }
+        self.reachable = false;
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        // Declared first, so the function can call itself.
+        self.declare(&stmt.name, None);
+        self.check_function(&stmt.params, &stmt.body);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &stmt::If) {
+        stmt.condition.accept(self);
+        let scopes = self.scopes.clone();
+        let reachable = self.reachable;
+
+        self.check_branch(&stmt.condition, true, Some(&stmt.then_branch));
+        let then_scopes = std::mem::replace(&mut self.scopes, scopes);
+        let then_reachable = std::mem::replace(&mut self.reachable, reachable);
+
+        self.check_branch(&stmt.condition, false, stmt.else_branch.as_deref());
+        self.join(then_scopes, then_reachable);
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &stmt::Print) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &stmt::Return) {
+        if let Some(value) = &stmt.value {
+            value.accept(self);
+        }
+        self.reachable = false;
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) {
+        let origin = match &stmt.initializer {
+            Some(initializer) => initializer.accept(self),
+            None => Some(Origin {
+                span: stmt.name.span,
+                message: format!("'{}' is declared without a value here.", stmt.name.lexeme),
+            }),
+        };
+        self.declare(&stmt.name, origin);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &stmt::While) {
+        let breaks = std::mem::take(&mut self.breaks);
+        let continues = std::mem::take(&mut self.continues);
+
+        // Check the body until another trip around the loop can't make any
+        // more variables nil.
+        let mut entry = self.scopes.clone();
+        loop {
+            self.scopes = entry.clone();
+            stmt.condition.accept(self);
+            self.check_branch(&stmt.condition, true, Some(&stmt.body));
+            for scopes in std::mem::take(&mut self.continues) {
+                self.join(scopes, true);
+            }
+            if let Some(increment) = &stmt.increment {
+                if self.reachable {
+                    increment.accept(self);
+                }
+            }
+
+            let reachable = std::mem::replace(&mut self.reachable, true);
+            let body = std::mem::replace(&mut self.scopes, entry.clone());
+            self.join(body, reachable);
+            if self.scopes == entry {
+                break;
+            }
+            entry = self.scopes.clone();
+        }
+
+        stmt.condition.accept(self);
+        self.narrow(&stmt.condition, false);
+        // Only a `break` leaves `while (true)`, as `for (;;)` becomes.
+        if matches!(&stmt.condition, Expr::Literal(l) if l.value.read().unwrap().as_bool()) {
+            self.reachable = false;
+        }
+        for scopes in std::mem::take(&mut self.breaks) {
+            self.join(scopes, true);
+        }
+        self.breaks = breaks;
+        self.continues = continues;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    /// The warnings for `source`, each with the message of its origin.
+    fn warnings(source: &str) -> Vec<(String, String)> {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap();
+        NilChecker::check(&statements)
+            .into_iter()
+            .map(|w| (w.message, w.origin.message))
+            .collect()
+    }
+
+    #[test]
+    fn warns_about_calling_an_uninitialized_variable() {
+        assert_eq!(
+            warnings("var f; f();"),
+            [(
+                String::from("This may be nil when called."),
+                String::from("'f' is declared without a value here.")
+            )]
+        );
+    }
+
+    #[test]
+    fn trusts_an_assignment() {
+        assert!(warnings("var f; f = fun () {}; f();").is_empty());
+    }
+
+    #[test]
+    fn warns_when_only_one_branch_assigns() {
+        let source = "var f; if (clock() > 0) f = fun () {}; f();";
+        assert_eq!(warnings(source).len(), 1);
+        let source = "var f; if (clock() > 0) f = fun () {}; else f = clock; f();";
+        assert!(warnings(source).is_empty());
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::token::Span;
+
+/// Identifies one node of a syntax tree, so analyses can keep what they learn
+/// about it in side tables instead of in the tree. Ids are unique across
+/// every tree built in a run, REPL lines included, and a cloned node keeps
+/// its id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    fn next() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        NodeId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// What every `Expr` and `Stmt` node carries besides its own fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Meta {
+    pub id: NodeId,
+    /// From the start of the node's first token to the end of its last. Nodes
+    /// the parser adds when lowering, like a `for` loop's `true` condition,
+    /// get the span of the source they stand in for.
+    pub span: Span,
+}
+
+impl Meta {
+    /// Gives a new node the next id.
+    pub fn new(span: Span) -> Self {
+        Meta {
+            id: NodeId::next(),
+            span,
+        }
+    }
+}
@@ -1,13 +1,41 @@
 use lazy_static::lazy_static;
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_traits::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
 
-use std::{borrow::Cow, fmt::Debug, fmt::Display, sync::Arc, sync::RwLock};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    fmt::Display,
+    sync::atomic::{AtomicU64, Ordering},
+    sync::mpsc::{Receiver, Sender},
+    sync::Arc,
+    sync::Mutex,
+    sync::RwLock,
+    thread::JoinHandle,
+};
 
 use crate::{
-    environment::Environment, interpreter::Interpreter, runtime_error::RuntimeError, stmt,
+    environment::Environment,
+    expr,
+    interpreter::Interpreter,
+    node::NodeId,
+    runtime_error::{RuntimeError, Unwind},
+    stmt::{self, Param, Stmt},
+    token::Token,
+    typecheck::Type,
 };
 
 pub type LoxObject = Arc<RwLock<Object>>;
 
+/// Signature of a native function. Natives get the calling interpreter so they
+/// can call back into Lox, and the closing paren of the call so their errors
+/// point at the call site.
+pub type NativeFn = fn(&mut Interpreter, &Token, Vec<LoxObject>) -> Result<LoxObject, RuntimeError>;
+
 lazy_static! {
     static ref NIL: LoxObject = Arc::new(RwLock::new(Object::Nil));
     static ref TRUE: LoxObject = Arc::new(RwLock::new(Object::Bool(true)));
@@ -18,10 +46,33 @@ lazy_static! {
 pub enum Object {
     Nil,
     String(String),
+    Bytes(Vec<u8>),
     Number(f64),
+    BigInt(BigInt),
+    Decimal(Decimal),
+    Complex(Complex64),
     Bool(bool),
-    BuiltinFunction(usize, fn(Vec<LoxObject>) -> LoxObject),
+    BuiltinFunction(usize, NativeFn),
     Function(LoxFunction),
+    Class(Arc<LoxClass>),
+    Instance(LoxInstance),
+    /// A spawned thread, until it's joined. Any thread may hold the handle,
+    /// since joining takes it out under the object's lock, so a second
+    /// join finds `None` however the handle was shared.
+    Thread(Option<JoinHandle<LoxObject>>),
+    Channel(LoxChannel),
+    Mutex(Mutex<()>),
+    Atomic(AtomicU64),
+    Task(Option<LoxObject>),
+    Native(NativeObject),
+    List(Vec<LoxObject>),
+    Map(LoxMap),
+    PersistentVector(im::Vector<LoxObject>),
+    PersistentMap(im::OrdMap<MapKey, LoxObject>),
+    SortedMap(BTreeMap<MapKey, LoxObject>),
+    Heap(LoxHeap),
+    Deque(VecDeque<LoxObject>),
+    StringBuilder(String),
 }
 
 impl Object {
@@ -29,6 +80,18 @@ impl Object {
         Arc::new(RwLock::new(Object::Number(value)))
     }
 
+    pub fn new_bigint(value: BigInt) -> LoxObject {
+        Arc::new(RwLock::new(Object::BigInt(value)))
+    }
+
+    pub fn new_decimal(value: Decimal) -> LoxObject {
+        Arc::new(RwLock::new(Object::Decimal(value)))
+    }
+
+    pub fn new_complex(value: Complex64) -> LoxObject {
+        Arc::new(RwLock::new(Object::Complex(value)))
+    }
+
     pub fn new_bool(value: bool) -> LoxObject {
         match value {
             true => TRUE.clone(),
@@ -44,46 +107,195 @@ impl Object {
         Arc::new(RwLock::new(Object::String(value)))
     }
 
-    pub fn new_builtin_function(arity: usize, func: fn(Vec<LoxObject>) -> LoxObject) -> LoxObject {
+    pub fn new_bytes(value: Vec<u8>) -> LoxObject {
+        Arc::new(RwLock::new(Object::Bytes(value)))
+    }
+
+    pub fn new_builtin_function(arity: usize, func: NativeFn) -> LoxObject {
         Arc::new(RwLock::new(Object::BuiltinFunction(arity, func)))
     }
 
-    pub fn new_function(declaration: stmt::Function) -> LoxObject {
-        Arc::new(RwLock::new(Object::Function(LoxFunction { declaration })))
+    pub fn new_thread(handle: JoinHandle<LoxObject>) -> LoxObject {
+        Arc::new(RwLock::new(Object::Thread(Some(handle))))
     }
 
-    pub fn is_nil(&self) -> bool {
+    pub fn new_channel() -> LoxObject {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        Arc::new(RwLock::new(Object::Channel(LoxChannel {
+            sender,
+            receiver: Mutex::new(receiver),
+        })))
+    }
+
+    pub fn new_function(function: LoxFunction) -> LoxObject {
+        Arc::new(RwLock::new(Object::Function(function)))
+    }
+
+    pub fn new_class(class: LoxClass) -> LoxObject {
+        Arc::new(RwLock::new(Object::Class(Arc::new(class))))
+    }
+
+    pub fn new_instance(class: Arc<LoxClass>) -> LoxObject {
+        Arc::new(RwLock::new(Object::Instance(LoxInstance {
+            class,
+            fields: HashMap::new(),
+        })))
+    }
+
+    pub fn new_mutex() -> LoxObject {
+        Arc::new(RwLock::new(Object::Mutex(Mutex::new(()))))
+    }
+
+    /// Atomics store the bits of their `f64` so they can be updated lock-free.
+    pub fn new_atomic(value: f64) -> LoxObject {
+        Arc::new(RwLock::new(Object::Atomic(AtomicU64::new(value.to_bits()))))
+    }
+
+    /// A task is the pending result of calling an `async` function.
+    pub fn new_task() -> LoxObject {
+        Arc::new(RwLock::new(Object::Task(None)))
+    }
+
+    pub fn new_native<T: Any + Send + Sync>(type_name: &'static str, value: T) -> LoxObject {
+        Arc::new(RwLock::new(Object::Native(NativeObject {
+            type_name,
+            value: Box::new(value),
+        })))
+    }
+
+    pub fn new_list(values: Vec<LoxObject>) -> LoxObject {
+        Arc::new(RwLock::new(Object::List(values)))
+    }
+
+    pub fn new_map(map: LoxMap) -> LoxObject {
+        Arc::new(RwLock::new(Object::Map(map)))
+    }
+
+    pub fn new_persistent_vector(values: im::Vector<LoxObject>) -> LoxObject {
+        Arc::new(RwLock::new(Object::PersistentVector(values)))
+    }
+
+    pub fn new_persistent_map(map: im::OrdMap<MapKey, LoxObject>) -> LoxObject {
+        Arc::new(RwLock::new(Object::PersistentMap(map)))
+    }
+
+    pub fn new_sorted_map() -> LoxObject {
+        Arc::new(RwLock::new(Object::SortedMap(BTreeMap::new())))
+    }
+
+    pub fn new_heap(less: Option<LoxObject>) -> LoxObject {
+        Arc::new(RwLock::new(Object::Heap(LoxHeap {
+            items: vec![],
+            less,
+        })))
+    }
+
+    pub fn new_deque() -> LoxObject {
+        Arc::new(RwLock::new(Object::Deque(VecDeque::new())))
+    }
+
+    pub fn new_string_builder() -> LoxObject {
+        Arc::new(RwLock::new(Object::StringBuilder(String::new())))
+    }
+
+    pub fn type_name(&self) -> &'static str {
         match self {
-            Object::Nil => true,
-            _ => false,
+            Object::Nil => "nil",
+            Object::String(_) => "string",
+            Object::Bytes(_) => "bytes",
+            Object::Number(_) => "number",
+            Object::BigInt(_) => "bigint",
+            Object::Decimal(_) => "decimal",
+            Object::Complex(_) => "complex",
+            Object::Bool(_) => "boolean",
+            Object::BuiltinFunction(..) => "native function",
+            Object::Function(_) => "function",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::Thread(_) => "thread",
+            Object::Channel(_) => "channel",
+            Object::Mutex(_) => "mutex",
+            Object::Atomic(_) => "atomic",
+            Object::Task(_) => "task",
+            Object::Native(n) => n.type_name,
+            Object::List(_) => "list",
+            Object::Map(_) => "map",
+            Object::PersistentVector(_) => "persistent vector",
+            Object::PersistentMap(_) => "persistent map",
+            Object::SortedMap(_) => "sorted map",
+            Object::Heap(_) => "heap",
+            Object::Deque(_) => "deque",
+            Object::StringBuilder(_) => "string builder",
         }
     }
 
+    pub fn is_nil(&self) -> bool {
+        matches!(self, Object::Nil)
+    }
+
     pub fn is_string(&self) -> bool {
+        matches!(self, Object::String(_))
+    }
+
+    pub fn is_bytes(&self) -> bool {
+        matches!(self, Object::Bytes(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Object::Number(_))
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Object::Bool(_))
+    }
+
+    pub fn is_bigint(&self) -> bool {
+        matches!(self, Object::BigInt(_))
+    }
+
+    /// The value as a bigint, if it's a bigint or a number with no fractional
+    /// part. Numbers mix with bigints only when they convert exactly.
+    pub fn as_bigint(&self) -> Option<BigInt> {
         match self {
-            Object::String(_) => true,
-            _ => false,
+            Object::BigInt(b) => Some(b.clone()),
+            Object::Number(n) if n.fract() == 0.0 => BigInt::from_f64(*n),
+            _ => None,
         }
     }
 
-    pub fn is_number(&self) -> bool {
+    pub fn is_complex(&self) -> bool {
+        matches!(self, Object::Complex(_))
+    }
+
+    /// The value as a complex number, if it's one or a number.
+    pub fn as_complex(&self) -> Option<Complex64> {
         match self {
-            Object::Number(_) => true,
-            _ => false,
+            Object::Complex(z) => Some(*z),
+            Object::Number(n) => Some(Complex64::new(*n, 0.0)),
+            _ => None,
         }
     }
 
-    pub fn is_bool(&self) -> bool {
+    pub fn is_decimal(&self) -> bool {
+        matches!(self, Object::Decimal(_))
+    }
+
+    /// The value as a decimal, if it's a decimal, a bigint small enough to
+    /// fit or a number with no fractional part. A fractional number is
+    /// already rounded to binary, so it doesn't mix with decimals.
+    pub fn as_decimal(&self) -> Option<Decimal> {
         match self {
-            Object::Bool(_) => true,
-            _ => false,
+            Object::Decimal(d) => Some(*d),
+            Object::BigInt(b) => Decimal::from_i128(b.to_i128()?),
+            Object::Number(n) if n.fract() == 0.0 => Decimal::from_f64(*n),
+            _ => None,
         }
     }
 
     /// Why?
     pub fn as_nil(&self) {}
 
-    pub fn as_string(&self) -> Cow<str> {
+    pub fn as_string(&self) -> Cow<'_, str> {
         match self {
             Object::String(s) => Cow::Borrowed(s),
             _ => Cow::Owned(self.to_string()),
@@ -112,28 +324,49 @@ impl Object {
         match self {
             Object::Nil => false,
             Object::String(_) => false,
+            Object::Bytes(_) => false,
             Object::Number(_) => false,
+            Object::BigInt(_) => false,
+            Object::Decimal(_) => false,
+            Object::Complex(_) => false,
             Object::Bool(_) => false,
             Object::BuiltinFunction(_, _) => true,
             Object::Function(_) => true,
+            Object::Class(_) => true,
+            Object::Instance(_) => false,
+            Object::Thread(_) => false,
+            Object::Channel(_) => false,
+            Object::Mutex(_) => false,
+            Object::Atomic(_) => false,
+            Object::Task(_) => false,
+            Object::Native(_) => false,
+            Object::List(_) => false,
+            Object::Map(_) => false,
+            Object::PersistentVector(_) => false,
+            Object::PersistentMap(_) => false,
+            Object::SortedMap(_) => false,
+            Object::Heap(_) => false,
+            Object::Deque(_) => false,
+            Object::StringBuilder(_) => false,
         }
     }
 
     pub fn call(
-        &mut self,
+        &self,
         interpreter: &mut Interpreter,
+        paren: &Token,
         arguments: Vec<LoxObject>,
     ) -> Result<LoxObject, RuntimeError> {
         match self {
-            Object::BuiltinFunction(_, func) => Ok(func(arguments)),
-            Object::Function(f) => {
-                let mut environment = Environment::new_enclosed(interpreter.globals.clone());
-                for i in 0..f.declaration.params.len() {
-                    environment.define(&f.declaration.params[i].lexeme, arguments[i].clone());
+            Object::BuiltinFunction(_, func) => func(interpreter, paren, arguments),
+            Object::Function(f) => f.call(interpreter, paren, arguments),
+            Object::Class(class) => {
+                let instance = Object::new_instance(class.clone());
+                if let Some(init) = class.find_method("init") {
+                    init.bind(instance.clone())
+                        .call(interpreter, paren, arguments)?;
                 }
-
-                interpreter.execute_block(&f.declaration.body, environment)?;
-                Ok(Object::nil())
+                Ok(instance)
             }
             _ => unreachable!(),
         }
@@ -142,25 +375,123 @@ impl Object {
     pub fn arity(&self) -> usize {
         match self {
             Object::BuiltinFunction(arity, ..) => *arity,
-            Object::Function(f) => f.declaration.params.len(),
-            _ => std::usize::MAX,
+            Object::Function(f) => f.params.len(),
+            Object::Class(class) => class.arity(),
+            _ => usize::MAX,
         }
     }
 }
 
+/// The values being formatted, from the outermost in, so a collection that
+/// contains itself prints as `[...]` rather than recursing forever.
+type Formatting = HashSet<*const Object>;
+
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_within(f, &mut Formatting::from([self as *const Object]))
+    }
+}
+
+impl Object {
+    fn fmt_within(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        within: &mut Formatting,
+    ) -> std::fmt::Result {
         match self {
             Object::Nil => write!(f, "nil"),
             Object::String(s) => write!(f, "{}", s),
+            Object::Bytes(b) => {
+                write!(f, "b\"")?;
+                for &byte in b {
+                    match byte {
+                        b'"' | b'\\' => write!(f, "\\{}", byte as char)?,
+                        0x20..=0x7e => write!(f, "{}", byte as char)?,
+                        _ => write!(f, "\\x{:02x}", byte)?,
+                    }
+                }
+                write!(f, "\"")
+            }
             Object::Number(n) => write!(f, "{}", n),
+            Object::BigInt(b) => write!(f, "{}", b),
+            Object::Decimal(d) => write!(f, "{}", d),
+            Object::Complex(z) if z.im.is_sign_negative() => write!(f, "{}-{}i", z.re, -z.im),
+            Object::Complex(z) => write!(f, "{}+{}i", z.re, z.im),
             Object::Bool(b) => write!(f, "{}", b),
             Object::BuiltinFunction(..) => write!(f, "<native fn>"),
-            Object::Function(func) => write!(f, "<fn {}>", func.declaration.name.lexeme),
+            Object::Function(func) => write!(f, "{}", func),
+            Object::Class(class) => write!(f, "{}", class.name.lexeme),
+            Object::Instance(instance) => write!(f, "{} instance", instance.class.name.lexeme),
+            Object::Thread(_) => write!(f, "<thread>"),
+            Object::Channel(_) => write!(f, "<channel>"),
+            Object::Mutex(_) => write!(f, "<mutex>"),
+            Object::Atomic(a) => write!(f, "<atomic {}>", f64::from_bits(a.load(Ordering::SeqCst))),
+            Object::Task(_) => write!(f, "<task>"),
+            Object::Native(n) => write!(f, "<{}>", n.type_name),
+            Object::List(values) => fmt_list(f, values.iter(), within),
+            Object::Map(map) => fmt_map(f, map.iter(), within),
+            Object::PersistentVector(values) => fmt_list(f, values.iter(), within),
+            Object::PersistentMap(map) => fmt_map(f, map.iter(), within),
+            Object::SortedMap(map) => fmt_map(f, map.iter(), within),
+            Object::Heap(heap) => write!(f, "<heap of {}>", heap.items.len()),
+            Object::Deque(values) => fmt_list(f, values.iter(), within),
+            Object::StringBuilder(_) => write!(f, "<string builder>"),
         }
     }
 }
 
+fn fmt_list<'a>(
+    f: &mut std::fmt::Formatter<'_>,
+    values: impl Iterator<Item = &'a LoxObject>,
+    within: &mut Formatting,
+) -> std::fmt::Result {
+    write!(f, "[")?;
+    for (i, value) in values.enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        fmt_element(f, value, within)?;
+    }
+    write!(f, "]")
+}
+
+fn fmt_map<'a>(
+    f: &mut std::fmt::Formatter<'_>,
+    entries: impl Iterator<Item = (&'a MapKey, &'a LoxObject)>,
+    within: &mut Formatting,
+) -> std::fmt::Result {
+    write!(f, "{{")?;
+    for (i, (key, value)) in entries.enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        key.fmt_element(f)?;
+        write!(f, ": ")?;
+        fmt_element(f, value, within)?;
+    }
+    write!(f, "}}")
+}
+
+/// Formats a value inside a collection, where strings are quoted so that
+/// `["a, b"]` and `["a", "b"]` print differently.
+fn fmt_element(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &LoxObject,
+    within: &mut Formatting,
+) -> std::fmt::Result {
+    let value = value.read().unwrap();
+    let pointer = &*value as *const Object;
+    if !within.insert(pointer) {
+        return write!(f, "[...]");
+    }
+    let result = match &*value {
+        Object::String(s) => write!(f, "{:?}", s),
+        value => value.fmt_within(f, within),
+    };
+    within.remove(&pointer);
+    result
+}
+
 impl PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
         if self.is_nil() && other.is_nil() {
@@ -173,13 +504,510 @@ impl PartialEq for Object {
             self.as_number() == other.as_number()
         } else if self.is_string() && other.is_string() {
             self.as_string().as_ref() == other.as_string().as_ref()
+        } else if let (Object::Bytes(a), Object::Bytes(b)) = (self, other) {
+            a == b
+        } else if self.is_complex() || other.is_complex() {
+            match (self.as_complex(), other.as_complex()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            }
+        } else if self.is_decimal() || other.is_decimal() {
+            match (self.as_decimal(), other.as_decimal()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            }
+        } else if self.is_bigint() || other.is_bigint() {
+            match (self.as_bigint(), other.as_bigint()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            }
         } else {
-            false
+            // Everything else, including lists and maps, compares by identity.
+            std::ptr::eq(self, other)
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct LoxFunction {
-    pub declaration: stmt::Function,
+    /// `None` for a lambda.
+    pub name: Option<Token>,
+    pub is_async: bool,
+    pub is_getter: bool,
+    pub params: Vec<Param>,
+    pub return_annotation: Option<Token>,
+    /// Shared, since every function made from one declaration has the same
+    /// body.
+    pub body: Arc<[Stmt]>,
+    /// The declaration or lambda the function was made from.
+    pub id: NodeId,
+    /// The environment the function was made in, which its body sees.
+    pub closure: Arc<RwLock<Environment>>,
+}
+
+impl LoxFunction {
+    pub fn new(declaration: &stmt::Function, closure: Arc<RwLock<Environment>>) -> Self {
+        Self {
+            name: Some(declaration.name.clone()),
+            is_async: declaration.is_async,
+            is_getter: declaration.is_getter,
+            params: declaration.params.clone(),
+            return_annotation: declaration.return_annotation.clone(),
+            body: declaration.body.clone().into(),
+            id: declaration.meta.id,
+            closure,
+        }
+    }
+
+    pub fn lambda(lambda: &expr::Lambda, closure: Arc<RwLock<Environment>>) -> Self {
+        Self {
+            name: None,
+            is_async: false,
+            is_getter: false,
+            params: lambda.params.clone(),
+            return_annotation: lambda.return_annotation.clone(),
+            body: lambda.body.clone().into(),
+            id: lambda.meta.id,
+            closure,
+        }
+    }
+
+    /// The method with `this` bound to `instance`, as it is when accessed on
+    /// the instance.
+    pub fn bind(&self, instance: LoxObject) -> LoxFunction {
+        let mut environment = Environment::new_enclosed(self.closure.clone());
+        environment.define("this", instance);
+        LoxFunction {
+            closure: Arc::new(RwLock::new(environment)),
+            ..self.clone()
+        }
+    }
+
+    /// The variables the function captured from the functions and blocks
+    /// around it, innermost first. Globals aren't included.
+    pub fn captured(&self) -> Vec<(String, LoxObject)> {
+        let mut captured = vec![];
+        let mut environment = self.closure.clone();
+        loop {
+            let enclosing = match environment.read().unwrap().enclosing() {
+                Some(enclosing) => enclosing,
+                None => break,
+            };
+            captured.extend(
+                environment
+                    .read()
+                    .unwrap()
+                    .values()
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            );
+            environment = enclosing;
+        }
+        captured
+    }
+
+    /// Checks each argument against its parameter's type annotation, for
+    /// `--check-contracts`. Errors point at `paren`, the call site.
+    pub fn check_arguments(
+        &self,
+        paren: &Token,
+        arguments: &[LoxObject],
+    ) -> Result<(), RuntimeError> {
+        for (param, argument) in self.params.iter().zip(arguments) {
+            let what = format!("parameter '{}'", param.name.lexeme);
+            self.check_contract(paren, &param.annotation, argument, &what)?;
+        }
+        Ok(())
+    }
+
+    /// Checks `value` against `annotation`, if it names a type. Unknown types
+    /// are left to `rustlox typecheck` to report.
+    fn check_contract(
+        &self,
+        paren: &Token,
+        annotation: &Option<Token>,
+        value: &LoxObject,
+        what: &str,
+    ) -> Result<(), RuntimeError> {
+        let expected = match annotation.as_ref().and_then(|a| Type::from_name(&a.lexeme)) {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        let value = value.read().unwrap();
+        if expected.admits(&value) {
+            return Ok(());
+        }
+        Err(RuntimeError::new(
+            paren.clone(),
+            format!(
+                "Expected {} for {} of {}, got {}.",
+                expected,
+                what,
+                self,
+                value.type_name()
+            ),
+        ))
+    }
+
+    /// Calls the function with arguments of the right arity: runs it, or
+    /// schedules it to run if it's `async`.
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LoxObject>,
+    ) -> Result<LoxObject, RuntimeError> {
+        if interpreter.check_contracts {
+            self.check_arguments(paren, &arguments)?;
+        }
+        if self.is_async {
+            return Ok(interpreter.timers.schedule_task(self.clone(), arguments));
+        }
+        self.invoke(interpreter, paren, arguments)
+    }
+
+    /// Runs the function body right away, even if the function is `async`.
+    /// With `--check-contracts`, the return value is checked against its
+    /// annotation, with errors pointing at `paren`.
+    pub fn invoke(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LoxObject>,
+    ) -> Result<LoxObject, RuntimeError> {
+        let mut environment = Environment::new_enclosed(self.closure.clone());
+        for (param, argument) in self.params.iter().zip(arguments) {
+            environment.define(&param.name.lexeme, argument);
+        }
+
+        let value = match interpreter.execute_block(&self.body, environment) {
+            Ok(()) => Object::nil(),
+            Err(Unwind::Return(value)) => value,
+            Err(unwind) => return Err(unwind.into_error()),
+        };
+        if interpreter.check_contracts {
+            self.check_contract(paren, &self.return_annotation, &value, "the return value")?;
+        }
+        Ok(value)
+    }
+}
+
+impl Display for LoxFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "<fn {}>", name.lexeme),
+            None => write!(f, "<fn>"),
+        }
+    }
+}
+
+impl Debug for LoxFunction {
+    // The closure is left out, since a recursive function's closure holds
+    // the function itself.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoxFunction")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A class, made by running a `class` declaration.
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: Token,
+    pub superclass: Option<Arc<LoxClass>>,
+    pub methods: HashMap<String, LoxFunction>,
+    /// Methods called on the class itself, with `this` bound to the class.
+    pub class_methods: HashMap<String, LoxFunction>,
+}
+
+impl LoxClass {
+    /// A method of the class, or else one it inherits.
+    pub fn find_method(&self, name: &str) -> Option<&LoxFunction> {
+        self.methods.get(name).or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+
+    /// A class method of the class, or else one it inherits.
+    pub fn find_class_method(&self, name: &str) -> Option<&LoxFunction> {
+        self.class_methods.get(name).or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_class_method(name))
+        })
+    }
+
+    /// A class method bound to `this`, the object holding the class.
+    pub fn get(&self, name: &Token, this: &LoxObject) -> Result<LoxObject, RuntimeError> {
+        match self.find_class_method(&name.lexeme) {
+            Some(method) => Ok(Object::new_function(method.bind(this.clone()))),
+            None => Err(RuntimeError::new(
+                name.clone(),
+                format!("Undefined property '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    /// Calling a class takes the arguments its `init` method does, if it has
+    /// one.
+    pub fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.params.len())
+    }
+}
+
+#[derive(Debug)]
+pub struct LoxInstance {
+    pub class: Arc<LoxClass>,
+    pub fields: HashMap<String, LoxObject>,
+}
+
+impl LoxInstance {
+    /// A field, or else a method of the instance's class bound to `this`,
+    /// the object holding the instance.
+    pub fn get(&self, name: &Token, this: &LoxObject) -> Result<LoxObject, RuntimeError> {
+        if let Some(value) = self.fields.get(&*name.lexeme) {
+            return Ok(value.clone());
+        }
+        if let Some(method) = self.class.find_method(&name.lexeme) {
+            return Ok(Object::new_function(method.bind(this.clone())));
+        }
+        Err(RuntimeError::new(
+            name.clone(),
+            format!("Undefined property '{}'.", name.lexeme),
+        ))
+    }
+
+    pub fn set(&mut self, name: &Token, value: LoxObject) {
+        self.fields.insert(name.lexeme.to_string(), value);
+    }
+}
+
+#[derive(Debug)]
+pub struct LoxChannel {
+    pub sender: Sender<LoxObject>,
+    pub receiver: Mutex<Receiver<LoxObject>>,
+}
+
+/// Userdata owned by natives, such as a socket. Natives find their own data
+/// again by downcasting `value`.
+pub struct NativeObject {
+    pub type_name: &'static str,
+    pub value: Box<dyn Any + Send + Sync>,
+}
+
+impl Debug for NativeObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativeObject({})", self.type_name)
+    }
+}
+
+/// The subset of values which can be used as map keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Nil,
+    Bool(bool),
+    /// The bits of the number, with `-0` folded into `0`.
+    Number(u64),
+    String(String),
+}
+
+impl MapKey {
+    pub fn from_object(object: &Object) -> Option<MapKey> {
+        match object {
+            Object::Nil => Some(MapKey::Nil),
+            Object::Bool(b) => Some(MapKey::Bool(*b)),
+            Object::Number(n) if n.is_nan() => None,
+            Object::Number(n) => Some(MapKey::Number((n + 0.0).to_bits())),
+            Object::String(s) => Some(MapKey::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn to_object(&self) -> LoxObject {
+        match self {
+            MapKey::Nil => Object::nil(),
+            MapKey::Bool(b) => Object::new_bool(*b),
+            MapKey::Number(bits) => Object::new_number(f64::from_bits(*bits)),
+            MapKey::String(s) => Object::new_string(s.clone()),
+        }
+    }
+
+    fn fmt_element(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapKey::Nil => write!(f, "nil"),
+            MapKey::Bool(b) => write!(f, "{}", b),
+            MapKey::Number(bits) => write!(f, "{}", f64::from_bits(*bits)),
+            MapKey::String(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// Keys are ordered by type, then by value, with numbers compared as numbers
+/// so persistent and sorted maps iterate them in numeric order.
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        fn rank(key: &MapKey) -> u8 {
+            match key {
+                MapKey::Nil => 0,
+                MapKey::Bool(_) => 1,
+                MapKey::Number(_) => 2,
+                MapKey::String(_) => 3,
+            }
+        }
+        match (self, other) {
+            (MapKey::Bool(a), MapKey::Bool(b)) => a.cmp(b),
+            (MapKey::Number(a), MapKey::Number(b)) => {
+                f64::from_bits(*a).total_cmp(&f64::from_bits(*b))
+            }
+            (MapKey::String(a), MapKey::String(b)) => a.cmp(b),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<&str> for MapKey {
+    fn from(s: &str) -> Self {
+        MapKey::String(s.to_owned())
+    }
+}
+
+/// A binary min-heap. Items are ordered by the `less` function if there is
+/// one, and otherwise must all be numbers or all be strings.
+#[derive(Debug)]
+pub struct LoxHeap {
+    pub items: Vec<LoxObject>,
+    pub less: Option<LoxObject>,
+}
+
+/// A map which remembers the order its keys were first inserted in.
+#[derive(Debug, Default)]
+pub struct LoxMap {
+    entries: Vec<(MapKey, LoxObject)>,
+    indices: HashMap<MapKey, usize>,
+}
+
+impl LoxMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&LoxObject> {
+        self.indices.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn insert(&mut self, key: MapKey, value: LoxObject) {
+        match self.indices.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<LoxObject> {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+        for i in self.indices.values_mut() {
+            if *i > index {
+                *i -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&MapKey, &LoxObject)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_lox::TestLox;
+
+    #[test]
+    fn closures_keep_their_own_counters() {
+        let result = TestLox::new().run(
+            "fun makeCounter() {
+                var count = 0;
+                fun counter() {
+                    count = count + 1;
+                    return count;
+                }
+                return counter;
+            }
+            var a = makeCounter();
+            var b = makeCounter();
+            print a();
+            print a();
+            print b();
+            print a();",
+        );
+        assert_eq!(result.stdout, "1\n2\n1\n3\n");
+    }
+
+    #[test]
+    fn curried_functions_see_every_argument() {
+        let result = TestLox::new().run(
+            "fun add(a) {
+                return fun (b) {
+                    return fun (c) { return a + b + c; };
+                };
+            }
+            var addOne = add(1);
+            print addOne(2)(3);
+            print addOne(10)(20);",
+        );
+        assert_eq!(result.stdout, "6\n31\n");
+    }
+
+    #[test]
+    fn closures_outlive_their_block() {
+        let result = TestLox::new().run(
+            "var show;
+            {
+                var message = \"inner\";
+                show = fun () { print message; };
+                message = \"changed\";
+            }
+            show();",
+        );
+        assert_eq!(result.stdout, "changed\n");
+    }
+
+    #[test]
+    fn prints_collections_that_contain_themselves() {
+        let result = TestLox::new().run(
+            "var l = [1, 2];
+            l[1] = l;
+            print l;
+            var m = {\"list\": l};
+            m[\"self\"] = m;
+            print m;
+            var shared = [3];
+            print [shared, shared];",
+        );
+        assert_eq!(
+            result.stdout,
+            "[1, [...]]\n{\"list\": [1, [...]], \"self\": [...]}\n[[3], [3]]\n"
+        );
+    }
 }
@@ -0,0 +1,189 @@
+#[derive(Debug, Default, PartialEq)]
+pub enum Command {
+    #[default]
+    Run,
+    /// `rustlox callgraph script` prints which functions call which.
+    CallGraph,
+    /// `rustlox unused script` warns about functions and classes which are
+    /// never used.
+    Unused,
+    /// `rustlox typecheck script` checks the script's type annotations.
+    TypeCheck,
+    /// `rustlox nilcheck script` warns about values which may be nil being
+    /// called or indexed.
+    NilCheck,
+    /// `rustlox tokens script` prints each token the script is scanned into.
+    Tokens,
+    /// `rustlox minify script` prints the script with comments and
+    /// whitespace stripped, and local variables given short names.
+    Minify,
+    /// `rustlox emit-js script` prints the script lowered to JavaScript.
+    EmitJs,
+    /// `rustlox emit-lox script` prints the script regenerated from its AST.
+    EmitLox,
+}
+
+use std::time::Duration;
+
+use crate::scanner::KeywordConfig;
+
+/// Command line options for the `rustlox` binary.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub command: Command,
+    pub script: Option<String>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub heap_dump_on_exit: Option<String>,
+    pub dot: bool,
+    pub keywords: KeywordConfig,
+    /// Report undefined globals before running instead of when reached.
+    pub strict: bool,
+    /// How long each line typed at the REPL may run for.
+    pub repl_timeout: Option<Duration>,
+    /// How long a script may run for.
+    pub timeout: Option<Duration>,
+    /// Run untrusted code: no natives which reach outside the interpreter,
+    /// and limits on time, steps, memory and recursion.
+    pub sandbox: bool,
+    /// Check arguments and return values against their type annotations.
+    pub check_contracts: bool,
+    /// Print the version and exit.
+    pub version: bool,
+    /// With `version`, also print the commit, build date and features.
+    pub verbose: bool,
+}
+
+pub const USAGE: &str =
+    "Usage: rustlox [--strict] [--sandbox] [--record trace | --replay trace] [--heap-dump-on-exit file]
+               [--timeout duration] [--repl-timeout duration] [--check-contracts] [script]
+       rustlox callgraph [--dot] script
+       rustlox unused script
+       rustlox typecheck script
+       rustlox nilcheck script
+       rustlox tokens script
+       rustlox minify script
+       rustlox emit-js script
+       rustlox emit-lox script
+       rustlox --version [--verbose]
+
+Any command also accepts --reserve word and --disable-keyword keyword, which
+may be repeated. Durations are numbers of seconds, optionally followed by ms, s
+or m, like 500ms or 5s.
+
+In the REPL, :copy copies what the last line printed and evaluated to, and
+:write file saves it to a file.";
+
+impl Options {
+    /// Parses the arguments following the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter().peekable();
+
+        match args.peek().map(String::as_str) {
+            Some("callgraph") => options.command = Command::CallGraph,
+            Some("unused") => options.command = Command::Unused,
+            Some("typecheck") => options.command = Command::TypeCheck,
+            Some("nilcheck") => options.command = Command::NilCheck,
+            Some("tokens") => options.command = Command::Tokens,
+            Some("minify") => options.command = Command::Minify,
+            Some("emit-js") => options.command = Command::EmitJs,
+            Some("emit-lox") => options.command = Command::EmitLox,
+            _ => {}
+        }
+        if options.command != Command::Run {
+            args.next();
+        }
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => options.record = Some(value(&mut args, &arg)?),
+                "--replay" => options.replay = Some(value(&mut args, &arg)?),
+                "--dot" if options.command == Command::CallGraph => options.dot = true,
+                "--heap-dump-on-exit" => options.heap_dump_on_exit = Some(value(&mut args, &arg)?),
+                "--strict" => options.strict = true,
+                "--sandbox" => options.sandbox = true,
+                "--check-contracts" => options.check_contracts = true,
+                "--version" => options.version = true,
+                "--verbose" => options.verbose = true,
+                "--repl-timeout" => {
+                    options.repl_timeout = Some(duration(&value(&mut args, &arg)?)?)
+                }
+                "--timeout" => options.timeout = Some(duration(&value(&mut args, &arg)?)?),
+                "--reserve" => {
+                    options.keywords.reserved.insert(value(&mut args, &arg)?);
+                }
+                "--disable-keyword" => {
+                    let keyword = value(&mut args, &arg)?;
+                    if !KeywordConfig::is_keyword(&keyword) {
+                        return Err(format!("'{}' is not a keyword.", keyword));
+                    }
+                    options.keywords.disabled.insert(keyword);
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option '{}'.", arg)),
+                _ if options.script.is_none() => options.script = Some(arg),
+                _ => return Err(String::from("Expected at most one script.")),
+            }
+        }
+
+        if options.verbose && !options.version {
+            return Err(String::from("--verbose only applies to --version."));
+        }
+        if options.version {
+            return Ok(options);
+        }
+        if options.command != Command::Run && options.script.is_none() {
+            return Err(String::from("Expected a script."));
+        }
+        if options.record.is_some() && options.replay.is_some() {
+            return Err(String::from("Can't both --record and --replay."));
+        }
+        if options.sandbox && options.timeout.is_none() {
+            options.timeout = Some(Duration::from_secs(5));
+        }
+        Ok(options)
+    }
+}
+
+/// Parses a duration like `5s`, `500ms` or `2m`. A bare number is seconds.
+fn duration(text: &str) -> Result<Duration, String> {
+    let (number, unit) = match text.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => text.split_at(i),
+        None => (text, "s"),
+    };
+    let scale = match unit {
+        "ms" => 0.001,
+        "s" => 1.0,
+        "m" => 60.0,
+        _ => return Err(format!("Invalid duration '{}'.", text)),
+    };
+    let invalid = || format!("Invalid duration '{}'.", text);
+    match number.parse::<f64>() {
+        Ok(n) if n > 0.0 => Duration::try_from_secs_f64(n * scale).map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Expected a value after '{}'.", option))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(duration("5"), Ok(Duration::from_secs(5)));
+        assert_eq!(duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(duration("2m"), Ok(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn rejects_durations_too_long_to_represent() {
+        assert!(duration("999999999999999999999999m").is_err());
+        assert!(duration("1e309").is_err());
+        assert!(duration("-1s").is_err());
+    }
+}
@@ -1,33 +1,75 @@
 use crate::{
     expr::Assign,
+    expr::Await,
     expr::Binary,
     expr::Call,
     expr::Expr,
+    expr::Get,
     expr::Grouping,
+    expr::Index,
+    expr::IndexSet,
+    expr::Lambda,
+    expr::List,
     expr::Literal,
     expr::Logical,
+    expr::Map,
+    expr::Set,
+    expr::Super,
+    expr::Ternary,
+    expr::This,
     expr::Unary,
     expr::Variable,
+    node::Meta,
     object::Object,
     stmt::Block,
+    stmt::Break,
+    stmt::Class,
+    stmt::Continue,
     stmt::Expression,
     stmt::Function,
     stmt::If,
+    stmt::Param,
     stmt::Print,
+    stmt::Return,
     stmt::Stmt,
     stmt::Var,
     stmt::While,
-    token::{Token, TokenKind},
+    token::{Span, Token, TokenKind},
 };
 
+/// What kind of class, if any, encloses the code being parsed, to know where
+/// `this` and `super` may be used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /// How many loops enclose the statement being parsed, within the current
+    /// function.
+    loop_depth: usize,
+    /// Whether the statement being parsed is inside a function body.
+    in_function: bool,
+    /// The innermost class whose methods enclose the statement being parsed.
+    class: ClassKind,
+    /// Errors found so far, if they're being collected instead of reported.
+    errors: Option<Vec<(Token, String)>>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            loop_depth: 0,
+            in_function: false,
+            class: ClassKind::None,
+            errors: None,
+        }
     }
 
     pub fn parse(&mut self) -> Result<Vec<Stmt>, (Token, String)> {
@@ -42,9 +84,24 @@ impl Parser {
         Ok(statements)
     }
 
+    /// Parses every statement, returning the errors found along the way
+    /// instead of reporting them.
+    pub fn parse_all(mut self) -> (Vec<Stmt>, Vec<(Token, String)>) {
+        self.errors = Some(vec![]);
+        let statements = self.parse().unwrap_or_default();
+        (statements, self.errors.unwrap_or_default())
+    }
+
     fn declaration(&mut self) -> Option<Stmt> {
-        let value = if self.matches(&[TokenKind::Fun]) {
-            self.function("function")
+        let start = self.peek().span;
+        let value = if self.matches(&[TokenKind::Async]) {
+            self.async_function(start)
+        } else if self.matches(&[TokenKind::Class]) {
+            self.class_declaration()
+        } else if self.check(TokenKind::Fun) && self.check_next(TokenKind::Identifier) {
+            // Without a name, `fun` starts a lambda in an expression statement.
+            self.advance();
+            self.function("function", false, start).map(Stmt::Function)
         } else if self.matches(&[TokenKind::Var]) {
             self.var_declaration()
         } else {
@@ -59,61 +116,184 @@ impl Parser {
         }
     }
 
-    fn function(&mut self, kind: &str) -> Result<Stmt, (Token, String)> {
+    fn async_function(&mut self, start: Span) -> Result<Stmt, (Token, String)> {
+        self.expect(TokenKind::Fun, "after 'async'")?;
+        self.function("function", true, start).map(Stmt::Function)
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, (Token, String)> {
+        let start = self.previous().span;
+        let name = self
+            .consume(TokenKind::Identifier, "Expect class name.")?
+            .clone();
+        let superclass = if self.matches(&[TokenKind::Less]) {
+            let superclass = self
+                .consume(TokenKind::Identifier, "Expect superclass name.")?
+                .clone();
+            if superclass.lexeme == name.lexeme {
+                self.error(&superclass, "A class can't inherit from itself.");
+            }
+            Some(Variable {
+                meta: Meta::new(superclass.span),
+                name: superclass,
+            })
+        } else {
+            None
+        };
+        self.expect(TokenKind::LBrace, "before class body")?;
+
+        let kind = if superclass.is_some() {
+            ClassKind::Subclass
+        } else {
+            ClassKind::Class
+        };
+        let enclosing = std::mem::replace(&mut self.class, kind);
+        let methods = self.methods();
+        self.class = enclosing;
+        let (methods, class_methods) = methods?;
+        self.expect(TokenKind::RBrace, "after class body")?;
+
+        Ok(Stmt::Class(Class {
+            name,
+            superclass,
+            methods,
+            class_methods,
+            meta: self.meta(start),
+        }))
+    }
+
+    /// Parses a class body's methods, returning those declared with a
+    /// `class` prefix separately.
+    fn methods(&mut self) -> Result<(Vec<Function>, Vec<Function>), (Token, String)> {
+        let mut methods = vec![];
+        let mut class_methods = vec![];
+        while !self.check(TokenKind::RBrace) && !self.at_end() {
+            let start = self.peek().span;
+            if self.matches(&[TokenKind::Class]) {
+                class_methods.push(self.function("method", false, start)?);
+            } else {
+                methods.push(self.function("method", false, start)?);
+            }
+        }
+        Ok((methods, class_methods))
+    }
+
+    /// Parses a function after its `fun`, or a method, where `start` is the
+    /// span of its first token.
+    fn function(
+        &mut self,
+        kind: &str,
+        is_async: bool,
+        start: Span,
+    ) -> Result<Function, (Token, String)> {
         let name = self
             .consume(TokenKind::Identifier, &format!("Expect {} name.", kind))?
             .clone();
-        self.consume(
-            TokenKind::LParen,
-            &format!("Expect '{{' after {} name.", kind),
-        )?;
+        let is_getter = kind == "method" && !self.check(TokenKind::LParen);
+        let parameters = if is_getter {
+            vec![]
+        } else {
+            self.expect(TokenKind::LParen, &format!("after {} name", kind))?;
+            self.parameters()?
+        };
+        let return_annotation = self.return_annotation()?;
+        self.expect(TokenKind::LBrace, &format!("before {} body", kind))?;
+        let body = self.function_body()?;
+        Ok(Function {
+            name,
+            is_async,
+            is_getter,
+            params: parameters,
+            return_annotation,
+            body,
+            meta: self.meta(start),
+        })
+    }
+
+    /// Parses a parameter list after its `(`, up to and including the `)`.
+    fn parameters(&mut self) -> Result<Vec<Param>, (Token, String)> {
         let mut parameters = vec![];
         if !self.check(TokenKind::RParen) {
             loop {
                 if parameters.len() >= 255 {
-                    Self::error(self.peek(), "Can't have more than 255 parameters.");
+                    self.error(&self.peek().clone(), "Can't have more than 255 parameters.");
                 }
 
-                parameters.push(
-                    self.consume(TokenKind::Identifier, "Expect parameter name.")?
-                        .clone(),
-                );
+                let name = self
+                    .consume(TokenKind::Identifier, "Expect parameter name.")?
+                    .clone();
+                let annotation = self.annotation()?;
+                parameters.push(Param { name, annotation });
                 if !self.matches(&[TokenKind::Comma]) {
                     break;
                 }
             }
         }
-        self.consume(TokenKind::RParen, "Expect ')' after parameters.")?;
-        self.consume(
-            TokenKind::LBrace,
-            &format!("Expect '{{' before {} body.", kind),
-        )?;
-        let body = self.block()?;
-        Ok(Stmt::Function(Function {
-            name,
-            params: parameters,
-            body,
-        }))
+        self.expect(TokenKind::RParen, "after parameters")?;
+        Ok(parameters)
+    }
+
+    /// Parses a `: type` annotation, if there is one.
+    fn annotation(&mut self) -> Result<Option<Token>, (Token, String)> {
+        if self.matches(&[TokenKind::Colon]) {
+            return self.type_name("after ':'").map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Parses a `-> type` annotation, if there is one.
+    fn return_annotation(&mut self) -> Result<Option<Token>, (Token, String)> {
+        if self.matches(&[TokenKind::Arrow]) {
+            return self.type_name("after '->'").map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Types are names, checked only by `TypeChecker`. `nil` is a keyword,
+    /// so it's allowed specially.
+    fn type_name(&mut self, context: &str) -> Result<Token, (Token, String)> {
+        if self.matches(&[TokenKind::Identifier, TokenKind::Nil]) {
+            return Ok(self.previous().clone());
+        }
+        Err(self.error(&self.peek().clone(), &format!("Expect type {}.", context)))
+    }
+
+    /// Parses a function body after its `{`. Loops around the function don't
+    /// enclose its body.
+    fn function_body(&mut self) -> Result<Vec<Stmt>, (Token, String)> {
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let in_function = std::mem::replace(&mut self.in_function, true);
+        let body = self.block();
+        self.loop_depth = loop_depth;
+        self.in_function = in_function;
+        body
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, (Token, String)> {
+        let start = self.previous().span;
         let name = self
             .consume(TokenKind::Identifier, "Expect variable name.")?
             .clone();
+        let annotation = self.annotation()?;
 
         let mut initializer = None;
         if self.matches(&[TokenKind::Equal]) {
             initializer = Some(self.expression()?);
         }
 
-        self.consume(
-            TokenKind::Semicolon,
-            "Expect ';' after variable declaration.",
-        )?;
-        Ok(Stmt::Var(Var { name, initializer }))
+        self.expect(TokenKind::Semicolon, "after variable declaration")?;
+        Ok(Stmt::Var(Var {
+            name,
+            annotation,
+            initializer,
+            meta: self.meta(start),
+        }))
     }
 
     fn statement(&mut self) -> Result<Stmt, (Token, String)> {
+        if self.matches(&[TokenKind::Break, TokenKind::Continue]) {
+            return self.jump_statement();
+        }
         if self.matches(&[TokenKind::For]) {
             return self.for_statement();
         }
@@ -123,12 +303,17 @@ impl Parser {
         if self.matches(&[TokenKind::Print]) {
             return self.print_statement();
         }
+        if self.matches(&[TokenKind::Return]) {
+            return self.return_statement();
+        }
         if self.matches(&[TokenKind::While]) {
             return self.while_statement();
         }
         if self.matches(&[TokenKind::LBrace]) {
+            let start = self.previous().span;
             return Ok(Stmt::Block(Block {
                 statements: self.block()?,
+                meta: self.meta(start),
             }));
         }
 
@@ -136,7 +321,8 @@ impl Parser {
     }
 
     fn for_statement(&mut self) -> Result<Stmt, (Token, String)> {
-        self.consume(TokenKind::LParen, "Expect '(' after 'for'.")?;
+        let keyword = self.previous().clone();
+        self.expect(TokenKind::LParen, "after 'for'")?;
         let initializer = if self.matches(&[TokenKind::Semicolon]) {
             None
         } else if self.matches(&[TokenKind::Var]) {
@@ -149,40 +335,37 @@ impl Parser {
         } else {
             Some(self.expression()?)
         };
-        self.consume(TokenKind::Semicolon, "Expect ';' after for condition.")?;
+        self.expect(TokenKind::Semicolon, "after for condition")?;
         let increment = if self.check(TokenKind::RParen) {
             None
         } else {
             Some(self.expression()?)
         };
-        self.consume(TokenKind::RParen, "Expect ')' after for clauses.")?;
-        let mut body = self.statement()?;
-
-        if let Some(increment) = increment {
-            body = Stmt::Block(Block {
-                statements: vec![
-                    body,
-                    Stmt::Expression(Expression {
-                        expression: increment,
-                    }),
-                ],
-            });
-        }
+        self.expect(TokenKind::RParen, "after for clauses")?;
+        let mut body = self.loop_body()?;
 
+        // A missing condition stands in for the `for` keyword, having no
+        // source of its own.
         let condition = condition.unwrap_or_else(|| {
             Expr::Literal(Literal {
                 value: Object::new_bool(true),
+                meta: Meta::new(keyword.span),
             })
         });
 
+        let meta = self.meta(keyword.span);
         body = Stmt::While(While {
+            keyword,
             condition,
             body: Box::new(body),
+            increment,
+            meta,
         });
 
         if let Some(initializer) = initializer {
             body = Stmt::Block(Block {
                 statements: vec![initializer, body],
+                meta: Meta::new(meta.span),
             });
         }
 
@@ -190,9 +373,10 @@ impl Parser {
     }
 
     fn if_statement(&mut self) -> Result<Stmt, (Token, String)> {
-        self.consume(TokenKind::LParen, "Expect '(' after 'if'.")?;
+        let start = self.previous().span;
+        self.expect(TokenKind::LParen, "after 'if'")?;
         let condition = self.expression()?;
-        self.consume(TokenKind::RParen, "Expect ')' after if condition.")?;
+        self.expect(TokenKind::RParen, "after if condition")?;
 
         let then_branch = Box::new(self.statement()?);
         let else_branch = if self.matches(&[TokenKind::Else]) {
@@ -200,12 +384,13 @@ impl Parser {
         } else {
             None
         }
-        .map(|eb| Box::new(eb));
+        .map(Box::new);
 
         Ok(Stmt::If(If {
             condition,
             then_branch,
             else_branch,
+            meta: self.meta(start),
         }))
     }
 
@@ -218,54 +403,171 @@ impl Parser {
             }
         }
 
-        self.consume(TokenKind::RBrace, "Expect '}' after block.")?;
+        self.expect(TokenKind::RBrace, "after block")?;
         Ok(statements)
     }
 
     fn print_statement(&mut self) -> Result<Stmt, (Token, String)> {
+        let start = self.previous().span;
         let value = self.expression()?;
-        self.consume(TokenKind::Semicolon, "Expect ';' after value.")?;
-        Ok(Stmt::Print(Print { expression: value }))
+        self.expect(TokenKind::Semicolon, "after value")?;
+        Ok(Stmt::Print(Print {
+            expression: value,
+            meta: self.meta(start),
+        }))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, (Token, String)> {
+        let keyword = self.previous().clone();
+        if !self.in_function {
+            // report, don't stop parsing
+            self.error(&keyword, "Can't return from top-level code.");
+        }
+        let value = if self.check(TokenKind::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.expect(TokenKind::Semicolon, "after return value")?;
+        let meta = self.meta(keyword.span);
+        Ok(Stmt::Return(Return {
+            keyword,
+            value,
+            meta,
+        }))
     }
 
     fn while_statement(&mut self) -> Result<Stmt, (Token, String)> {
-        self.consume(TokenKind::LParen, "Expect '(' after 'while'.")?;
+        let keyword = self.previous().clone();
+        self.expect(TokenKind::LParen, "after 'while'")?;
         let condition = self.expression()?;
-        self.consume(TokenKind::RParen, "Expect ')' after while condition")?;
+        self.expect(TokenKind::RParen, "after while condition")?;
 
-        let body = Box::new(self.statement()?);
+        let body = Box::new(self.loop_body()?);
 
-        Ok(Stmt::While(While { condition, body }))
+        let meta = self.meta(keyword.span);
+        Ok(Stmt::While(While {
+            keyword,
+            condition,
+            body,
+            increment: None,
+            meta,
+        }))
+    }
+
+    fn loop_body(&mut self) -> Result<Stmt, (Token, String)> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn jump_statement(&mut self) -> Result<Stmt, (Token, String)> {
+        let keyword = self.previous().clone();
+        if self.loop_depth == 0 {
+            // report, don't stop parsing
+            self.error(
+                &keyword,
+                &format!("Can't use '{}' outside of a loop.", keyword.lexeme),
+            );
+        }
+        self.expect(TokenKind::Semicolon, &format!("after '{}'", keyword.lexeme))?;
+        let meta = self.meta(keyword.span);
+        Ok(match keyword.kind {
+            TokenKind::Break => Stmt::Break(Break { keyword, meta }),
+            _ => Stmt::Continue(Continue { keyword, meta }),
+        })
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, (Token, String)> {
+        let start = self.peek().span;
         let expr = self.expression()?;
-        self.consume(TokenKind::Semicolon, "Expect ';' after expression.")?;
-        Ok(Stmt::Expression(Expression { expression: expr }))
+        self.expect(TokenKind::Semicolon, "after expression")?;
+        Ok(Stmt::Expression(Expression {
+            expression: expr,
+            meta: self.meta(start),
+        }))
     }
 
     fn expression(&mut self) -> Result<Expr, (Token, String)> {
-        self.assignment()
+        self.comma()
+    }
+
+    /// The comma operator, which evaluates both operands and yields the right.
+    /// Argument lists parse `assignment` instead, so their commas aren't taken
+    /// for it.
+    fn comma(&mut self) -> Result<Expr, (Token, String)> {
+        let mut expr = self.assignment()?;
+
+        while self.matches(&[TokenKind::Comma]) {
+            let operator = self.previous().clone();
+            let right = self.assignment()?;
+            expr = Expr::Binary(Binary {
+                meta: Meta::new(expr.span().to(right.span())),
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
     }
 
     fn assignment(&mut self) -> Result<Expr, (Token, String)> {
-        let expr = self.or()?;
+        let expr = self.ternary()?;
 
         if self.matches(&[TokenKind::Equal]) {
             let equals = self.previous().clone();
             let value = Box::new(self.assignment()?);
 
-            if let Expr::Variable(v) = &expr {
-                let name = v.name.clone();
-                return Ok(Expr::Assign(Assign { name, value }));
-            }
-
-            Self::error(&equals, "Invalid assignment target.");
+            let span = expr.span().to(value.span());
+            return match expr {
+                Expr::Variable(v) => Ok(Expr::Assign(Assign {
+                    name: v.name,
+                    value,
+                    meta: Meta::new(span),
+                })),
+                Expr::Get(g) => Ok(Expr::Set(Set {
+                    object: g.object,
+                    name: g.name,
+                    value,
+                    meta: Meta::new(span),
+                })),
+                Expr::Index(i) => Ok(Expr::IndexSet(IndexSet {
+                    object: i.object,
+                    bracket: i.bracket,
+                    index: i.index,
+                    value,
+                    meta: Meta::new(span),
+                })),
+                expr => {
+                    self.error(&equals, "Invalid assignment target.");
+                    Ok(expr)
+                }
+            };
         }
 
         Ok(expr)
     }
 
+    fn ternary(&mut self) -> Result<Expr, (Token, String)> {
+        let condition = self.or()?;
+
+        if self.matches(&[TokenKind::Question]) {
+            let then_branch = Box::new(self.expression()?);
+            self.expect(TokenKind::Colon, "after then branch of conditional")?;
+            let else_branch = Box::new(self.ternary()?);
+            return Ok(Expr::Ternary(Ternary {
+                meta: Meta::new(condition.span().to(else_branch.span())),
+                condition: Box::new(condition),
+                then_branch,
+                else_branch,
+            }));
+        }
+
+        Ok(condition)
+    }
+
     fn or(&mut self) -> Result<Expr, (Token, String)> {
         let mut expr = self.and()?;
 
@@ -273,6 +575,7 @@ impl Parser {
             let operator = self.previous().clone();
             let right = Box::new(self.and()?);
             expr = Expr::Logical(Logical {
+                meta: Meta::new(expr.span().to(right.span())),
                 left: Box::new(expr),
                 operator,
                 right,
@@ -289,6 +592,7 @@ impl Parser {
             let operator = self.previous().clone();
             let right = Box::new(self.equality()?);
             expr = Expr::Logical(Logical {
+                meta: Meta::new(expr.span().to(right.span())),
                 left: Box::new(expr),
                 operator,
                 right,
@@ -305,6 +609,7 @@ impl Parser {
             let operator = self.previous().clone();
             let right = self.comparison()?;
             expr = Expr::Binary(Binary {
+                meta: Meta::new(expr.span().to(right.span())),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -326,6 +631,7 @@ impl Parser {
             let operator = self.previous().clone();
             let right = self.term()?;
             expr = Expr::Binary(Binary {
+                meta: Meta::new(expr.span().to(right.span())),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -342,6 +648,7 @@ impl Parser {
             let operator = self.previous().clone();
             let right = self.factor()?;
             expr = Expr::Binary(Binary {
+                meta: Meta::new(expr.span().to(right.span())),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -358,6 +665,7 @@ impl Parser {
             let operator = self.previous().clone();
             let right = self.unary()?;
             expr = Expr::Binary(Binary {
+                meta: Meta::new(expr.span().to(right.span())),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -368,16 +676,92 @@ impl Parser {
     }
 
     fn unary(&mut self) -> Result<Expr, (Token, String)> {
+        if self.matches(&[TokenKind::PlusPlus, TokenKind::MinusMinus]) {
+            return self.increment();
+        }
         if self.matches(&[TokenKind::Bang, TokenKind::Minus]) {
             let operator = self.previous().clone();
             let right = self.unary()?;
             return Ok(Expr::Unary(Unary {
+                meta: Meta::new(operator.span.to(right.span())),
+                operator,
+                right: Box::new(right),
+            }));
+        }
+        if self.matches(&[TokenKind::Await]) {
+            let keyword = self.previous().clone();
+            let expression = self.unary()?;
+            return Ok(Expr::Await(Await {
+                meta: Meta::new(keyword.span.to(expression.span())),
+                keyword,
+                expression: Box::new(expression),
+            }));
+        }
+
+        self.power()
+    }
+
+    /// Lowers `++x` to `x = x + 1` and `--x` to `x = x - 1`, so its value is
+    /// the variable's new value.
+    fn increment(&mut self) -> Result<Expr, (Token, String)> {
+        let operator = self.previous().clone();
+        let target = self.unary()?;
+
+        if let Expr::Variable(v) = &target {
+            let (kind, lexeme) = if operator.kind == TokenKind::PlusPlus {
+                (TokenKind::Plus, "+")
+            } else {
+                (TokenKind::Minus, "-")
+            };
+            // Keeps the span of the `++` or `--`, for runtime errors to point at.
+            let operator = Token {
+                kind,
+                lexeme: lexeme.into(),
+                ..operator
+            };
+            let span = operator.span.to(target.span());
+            return Ok(Expr::Assign(Assign {
+                name: v.name.clone(),
+                value: Box::new(Expr::Binary(Binary {
+                    left: Box::new(target.clone()),
+                    right: Box::new(Expr::Literal(Literal {
+                        value: Object::new_number(1.0),
+                        meta: Meta::new(operator.span),
+                    })),
+                    operator,
+                    meta: Meta::new(span),
+                })),
+                meta: Meta::new(span),
+            }));
+        }
+
+        let kind = if operator.kind == TokenKind::PlusPlus {
+            "increment"
+        } else {
+            "decrement"
+        };
+        self.error(&operator, &format!("Invalid {} target.", kind));
+        Ok(target)
+    }
+
+    /// `**` binds tighter than a prefix operator on its left, so `-2 ** 2` is
+    /// -4, but its right operand may be one, as in `2 ** -1`. It is
+    /// right-associative.
+    fn power(&mut self) -> Result<Expr, (Token, String)> {
+        let expr = self.call()?;
+
+        if self.matches(&[TokenKind::StarStar]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(Expr::Binary(Binary {
+                meta: Meta::new(expr.span().to(right.span())),
+                left: Box::new(expr),
                 operator,
                 right: Box::new(right),
             }));
         }
 
-        self.call()
+        Ok(expr)
     }
 
     fn call(&mut self) -> Result<Expr, (Token, String)> {
@@ -386,6 +770,24 @@ impl Parser {
         loop {
             if self.matches(&[TokenKind::LParen]) {
                 expr = self.finish_call(expr)?;
+            } else if self.matches(&[TokenKind::Dot]) {
+                let name = self
+                    .consume(TokenKind::Identifier, "Expect property name after '.'.")?
+                    .clone();
+                expr = Expr::Get(Get {
+                    meta: Meta::new(expr.span().to(name.span)),
+                    object: Box::new(expr),
+                    name,
+                });
+            } else if self.matches(&[TokenKind::LBracket]) {
+                let index = self.expression()?;
+                let bracket = self.expect(TokenKind::RBracket, "after index")?.clone();
+                expr = Expr::Index(Index {
+                    meta: Meta::new(expr.span().to(bracket.span)),
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                });
             } else {
                 break;
             }
@@ -400,20 +802,19 @@ impl Parser {
             loop {
                 if arguments.len() >= 255 {
                     // report, don't stop parsing
-                    Self::error(self.peek(), "Can't have more than 255 arguments");
+                    self.error(&self.peek().clone(), "Can't have more than 255 arguments");
                 }
-                arguments.push(self.expression()?);
+                arguments.push(self.assignment()?);
                 if !self.matches(&[TokenKind::Comma]) {
                     break;
                 }
             }
         }
 
-        let paren = self
-            .consume(TokenKind::RParen, "Expect ')' after arguments.")?
-            .clone();
+        let paren = self.expect(TokenKind::RParen, "after arguments")?.clone();
 
         Ok(Expr::Call(Call {
+            meta: Meta::new(callee.span().to(paren.span)),
             callee: Box::new(callee),
             paren,
             arguments,
@@ -424,40 +825,140 @@ impl Parser {
         if self.matches(&[TokenKind::False]) {
             return Ok(Expr::Literal(Literal {
                 value: Object::new_bool(false),
+                meta: Meta::new(self.previous().span),
             }));
         }
         if self.matches(&[TokenKind::True]) {
             return Ok(Expr::Literal(Literal {
                 value: Object::new_bool(true),
+                meta: Meta::new(self.previous().span),
             }));
         }
         if self.matches(&[TokenKind::Nil]) {
             return Ok(Expr::Literal(Literal {
                 value: Object::nil(),
+                meta: Meta::new(self.previous().span),
             }));
         }
 
-        if self.matches(&[TokenKind::Number, TokenKind::String]) {
+        if self.matches(&[TokenKind::Number, TokenKind::String, TokenKind::Bytes]) {
             return Ok(Expr::Literal(Literal {
                 value: self.previous().literal.clone(),
+                meta: Meta::new(self.previous().span),
+            }));
+        }
+
+        if self.matches(&[TokenKind::Fun]) {
+            return self.lambda();
+        }
+
+        if self.matches(&[TokenKind::Super]) {
+            let keyword = self.previous().clone();
+            match self.class {
+                ClassKind::None => {
+                    self.error(&keyword, "Can't use 'super' outside of a class.");
+                }
+                ClassKind::Class => {
+                    self.error(&keyword, "Can't use 'super' in a class with no superclass.");
+                }
+                ClassKind::Subclass => {}
+            }
+            self.expect(TokenKind::Dot, "after 'super'")?;
+            let method = self
+                .consume(TokenKind::Identifier, "Expect superclass method name.")?
+                .clone();
+            return Ok(Expr::Super(Super {
+                meta: self.meta(keyword.span),
+                keyword,
+                method,
+            }));
+        }
+
+        if self.matches(&[TokenKind::This]) {
+            let keyword = self.previous().clone();
+            if self.class == ClassKind::None {
+                self.error(&keyword, "Can't use 'this' outside of a class.");
+            }
+            return Ok(Expr::This(This {
+                meta: Meta::new(keyword.span),
+                keyword,
             }));
         }
 
         if self.matches(&[TokenKind::Identifier]) {
             return Ok(Expr::Variable(Variable {
                 name: self.previous().clone(),
+                meta: Meta::new(self.previous().span),
+            }));
+        }
+
+        if self.matches(&[TokenKind::LBracket]) {
+            let bracket = self.previous().clone();
+            let mut elements = vec![];
+            if !self.check(TokenKind::RBracket) {
+                loop {
+                    elements.push(self.assignment()?);
+                    if !self.matches(&[TokenKind::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.expect(TokenKind::RBracket, "after list elements")?;
+            return Ok(Expr::List(List {
+                meta: self.meta(bracket.span),
+                bracket,
+                elements,
+            }));
+        }
+
+        if self.matches(&[TokenKind::LBrace]) {
+            let brace = self.previous().clone();
+            let mut entries = vec![];
+            if !self.check(TokenKind::RBrace) {
+                loop {
+                    let key = self.assignment()?;
+                    self.expect(TokenKind::Colon, "after map key")?;
+                    entries.push((key, self.assignment()?));
+                    if !self.matches(&[TokenKind::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.expect(TokenKind::RBrace, "after map entries")?;
+            return Ok(Expr::Map(Map {
+                meta: self.meta(brace.span),
+                brace,
+                entries,
             }));
         }
 
         if self.matches(&[TokenKind::LParen]) {
+            let start = self.previous().span;
             let expr = self.expression()?;
-            self.consume(TokenKind::RParen, "Expect ')' after expression.")?;
+            self.expect(TokenKind::RParen, "after expression")?;
             return Ok(Expr::Grouping(Grouping {
                 expression: Box::new(expr),
+                meta: self.meta(start),
             }));
         }
 
-        Err(Self::error(self.peek(), "Expect expression."))
+        Err(self.error(&self.peek().clone(), "Expect expression."))
+    }
+
+    fn lambda(&mut self) -> Result<Expr, (Token, String)> {
+        let keyword = self.previous().clone();
+        self.expect(TokenKind::LParen, "after 'fun'")?;
+        let params = self.parameters()?;
+        let return_annotation = self.return_annotation()?;
+        self.expect(TokenKind::LBrace, "before lambda body")?;
+        let body = self.function_body()?;
+        Ok(Expr::Lambda(Lambda {
+            meta: self.meta(keyword.span),
+            keyword,
+            params,
+            return_annotation,
+            body,
+        }))
     }
 
     fn consume(&mut self, kind: TokenKind, message: &str) -> Result<&Token, (Token, String)> {
@@ -465,7 +966,12 @@ impl Parser {
             return Ok(self.advance());
         }
 
-        Err(Self::error(self.peek(), message))
+        Err(self.error(&self.peek().clone(), message))
+    }
+
+    /// Consumes a token of `kind`, or reports "Expect <kind> <context>.".
+    fn expect(&mut self, kind: TokenKind, context: &str) -> Result<&Token, (Token, String)> {
+        self.consume(kind, &format!("Expect {} {}.", kind, context))
     }
 
     fn synchronize(&mut self) {
@@ -477,7 +983,8 @@ impl Parser {
             }
 
             match self.peek().kind {
-                TokenKind::Class
+                TokenKind::Async
+                | TokenKind::Class
                 | TokenKind::Fun
                 | TokenKind::Var
                 | TokenKind::For
@@ -492,11 +999,22 @@ impl Parser {
         }
     }
 
-    fn error(token: &Token, message: &str) -> (Token, String) {
-        crate::error_at_token(token, message);
+    /// Reports an error at `token`, or keeps it if errors are being
+    /// collected.
+    fn error(&mut self, token: &Token, message: &str) -> (Token, String) {
+        match &mut self.errors {
+            Some(errors) => errors.push((token.clone(), message.to_string())),
+            None => crate::error_at_token(token, message),
+        }
         (token.clone(), message.to_string())
     }
 
+    /// Gives a node spanning from `start` to the end of the token just
+    /// consumed.
+    fn meta(&self, start: Span) -> Meta {
+        Meta::new(start.to(self.previous().span))
+    }
+
     fn matches(&mut self, kinds: &[TokenKind]) -> bool {
         if kinds.iter().cloned().any(|k| self.check(k)) {
             self.advance();
@@ -514,6 +1032,13 @@ impl Parser {
         }
     }
 
+    /// Whether the token after the next one is of `kind`.
+    fn check_next(&self, kind: TokenKind) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|t| t.kind == kind)
+    }
+
     fn advance(&mut self) -> &Token {
         if !self.at_end() {
             self.current += 1;
@@ -0,0 +1,369 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    environment::Environment,
+    expr::{self, Expr},
+    node::NodeId,
+    object::{LoxFunction, LoxObject, Object},
+    stmt::{self, Stmt},
+    token::Token,
+};
+
+/// Natives whose result depends only on their arguments, and which change
+/// nothing outside of it.
+const PURE_NATIVES: &[&str] = &[
+    "len",
+    "bigint",
+    "decimal",
+    "complex",
+    "re",
+    "im",
+    "abs",
+    "arg",
+    "polar",
+    "toPolar",
+    "vec2",
+    "vec3",
+    "mat2",
+    "mat3",
+    "dot",
+    "cross",
+    "matMul",
+    "toString",
+    "compareNatural",
+    "pathJoin",
+    "basename",
+    "dirname",
+    "extension",
+    "slice",
+    "encode",
+    "decode",
+    "utf8Encode",
+    "utf8Decode",
+    "base64Encode",
+    "base64Decode",
+    "hexEncode",
+    "hexDecode",
+    "csvParse",
+    "csvParseRecords",
+    "csvWrite",
+    "render",
+    "tomlParse",
+    "yamlParse",
+    "pvec",
+    "pdict",
+    "with",
+    "without",
+    "sortedMap",
+    "heap",
+    "deque",
+    "firstKey",
+    "lastKey",
+    "keysBetween",
+    "keys",
+];
+
+/// Decides before a function runs whether it is pure: it may read globals and
+/// captured variables and call pure functions, but not print, assign either,
+/// schedule tasks or call anything it can't see into. Calls to locals are
+/// rejected, since their value isn't known until the function runs.
+pub struct PurityChecker<'a> {
+    globals: &'a Environment,
+    pure_natives: Vec<&'a LoxObject>,
+    /// Functions already checked or being checked, so recursion terminates.
+    checked: HashSet<NodeId>,
+    scopes: Vec<HashSet<String>>,
+    function: String,
+    /// The variables captured by the function being checked.
+    captured: Vec<(String, LoxObject)>,
+    impurity: Option<String>,
+}
+
+impl<'a> PurityChecker<'a> {
+    /// Checks `function`, returning why it isn't pure if it isn't.
+    pub fn check(function: &LoxObject, globals: &'a Environment) -> Result<(), String> {
+        let pure_natives = globals
+            .values()
+            .filter(|(name, _)| PURE_NATIVES.contains(&name.as_str()))
+            .map(|(_, value)| value)
+            .collect();
+        let mut checker = PurityChecker {
+            globals,
+            pure_natives,
+            checked: HashSet::new(),
+            scopes: vec![],
+            function: String::new(),
+            captured: vec![],
+            impurity: None,
+        };
+
+        match &*function.read().unwrap() {
+            Object::BuiltinFunction(..) => {
+                if !checker.is_pure_native(function) {
+                    return Err(String::from("the native function may have side effects"));
+                }
+            }
+            Object::Function(f) => checker.check_function(f),
+            _ => return Err(String::from("it isn't a function")),
+        }
+
+        match checker.impurity {
+            Some(impurity) => Err(impurity),
+            None => Ok(()),
+        }
+    }
+
+    fn check_function(&mut self, function: &LoxFunction) {
+        if !self.checked.insert(function.id) {
+            return;
+        }
+        if function.is_async {
+            self.impure("is async");
+            return;
+        }
+
+        let enclosing = std::mem::take(&mut self.scopes);
+        let name = match &function.name {
+            Some(name) => name.lexeme.to_string(),
+            None => String::from("<fn>"),
+        };
+        let name = std::mem::replace(&mut self.function, name);
+        let captured = std::mem::replace(&mut self.captured, function.captured());
+        self.scopes.push(
+            function
+                .params
+                .iter()
+                .map(|p| p.name.lexeme.to_string())
+                .collect(),
+        );
+        self.check_block(&function.body);
+        self.scopes = enclosing;
+        self.function = name;
+        self.captured = captured;
+    }
+
+    fn check_block(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashSet::new());
+        statements.iter().for_each(|s| s.accept(self));
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.to_string());
+        }
+    }
+
+    fn is_pure_native(&self, native: &LoxObject) -> bool {
+        self.pure_natives.iter().any(|n| Arc::ptr_eq(n, native))
+    }
+
+    fn is_local(&self, name: &Token) -> bool {
+        self.scopes.iter().any(|s| s.contains(&*name.lexeme))
+    }
+
+    /// Records the first reason the function isn't pure.
+    fn captured_value(&self, name: &Token) -> Option<LoxObject> {
+        self.captured
+            .iter()
+            .find(|(captured, _)| *captured == *name.lexeme)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn impure(&mut self, what: &str) {
+        if self.impurity.is_none() {
+            self.impurity = Some(format!("'{}' {}", self.function, what));
+        }
+    }
+
+    fn check_callee(&mut self, callee: &Expr) {
+        let name = match callee {
+            Expr::Variable(v) if !self.is_local(&v.name) => &v.name,
+            _ => return self.impure("calls a function it can't check"),
+        };
+        let value = match self.captured_value(name) {
+            Some(value) => value,
+            None => match self.globals.get(name) {
+                Ok(value) => value,
+                // Calling it will fail before anything happens.
+                Err(_) => return,
+            },
+        };
+
+        match &*value.read().unwrap() {
+            Object::Function(f) => self.check_function(f),
+            // Making an instance runs its initializer, if it has one.
+            Object::Class(c) => {
+                if let Some(init) = c.find_method("init") {
+                    self.check_function(init);
+                }
+            }
+            Object::BuiltinFunction(..) if !self.is_pure_native(&value) => {
+                self.impure(&format!("calls '{}'", name.lexeme))
+            }
+            _ => {}
+        };
+    }
+}
+
+impl expr::Visitor<()> for PurityChecker<'_> {
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) {
+        expr.value.accept(self);
+        if !self.is_local(&expr.name) {
+            let kind = if self.captured_value(&expr.name).is_some() {
+                "captured variable"
+            } else {
+                "global"
+            };
+            self.impure(&format!("assigns the {} '{}'", kind, expr.name.lexeme));
+        }
+    }
+
+    fn visit_await_expr(&mut self, _expr: &expr::Await) {
+        self.impure("awaits a task");
+    }
+
+    fn visit_binary_expr(&mut self, expr: &expr::Binary) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_call_expr(&mut self, expr: &expr::Call) {
+        expr.callee.accept(self);
+        expr.arguments.iter().for_each(|a| a.accept(self));
+        self.check_callee(&expr.callee);
+    }
+
+    fn visit_get_expr(&mut self, expr: &expr::Get) {
+        expr.object.accept(self);
+        // It can't tell which class the object is, so whether this runs a
+        // getter.
+        self.impure(&format!(
+            "reads the property '{}', which may be a getter",
+            expr.name.lexeme
+        ));
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_index_expr(&mut self, expr: &expr::Index) {
+        expr.object.accept(self);
+        expr.index.accept(self);
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) {
+        expr.object.accept(self);
+        expr.index.accept(self);
+        expr.value.accept(self);
+        self.impure("sets an element");
+    }
+
+    fn visit_lambda_expr(&mut self, _expr: &expr::Lambda) {
+        // Only runs if called, and calls to locals are already rejected.
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) {
+        expr.elements.iter().for_each(|e| e.accept(self));
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &expr::Literal) {}
+
+    fn visit_logical_expr(&mut self, expr: &expr::Logical) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_map_expr(&mut self, expr: &expr::Map) {
+        for (key, value) in &expr.entries {
+            key.accept(self);
+            value.accept(self);
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) {
+        expr.object.accept(self);
+        expr.value.accept(self);
+        self.impure(&format!("sets the property '{}'", expr.name.lexeme));
+    }
+
+    fn visit_super_expr(&mut self, expr: &expr::Super) {
+        self.impure(&format!(
+            "reads 'super.{}', which may be a getter",
+            expr.method.lexeme
+        ));
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) {
+        expr.condition.accept(self);
+        expr.then_branch.accept(self);
+        expr.else_branch.accept(self);
+    }
+
+    fn visit_this_expr(&mut self, _expr: &expr::This) {}
+
+    fn visit_unary_expr(&mut self, expr: &expr::Unary) {
+        expr.right.accept(self);
+    }
+
+    fn visit_variable_expr(&mut self, _expr: &expr::Variable) {}
+}
+
+impl stmt::Visitor<()> for PurityChecker<'_> {
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) {
+        self.check_block(&stmt.statements);
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &stmt::Break) {}
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        self.declare(&stmt.name);
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &stmt::Continue) {}
+
+    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        self.declare(&stmt.name);
+        // Nested functions only run if called, and calls to locals are
+        // already rejected.
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &stmt::If) {
+        stmt.condition.accept(self);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &stmt::Print) {
+        stmt.expression.accept(self);
+        self.impure("prints");
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &stmt::Return) {
+        if let Some(value) = &stmt.value {
+            value.accept(self);
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) {
+        if let Some(initializer) = &stmt.initializer {
+            initializer.accept(self);
+        }
+        self.declare(&stmt.name);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &stmt::While) {
+        stmt.condition.accept(self);
+        stmt.body.accept(self);
+        if let Some(increment) = &stmt.increment {
+            increment.accept(self);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{Read, Write},
+};
+
+const MAGIC: &[u8] = b"LOXTRACE1";
+
+/// Records the results of nondeterministic natives to a trace file, or feeds
+/// results from a trace back to them, so that a run can be reproduced
+/// exactly. Replays are only deterministic for single-threaded scripts.
+pub enum Recorder {
+    Live,
+    Recording(File),
+    Replaying(VecDeque<(String, Vec<u8>)>),
+}
+
+impl Recorder {
+    pub fn record_to(path: &str) -> std::io::Result<Self> {
+        let mut file = File::create(path)?;
+        file.write_all(MAGIC)?;
+        Ok(Recorder::Recording(file))
+    }
+
+    /// Loads a trace written by `record_to`. Each event is the name of its
+    /// source, then its payload, each prefixed by a little-endian u32 length.
+    pub fn replay_from(path: &str) -> std::io::Result<Self> {
+        let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid trace file");
+
+        let mut data = vec![];
+        File::open(path)?.read_to_end(&mut data)?;
+        let mut data = data.strip_prefix(MAGIC).ok_or_else(invalid)?;
+
+        let mut events = VecDeque::new();
+        while !data.is_empty() {
+            let source = take_chunk(&mut data).ok_or_else(invalid)?;
+            let payload = take_chunk(&mut data).ok_or_else(invalid)?;
+            let source = String::from_utf8(source.to_vec()).map_err(|_| invalid())?;
+            events.push_back((source, payload.to_vec()));
+        }
+        Ok(Recorder::Replaying(events))
+    }
+
+    /// Returns the result of `live` while recording it, or the next recorded
+    /// result when replaying. `source` names the native asking, so a replay
+    /// which has diverged from its recording is caught.
+    pub fn bytes(
+        &mut self,
+        source: &str,
+        live: impl FnOnce() -> Vec<u8>,
+    ) -> Result<Vec<u8>, String> {
+        match self {
+            Recorder::Live => Ok(live()),
+            Recorder::Recording(file) => {
+                let payload = live();
+                let mut event = vec![];
+                put_chunk(&mut event, source.as_bytes());
+                put_chunk(&mut event, &payload);
+                file.write_all(&event)
+                    .map_err(|e| format!("Could not write trace: {}.", e))?;
+                Ok(payload)
+            }
+            Recorder::Replaying(events) => match events.pop_front() {
+                Some((recorded, payload)) if recorded == source => Ok(payload),
+                Some((recorded, _)) => Err(format!(
+                    "Replay diverged: expected a call to '{}' but the script called '{}'.",
+                    recorded, source
+                )),
+                None => Err(format!(
+                    "Replay diverged: the trace ended before a call to '{}'.",
+                    source
+                )),
+            },
+        }
+    }
+
+    pub fn number(&mut self, source: &str, live: impl FnOnce() -> f64) -> Result<f64, String> {
+        let payload = self.bytes(source, || live().to_le_bytes().to_vec())?;
+        let mut bits = [0; 8];
+        if payload.len() != bits.len() {
+            return Err(format!("Trace entry for '{}' is not a number.", source));
+        }
+        bits.copy_from_slice(&payload);
+        Ok(f64::from_le_bytes(bits))
+    }
+}
+
+fn put_chunk(out: &mut Vec<u8>, chunk: &[u8]) {
+    out.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+    out.extend_from_slice(chunk);
+}
+
+fn take_chunk<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    if data.len() < 4 {
+        return None;
+    }
+    let mut len = [0; 4];
+    len.copy_from_slice(&data[..4]);
+    let len = u32::from_le_bytes(len) as usize;
+    if data.len() < 4 + len {
+        return None;
+    }
+    let chunk = &data[4..4 + len];
+    *data = &data[4 + len..];
+    Some(chunk)
+}
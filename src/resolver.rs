@@ -0,0 +1,227 @@
+use std::collections::HashSet;
+
+use crate::{
+    expr,
+    interpreter::Interpreter,
+    node::NodeId,
+    stmt::{self, Param, Stmt},
+    token::Token,
+};
+
+/// Works out which declaration each variable reference means before a script
+/// runs, telling the interpreter how many environments out from the one
+/// running the reference to find it in.
+///
+/// A reference means the innermost declaration before it in the blocks and
+/// functions around it, fixed where it's written rather than looked up by
+/// name when it runs. So a function keeps seeing the variables it saw when
+/// it was declared, even if a block declares another by the same name later.
+/// References to anything not declared in an enclosing scope are left for
+/// the interpreter to look up in the globals.
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    /// One scope per enclosing block or function. The globals aren't tracked.
+    scopes: Vec<HashSet<String>>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn resolve(interpreter: &'a mut Interpreter, statements: &[Stmt]) {
+        let mut resolver = Resolver {
+            interpreter,
+            scopes: vec![],
+        };
+        statements.iter().for_each(|s| s.accept(&mut resolver));
+    }
+
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.to_string());
+        }
+    }
+
+    fn resolve_local(&mut self, id: NodeId, name: &Token) {
+        let depth = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|s| s.contains(&*name.lexeme));
+        if let Some(depth) = depth {
+            self.interpreter.resolve(id, depth);
+        }
+    }
+
+    /// Parameters and the body's top-level declarations share a scope, as
+    /// they share an environment when the function is called.
+    fn resolve_function(&mut self, params: &[Param], body: &[Stmt]) {
+        self.scopes
+            .push(params.iter().map(|p| p.name.lexeme.to_string()).collect());
+        body.iter().for_each(|s| s.accept(self));
+        self.scopes.pop();
+    }
+}
+
+impl expr::Visitor<()> for Resolver<'_> {
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) {
+        expr.value.accept(self);
+        self.resolve_local(expr.meta.id, &expr.name);
+    }
+
+    fn visit_await_expr(&mut self, expr: &expr::Await) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &expr::Binary) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_call_expr(&mut self, expr: &expr::Call) {
+        expr.callee.accept(self);
+        expr.arguments.iter().for_each(|a| a.accept(self));
+    }
+
+    fn visit_get_expr(&mut self, expr: &expr::Get) {
+        expr.object.accept(self);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_index_expr(&mut self, expr: &expr::Index) {
+        expr.object.accept(self);
+        expr.index.accept(self);
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) {
+        expr.value.accept(self);
+        expr.object.accept(self);
+        expr.index.accept(self);
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) {
+        self.resolve_function(&expr.params, &expr.body);
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) {
+        expr.elements.iter().for_each(|e| e.accept(self));
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &expr::Literal) {}
+
+    fn visit_logical_expr(&mut self, expr: &expr::Logical) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_map_expr(&mut self, expr: &expr::Map) {
+        for (key, value) in &expr.entries {
+            key.accept(self);
+            value.accept(self);
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) {
+        expr.value.accept(self);
+        expr.object.accept(self);
+    }
+
+    fn visit_super_expr(&mut self, expr: &expr::Super) {
+        self.resolve_local(expr.meta.id, &expr.keyword);
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) {
+        expr.condition.accept(self);
+        expr.then_branch.accept(self);
+        expr.else_branch.accept(self);
+    }
+
+    fn visit_this_expr(&mut self, expr: &expr::This) {
+        self.resolve_local(expr.meta.id, &expr.keyword);
+    }
+
+    fn visit_unary_expr(&mut self, expr: &expr::Unary) {
+        expr.right.accept(self);
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) {
+        self.resolve_local(expr.meta.id, &expr.name);
+    }
+}
+
+impl stmt::Visitor<()> for Resolver<'_> {
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) {
+        self.scopes.push(HashSet::new());
+        stmt.statements.iter().for_each(|s| s.accept(self));
+        self.scopes.pop();
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &stmt::Break) {}
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        self.declare(&stmt.name);
+        if let Some(superclass) = &stmt.superclass {
+            self.resolve_local(superclass.meta.id, &superclass.name);
+            // The methods' closure, where the interpreter defines `super`.
+            self.scopes.push(HashSet::from([String::from("super")]));
+        }
+        // Where accessing a method on an instance, or a class method on the
+        // class, binds `this`.
+        self.scopes.push(HashSet::from([String::from("this")]));
+        stmt.methods
+            .iter()
+            .chain(&stmt.class_methods)
+            .for_each(|m| self.resolve_function(&m.params, &m.body));
+        self.scopes.pop();
+        if stmt.superclass.is_some() {
+            self.scopes.pop();
+        }
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &stmt::Continue) {}
+
+    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        // Declared first, so the function can call itself.
+        self.declare(&stmt.name);
+        self.resolve_function(&stmt.params, &stmt.body);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &stmt::If) {
+        stmt.condition.accept(self);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &stmt::Print) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &stmt::Return) {
+        if let Some(value) = &stmt.value {
+            value.accept(self);
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) {
+        // The initializer runs before the variable exists, so a name in it
+        // means an outer variable: `var a = a;` copies the outer `a`.
+        if let Some(initializer) = &stmt.initializer {
+            initializer.accept(self);
+        }
+        self.declare(&stmt.name);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &stmt::While) {
+        stmt.condition.accept(self);
+        stmt.body.accept(self);
+        if let Some(increment) = &stmt.increment {
+            increment.accept(self);
+        }
+    }
+}
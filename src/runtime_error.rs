@@ -1,6 +1,6 @@
 use std::{error::Error, fmt::Display};
 
-use crate::token::Token;
+use crate::{object::LoxObject, token::Token};
 
 #[derive(Debug)]
 pub struct RuntimeError {
@@ -12,6 +12,11 @@ impl RuntimeError {
     pub fn new(token: Token, message: String) -> Self {
         Self { token, message }
     }
+
+    /// The token the error is reported at.
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
 }
 
 impl Display for RuntimeError {
@@ -25,3 +30,32 @@ impl Display for RuntimeError {
 }
 
 impl Error for RuntimeError {}
+
+/// Why a statement stopped before reaching its end: an error, or a jump out
+/// of the innermost loop.
+#[derive(Debug)]
+pub enum Unwind {
+    Error(RuntimeError),
+    Break,
+    Continue,
+    /// A `return`, carrying its value out to the function call.
+    Return(LoxObject),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+impl Unwind {
+    /// The error, for code outside any loop or function, where the parser
+    /// has already rejected `break`, `continue` and `return`.
+    pub fn into_error(self) -> RuntimeError {
+        match self {
+            Unwind::Error(error) => error,
+            Unwind::Break | Unwind::Continue => unreachable!("jump outside of a loop"),
+            Unwind::Return(_) => unreachable!("return outside of a function"),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::interpreter::Interpreter;
+
+/// Natives a sandboxed script can't use, because they reach outside the
+/// interpreter: files, sockets, threads and the user at the terminal.
+const DENIED: &[&str] = &[
+    "heapDump",
+    "glob",
+    "canonicalize",
+    "tempFile",
+    "tempDir",
+    "spawn",
+    "pmap",
+    "tcpConnect",
+    "tcpListen",
+    "tcpAccept",
+    "tcpRead",
+    "tcpWrite",
+    "tcpClose",
+    "prompt",
+    "confirm",
+    "passwordPrompt",
+    "termWidth",
+    "clearScreen",
+    "cursorTo",
+    "style",
+];
+
+/// Limits on how much a script may do, checked before each loop iteration
+/// and call.
+#[derive(Debug, Default, Clone)]
+pub struct Limits {
+    /// Loop iterations and calls, together.
+    pub steps: Option<u64>,
+    /// Bytes allocated by the whole process.
+    pub memory: Option<usize>,
+    /// Calls in progress at once.
+    pub depth: Option<usize>,
+}
+
+impl Limits {
+    /// What `--sandbox` allows.
+    pub fn sandbox() -> Self {
+        Self {
+            steps: Some(10_000_000),
+            memory: Some(64 << 20),
+            depth: Some(256),
+        }
+    }
+}
+
+/// Turns `interpreter` into one suitable for running untrusted scripts.
+pub fn apply(interpreter: &mut Interpreter) {
+    let mut globals = interpreter.globals.write().unwrap();
+    for name in DENIED {
+        globals.remove(name);
+    }
+    interpreter.limits = Limits::sandbox();
+}
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Bytes currently allocated on the heap.
+pub fn allocated() -> usize {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// The system allocator, counting what's allocated so the memory limit can
+/// be checked.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defined(interpreter: &Interpreter, name: &str) -> bool {
+        let globals = interpreter.globals.read().unwrap();
+        let found = globals.values().any(|(global, _)| global == name);
+        found
+    }
+
+    #[test]
+    fn removes_the_denied_natives() {
+        let mut interpreter = Interpreter::new();
+        assert!(defined(&interpreter, "prompt"));
+        assert!(defined(&interpreter, "clearScreen"));
+        apply(&mut interpreter);
+        for name in DENIED {
+            assert!(!defined(&interpreter, name), "'{}' is still defined", name);
+        }
+    }
+}
@@ -1,73 +1,134 @@
 use lazy_static::lazy_static;
+use num_bigint::BigInt;
 
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use crate::{
     object::LoxObject,
     object::Object,
-    token::{Token, TokenKind},
+    token::{Lexeme, Span, Token, TokenKind, TOKEN_INFO},
 };
 
 lazy_static! {
-    static ref KEYWORDS: HashMap<String, TokenKind> = {
-        let mut keywords = HashMap::new();
-
-        keywords.insert(String::from("and"), TokenKind::And);
-        keywords.insert(String::from("class"), TokenKind::Class);
-        keywords.insert(String::from("else"), TokenKind::Else);
-        keywords.insert(String::from("false"), TokenKind::False);
-        keywords.insert(String::from("for"), TokenKind::For);
-        keywords.insert(String::from("fun"), TokenKind::Fun);
-        keywords.insert(String::from("if"), TokenKind::If);
-        keywords.insert(String::from("nil"), TokenKind::Nil);
-        keywords.insert(String::from("or"), TokenKind::Or);
-        keywords.insert(String::from("print"), TokenKind::Print);
-        keywords.insert(String::from("return"), TokenKind::Return);
-        keywords.insert(String::from("super"), TokenKind::Super);
-        keywords.insert(String::from("this"), TokenKind::This);
-        keywords.insert(String::from("true"), TokenKind::True);
-        keywords.insert(String::from("var"), TokenKind::Var);
-        keywords.insert(String::from("while"), TokenKind::While);
-
-        keywords
-    };
+    static ref KEYWORDS: HashMap<String, TokenKind> = TOKEN_INFO
+        .iter()
+        .filter(|(_, info)| info.is_keyword)
+        .filter_map(|(kind, info)| Some((String::from(info.lexeme?), kind)))
+        .collect();
+}
+
+/// Whether `c` can start an identifier: `_` or a character Unicode allows to
+/// start one, which includes letters in any script.
+pub fn is_identifier_start(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
+}
+
+/// Whether `c` can continue an identifier, which also allows digits and
+/// combining marks.
+pub fn is_identifier_char(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+/// Changes an embedder can make to the keyword table.
+#[derive(Debug, Default, Clone)]
+pub struct KeywordConfig {
+    /// Words which aren't keywords but can't be used as names either, so
+    /// they're free to become keywords later.
+    pub reserved: HashSet<String>,
+    /// Keywords which scripts may not use, like `print` in a library.
+    pub disabled: HashSet<String>,
+}
+
+impl KeywordConfig {
+    /// Whether `word` is one of the built-in keywords.
+    pub fn is_keyword(word: &str) -> bool {
+        KEYWORDS.contains_key(word)
+    }
 }
 
+/// A problem found while scanning, kept rather than reported by `scan_all`.
+#[derive(Debug, Clone)]
+pub struct LexError {
+    pub line: usize,
+    /// The column the offending token starts at.
+    pub column: usize,
+    /// The offending text, up to the end of the line it starts on.
+    pub lexeme: String,
+    pub span: Span,
+    pub message: String,
+}
+
+impl LexError {
+    /// Reports the error the way the scanner does when it isn't collecting.
+    pub fn report(&self) {
+        crate::error_at(self.line, &self.lexeme, self.span, &self.message);
+    }
+}
+
+/// Turns source text into tokens, one at a time as an iterator or all at once
+/// with `scan_tokens`. The last token is always `Eof`.
 pub struct Scanner {
     source: Vec<char>,
-    tokens: Vec<Token>,
+    /// The source again, shared by the lexemes of the tokens scanned.
+    text: Arc<str>,
+    /// The token just scanned, if the last character consumed finished one.
+    token: Option<Token>,
+    /// Whether `Eof` has been returned.
+    finished: bool,
+    keywords: KeywordConfig,
+    /// Errors found so far, if they're being collected instead of reported.
+    errors: Option<Vec<LexError>>,
 
     start: usize,
     current: usize,
     line: usize,
+    /// Where the current line starts in `source`.
+    line_start: usize,
+    /// The column `start` is at.
+    start_column: usize,
+    /// The byte offsets of `start` and `current` in the source text.
+    start_byte: usize,
+    current_byte: usize,
 }
 
 impl Scanner {
     pub fn new(source: &str) -> Self {
         Self {
             source: source.chars().collect(),
-            tokens: vec![],
+            text: Arc::from(source),
+            token: None,
+            finished: false,
+            keywords: KeywordConfig::default(),
+            errors: None,
 
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_column: 1,
+            start_byte: 0,
+            current_byte: 0,
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
-        while !self.at_end() {
-            self.start = self.current;
-            self.scan_token();
-        }
+    pub fn with_keywords(mut self, keywords: &KeywordConfig) -> Self {
+        self.keywords = keywords.clone();
+        self
+    }
 
-        self.tokens.push(Token::new(
-            TokenKind::Eof,
-            String::from(""),
-            Object::nil(),
-            self.line,
-        ));
+    pub fn scan_tokens(&mut self) -> Vec<Token> {
+        self.by_ref().collect()
+    }
 
-        self.tokens.clone()
+    /// Scans every token, returning the errors found along the way instead
+    /// of reporting them, so tools can show all of them at once.
+    pub fn scan_all(mut self) -> (Vec<Token>, Vec<LexError>) {
+        self.errors = Some(vec![]);
+        let tokens = self.scan_tokens();
+        (tokens, self.errors.unwrap_or_default())
     }
 
     fn scan_token(&mut self) {
@@ -78,12 +139,36 @@ impl Scanner {
             ')' => self.add_token(TokenKind::RParen, Object::nil()),
             '{' => self.add_token(TokenKind::LBrace, Object::nil()),
             '}' => self.add_token(TokenKind::RBrace, Object::nil()),
+            '[' => self.add_token(TokenKind::LBracket, Object::nil()),
+            ']' => self.add_token(TokenKind::RBracket, Object::nil()),
             ',' => self.add_token(TokenKind::Comma, Object::nil()),
             '.' => self.add_token(TokenKind::Dot, Object::nil()),
-            '-' => self.add_token(TokenKind::Minus, Object::nil()),
-            '+' => self.add_token(TokenKind::Plus, Object::nil()),
+            '-' => {
+                if self.matches('-') {
+                    self.add_token(TokenKind::MinusMinus, Object::nil());
+                } else if self.matches('>') {
+                    self.add_token(TokenKind::Arrow, Object::nil());
+                } else {
+                    self.add_token(TokenKind::Minus, Object::nil());
+                }
+            }
+            '+' => {
+                if self.matches('+') {
+                    self.add_token(TokenKind::PlusPlus, Object::nil());
+                } else {
+                    self.add_token(TokenKind::Plus, Object::nil());
+                }
+            }
             ';' => self.add_token(TokenKind::Semicolon, Object::nil()),
-            '*' => self.add_token(TokenKind::Star, Object::nil()),
+            '*' => {
+                if self.matches('*') {
+                    self.add_token(TokenKind::StarStar, Object::nil());
+                } else {
+                    self.add_token(TokenKind::Star, Object::nil());
+                }
+            }
+            '?' => self.add_token(TokenKind::Question, Object::nil()),
+            ':' => self.add_token(TokenKind::Colon, Object::nil()),
             '!' => {
                 if self.matches('=') {
                     self.add_token(TokenKind::BangEqual, Object::nil());
@@ -117,24 +202,59 @@ impl Scanner {
                     while self.peek() != '\n' && !self.at_end() {
                         self.advance();
                     }
+                } else if self.matches('*') {
+                    self.block_comment();
                 } else {
                     self.add_token(TokenKind::Slash, Object::nil());
                 }
             }
-            c if c.is_whitespace() => {
-                if c == '\n' {
-                    self.line += 1;
+            // A `#!` line at the very start, so scripts can be run directly.
+            '#' if self.start == 0 && self.peek() == '!' => {
+                while self.peek() != '\n' && !self.at_end() {
+                    self.advance();
                 }
             }
+            c if c.is_whitespace() => {}
+            '"' if self.peek() == '"' && self.peek_next() == '"' => {
+                self.advance();
+                self.advance();
+                self.raw_string("\"\"\"");
+            }
             '"' => self.string(),
-            c if c.is_digit(10) => self.number(),
-            c if c.is_alphabetic() || c == '_' => self.identifier(),
-            _ => crate::error(self.line, "Unexpected character."),
+            'r' if self.peek() == '"' => {
+                self.advance();
+                self.raw_string("\"");
+            }
+            'b' if self.peek() == '"' => {
+                self.advance();
+                self.bytes();
+            }
+            c if c.is_ascii_digit() => self.number(),
+            c if is_identifier_start(c) => self.identifier(),
+            _ => self.error(self.line, "Unexpected character."),
+        }
+    }
+
+    /// Skips a `/* ... */` comment whose opening `/*` has been consumed.
+    /// Block comments nest, so a commented-out block can contain one.
+    fn block_comment(&mut self) {
+        let line = self.line;
+        let mut depth = 1;
+        while depth > 0 {
+            if self.at_end() {
+                self.error(line, "Unterminated block comment.");
+                return;
+            }
+            match self.advance() {
+                '/' if self.matches('*') => depth += 1,
+                '*' if self.matches('/') => depth -= 1,
+                _ => {}
+            }
         }
     }
 
     fn identifier(&mut self) {
-        while self.peek().is_alphanumeric() || self.peek() == '_' {
+        while is_identifier_char(self.peek()) {
             self.advance();
         }
 
@@ -145,56 +265,264 @@ impl Scanner {
             .get(&text)
             .cloned()
             .unwrap_or(TokenKind::Identifier);
+        if self.keywords.disabled.contains(&text) {
+            self.error(self.line, "Keyword is disabled.");
+        } else if self.keywords.reserved.contains(&text) {
+            self.error(self.line, "Can't use a reserved word.");
+        }
         self.add_token(kind, Object::nil());
     }
 
     fn number(&mut self) {
-        while self.peek().is_digit(10) {
+        if self.source[self.start] == '0' {
+            let radix = match self.peek() {
+                'x' => 16,
+                'b' => 2,
+                _ => 10,
+            };
+            if radix != 10 && self.peek_next().is_digit(radix) {
+                self.advance();
+                return self.radix_number(radix);
+            }
+        }
+
+        while self.peek().is_ascii_digit() {
             self.advance();
         }
 
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.advance();
 
-            while self.peek().is_digit(10) {
+            while self.peek().is_ascii_digit() {
                 self.advance();
             }
         }
 
-        let value = self.source[self.start..self.current]
+        // An exponent, like the `e-3` in `2.5e-3`.
+        if matches!(self.peek(), 'e' | 'E') {
+            let sign = matches!(self.peek_next(), '+' | '-');
+            let digit = self.source.get(self.current + 1 + sign as usize);
+            if digit.is_some_and(char::is_ascii_digit) {
+                self.advance();
+                if sign {
+                    self.advance();
+                }
+                while self.peek().is_ascii_digit() {
+                    self.advance();
+                }
+            }
+        }
+
+        let text = self.source[self.start..self.current]
+            .iter()
+            .collect::<String>();
+
+        // An integer followed by `n`, like `123n`, is a bigint.
+        let integer = text.chars().all(|c| c.is_ascii_digit());
+        if self.peek() == 'n' && integer && !is_identifier_char(self.peek_next()) {
+            self.advance();
+            let value = text.parse::<BigInt>().unwrap();
+            self.add_token(TokenKind::Number, Object::new_bigint(value));
+            return;
+        }
+
+        let value = text.parse::<f64>().unwrap();
+        self.add_token(TokenKind::Number, Object::new_number(value));
+    }
+
+    /// Scans the digits of a `0x1F` or `0b1010` literal, after its prefix.
+    fn radix_number(&mut self, radix: u32) {
+        let digits_start = self.current;
+        while self.peek().is_digit(radix) {
+            self.advance();
+        }
+        let digits = self.source[digits_start..self.current]
             .iter()
-            .collect::<String>()
-            .parse::<f64>()
-            .unwrap();
+            .collect::<String>();
+
+        // Too many digits for a u64 still fits an f64, approximately.
+        let value = digits.chars().fold(0.0, |value, c| {
+            value * radix as f64 + c.to_digit(radix).unwrap() as f64
+        });
         self.add_token(TokenKind::Number, Object::new_number(value));
     }
 
     fn string(&mut self) {
-        while self.peek() != '"' && !self.at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+        if let Some(value) = self.string_contents(false) {
+            // Without `\x` escapes the contents are whole characters.
+            let value = String::from_utf8(value).unwrap();
+            self.add_token(TokenKind::String, Object::new_string(value));
+        }
+    }
+
+    /// Scans the rest of a raw string, `r"..."` or `"""..."""`, whose opening
+    /// quotes have already been consumed. Backslashes are kept as they are,
+    /// and the string ends at the first `close`.
+    fn raw_string(&mut self, close: &str) {
+        let line = self.line;
+        let close = close.chars().collect::<Vec<_>>();
+        let mut value = String::new();
+        while !self.source[self.current..].starts_with(&close) {
+            if self.at_end() {
+                self.error(line, "Unterminated string.");
+                return;
             }
+            value.push(self.advance());
+        }
+        for _ in 0..close.len() {
             self.advance();
         }
+        self.add_token(TokenKind::String, Object::new_string(value));
+    }
+
+    /// Scans a `b"..."` literal, whose bytes are the UTF-8 encoding of its
+    /// text once escapes are replaced. A `\xNN` escape stands for any one
+    /// byte, as bytes print.
+    fn bytes(&mut self) {
+        if let Some(value) = self.string_contents(true) {
+            self.add_token(TokenKind::Bytes, Object::new_bytes(value));
+        }
+    }
+
+    /// Consumes the rest of a string literal whose opening quote has already
+    /// been consumed, and returns the UTF-8 encoding of the text between the
+    /// quotes with its escape sequences replaced. `\x` escapes are only
+    /// allowed in `bytes` literals.
+    fn string_contents(&mut self, bytes: bool) -> Option<Vec<u8>> {
+        let line = self.line;
+        let mut value = vec![];
+        while self.peek() != '"' && !self.at_end() {
+            let c = match self.advance() {
+                '\\' if bytes && self.matches('x') => {
+                    value.extend(self.byte_escape());
+                    continue;
+                }
+                '\\' if !self.at_end() => match self.escape() {
+                    Some(c) => c,
+                    None => continue,
+                },
+                c => c,
+            };
+            value.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
 
         if self.at_end() {
-            crate::error(self.line, "Unterminated string.");
-            return;
+            self.error(line, "Unterminated string.");
+            return None;
         }
 
         self.advance();
 
-        let value = self.source[self.start + 1..self.current - 1]
+        Some(value)
+    }
+
+    /// Consumes an escape sequence whose backslash has already been consumed,
+    /// and returns the character it stands for.
+    fn escape(&mut self) -> Option<char> {
+        let c = match self.peek() {
+            'n' => '\n',
+            't' => '\t',
+            '\\' => '\\',
+            '"' => '"',
+            'u' => {
+                self.advance();
+                return self.unicode_escape();
+            }
+            c => {
+                self.error(
+                    self.line,
+                    &format!("Invalid escape sequence '\\{}'.", c.escape_default()),
+                );
+                return None;
+            }
+        };
+        self.advance();
+        Some(c)
+    }
+
+    /// Consumes the two hex digits of a `\x` escape, and returns the byte
+    /// they stand for.
+    fn byte_escape(&mut self) -> Option<u8> {
+        let digits = self.current;
+        while self.current - digits < 2 && self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits = self.source[digits..self.current].iter().collect::<String>();
+        if digits.len() != 2 {
+            self.error(self.line, "Expect two hex digits after '\\x'.");
+            return None;
+        }
+        u8::from_str_radix(&digits, 16).ok()
+    }
+
+    /// Consumes the `{...}` of a `\u{...}` escape, which holds the code point
+    /// of any character in up to six hex digits.
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.matches('{') {
+            self.error(self.line, "Expect '{' after '\\u'.");
+            return None;
+        }
+
+        let digits = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits = self.source[digits..self.current].iter().collect::<String>();
+        if !self.matches('}') {
+            self.error(self.line, "Expect '}' after unicode escape.");
+            return None;
+        }
+
+        let c = u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| digits.len() <= 6)
+            .and_then(char::from_u32);
+        if c.is_none() {
+            self.error(
+                self.line,
+                &format!("Invalid unicode escape '\\u{{{}}}'.", digits),
+            );
+        }
+        c
+    }
+
+    /// Reports an error at the token being scanned, up to the end of the
+    /// line it starts on, or keeps it if errors are being collected.
+    fn error(&mut self, line: usize, message: &str) {
+        let lexeme = self.source[self.start..self.current]
             .iter()
+            .take_while(|&&c| c != '\n')
             .collect::<String>();
-        self.add_token(TokenKind::String, Object::new_string(value));
+        let span = Span {
+            start: self.start_byte,
+            end: self.current_byte,
+        };
+        let error = LexError {
+            line,
+            column: self.start_column,
+            lexeme,
+            span,
+            message: String::from(message),
+        };
+        match &mut self.errors {
+            Some(errors) => errors.push(error),
+            None => error.report(),
+        }
     }
 
     fn add_token(&mut self, kind: TokenKind, literal: LoxObject) {
-        let text = self.source[self.start..self.current]
-            .iter()
-            .collect::<String>();
-        self.tokens.push(Token::new(kind, text, literal, self.line));
+        let span = Span {
+            start: self.start_byte,
+            end: self.current_byte,
+        };
+        self.token = Some(Token::new(
+            kind,
+            Lexeme::new(self.text.clone(), span),
+            literal,
+            self.line,
+            self.start_column,
+            span,
+        ));
     }
 
     fn peek_next(&self) -> char {
@@ -217,17 +545,122 @@ impl Scanner {
         if self.at_end() || self.source[self.current] != expected {
             false
         } else {
-            self.current += 1;
+            self.advance();
             true
         }
     }
 
+    /// Consumes a character, keeping track of the line and byte offset.
     fn advance(&mut self) -> char {
+        let c = self.source[self.current];
         self.current += 1;
-        self.source[self.current - 1]
+        self.current_byte += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.line_start = self.current;
+        }
+        c
     }
 
     fn at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 }
+
+impl Iterator for Scanner {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while !self.at_end() {
+            self.start = self.current;
+            self.start_column = self.current - self.line_start + 1;
+            self.start_byte = self.current_byte;
+            self.scan_token();
+            if let Some(token) = self.token.take() {
+                return Some(token);
+            }
+        }
+
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        let span = Span {
+            start: self.current_byte,
+            end: self.current_byte,
+        };
+        Some(Token::new(
+            TokenKind::Eof,
+            Lexeme::new(self.text.clone(), span),
+            Object::nil(),
+            self.line,
+            self.current - self.line_start + 1,
+            span,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_lox::{Outcome, TestLox};
+
+    #[test]
+    fn scans_cjk_identifiers() {
+        let tokens = Scanner::new("var 名前 = 变量2;").scan_tokens();
+        let names = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Identifier)
+            .map(|t| (t.lexeme.to_string(), t.column, t.span.start..t.span.end))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                (String::from("名前"), 5, 4..10),
+                (String::from("变量2"), 10, 13..20)
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_emoji_in_string_literals() {
+        let tokens = Scanner::new("print \"🦀 👍🏽\"; x").scan_tokens();
+        assert_eq!(tokens[1].kind, TokenKind::String);
+        assert_eq!(tokens[1].literal.read().unwrap().to_string(), "🦀 👍🏽");
+        assert_eq!(tokens[1].lexeme, "\"🦀 👍🏽\"");
+        // Spans after the string still land on the right bytes.
+        assert_eq!(tokens[3].lexeme, "x");
+        assert_eq!(tokens[3].span.start, "print \"🦀 👍🏽\"; ".len());
+    }
+
+    #[test]
+    fn aligns_carets_under_wide_characters() {
+        let source = "var 名前 = \"🦀\";\n\tprint 名前 § 1;";
+        let (_, errors) = Scanner::new(source).scan_all();
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!((error.line, error.column), (2, 11));
+        let (line, underline) = crate::underline(source, error.span, &error.lexeme).unwrap();
+        assert_eq!(line, "\tprint 名前 § 1;");
+        assert_eq!(underline, "\t           ^");
+    }
+
+    #[test]
+    fn reads_bytes_back_as_they_print() {
+        let mut lox = TestLox::new();
+        let printed = lox.run(r#"print b"a\"\\" + hexDecode("00ff7f");"#).stdout;
+        assert_eq!(printed, "b\"a\\\"\\\\\\x00\\xff\\x7f\"\n");
+        let result = lox.run(&format!("print {};", printed.trim_end()));
+        assert_eq!(result.stdout, printed);
+    }
+
+    #[test]
+    fn rejects_short_byte_escapes() {
+        match TestLox::new().run(r#"print b"\xf";"#).outcome {
+            Outcome::SyntaxErrors(errors) => {
+                assert!(errors[0].contains("Expect two hex digits after '\\x'."))
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+}
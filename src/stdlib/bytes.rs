@@ -0,0 +1,182 @@
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("len", Object::new_builtin_function(1, len));
+    globals.define("slice", Object::new_builtin_function(3, slice));
+    globals.define("encode", Object::new_builtin_function(2, encode));
+    globals.define("decode", Object::new_builtin_function(2, decode));
+}
+
+/// `len(x)` is the number of bytes in bytes, characters in a string, elements
+/// in a list or entries in a map.
+fn len(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &*args[0].read().unwrap() {
+        Object::Bytes(b) => Ok(Object::new_number(b.len() as f64)),
+        Object::String(s) => Ok(Object::new_number(s.chars().count() as f64)),
+        Object::List(values) => Ok(Object::new_number(values.len() as f64)),
+        Object::Map(map) => Ok(Object::new_number(map.len() as f64)),
+        Object::PersistentVector(values) => Ok(Object::new_number(values.len() as f64)),
+        Object::PersistentMap(map) => Ok(Object::new_number(map.len() as f64)),
+        Object::SortedMap(map) => Ok(Object::new_number(map.len() as f64)),
+        Object::Heap(heap) => Ok(Object::new_number(heap.items.len() as f64)),
+        Object::Deque(values) => Ok(Object::new_number(values.len() as f64)),
+        Object::StringBuilder(s) => Ok(Object::new_number(s.chars().count() as f64)),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only take the length of strings, bytes, lists and maps."),
+        )),
+    }
+}
+
+/// `slice(bytes, start, end)` copies the bytes from `start` up to, but not
+/// including, `end`.
+fn slice(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let bounds = (&*args[1].read().unwrap(), &*args[2].read().unwrap());
+    let (start, end) = match bounds {
+        (Object::Number(start), Object::Number(end))
+            if start.fract() == 0.0 && end.fract() == 0.0 =>
+        {
+            (*start, *end)
+        }
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Slice bounds must be integers."),
+            ))
+        }
+    };
+
+    match &*args[0].read().unwrap() {
+        Object::Bytes(b) => {
+            if start < 0.0 || end < start || end > b.len() as f64 {
+                return Err(RuntimeError::new(
+                    paren.clone(),
+                    String::from("Slice bounds out of range."),
+                ));
+            }
+            Ok(Object::new_bytes(b[start as usize..end as usize].to_vec()))
+        }
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only slice bytes."),
+        )),
+    }
+}
+
+fn encoding_name(paren: &Token, encoding: &LoxObject) -> Result<String, RuntimeError> {
+    match &*encoding.read().unwrap() {
+        Object::String(s) => Ok(s.to_ascii_lowercase().replace('_', "-")),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Encoding must be a string."),
+        )),
+    }
+}
+
+fn unknown_encoding(paren: &Token, encoding: &str) -> RuntimeError {
+    RuntimeError::new(
+        paren.clone(),
+        format!(
+            "Unknown encoding '{}'; expected 'utf-8', 'latin-1' or 'ascii'.",
+            encoding
+        ),
+    )
+}
+
+/// `encode(string, encoding)` converts a string to bytes. Supported encodings
+/// are "utf-8", "latin-1" and "ascii".
+pub fn encode(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let encoding = encoding_name(paren, &args[1])?;
+    let string = match &*args[0].read().unwrap() {
+        Object::String(s) => s.clone(),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only encode strings."),
+            ))
+        }
+    };
+
+    let limit = match encoding.as_str() {
+        "utf-8" | "utf8" => return Ok(Object::new_bytes(string.into_bytes())),
+        "latin-1" | "latin1" | "iso-8859-1" => 0xff,
+        "ascii" => 0x7f,
+        _ => return Err(unknown_encoding(paren, &encoding)),
+    };
+
+    string
+        .chars()
+        .map(|c| {
+            if (c as u32) <= limit {
+                Ok(c as u8)
+            } else {
+                Err(RuntimeError::new(
+                    paren.clone(),
+                    format!("Character '{}' can't be encoded as {}.", c, encoding),
+                ))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Object::new_bytes)
+}
+
+/// `decode(bytes, encoding)` converts bytes to a string, failing if they
+/// aren't valid in the given encoding.
+pub fn decode(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let encoding = encoding_name(paren, &args[1])?;
+    let bytes = match &*args[0].read().unwrap() {
+        Object::Bytes(b) => b.clone(),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only decode bytes."),
+            ))
+        }
+    };
+
+    match encoding.as_str() {
+        "utf-8" | "utf8" => String::from_utf8(bytes)
+            .map(Object::new_string)
+            .map_err(|e| {
+                RuntimeError::new(
+                    paren.clone(),
+                    format!("Invalid utf-8 at byte {}.", e.utf8_error().valid_up_to()),
+                )
+            }),
+        "latin-1" | "latin1" | "iso-8859-1" => Ok(Object::new_string(
+            bytes.into_iter().map(char::from).collect(),
+        )),
+        "ascii" => match bytes.iter().position(|b| !b.is_ascii()) {
+            Some(i) => Err(RuntimeError::new(
+                paren.clone(),
+                format!("Invalid ascii at byte {}.", i),
+            )),
+            None => Ok(Object::new_string(
+                bytes.into_iter().map(char::from).collect(),
+            )),
+        },
+        _ => Err(unknown_encoding(paren, &encoding)),
+    }
+}
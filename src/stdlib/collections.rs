@@ -0,0 +1,392 @@
+use crate::{
+    environment::Environment,
+    interpreter::{check_key, Interpreter},
+    object::{LoxHeap, LoxObject, MapKey, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("sortedMap", Object::new_builtin_function(0, sorted_map));
+    globals.define("put", Object::new_builtin_function(3, put));
+    globals.define("remove", Object::new_builtin_function(2, remove));
+    globals.define("keys", Object::new_builtin_function(1, keys));
+    globals.define("firstKey", Object::new_builtin_function(1, first_key));
+    globals.define("lastKey", Object::new_builtin_function(1, last_key));
+    globals.define("keysBetween", Object::new_builtin_function(3, keys_between));
+    globals.define("heap", Object::new_builtin_function(0, heap));
+    globals.define("heapBy", Object::new_builtin_function(1, heap_by));
+    globals.define("push", Object::new_builtin_function(2, push));
+    globals.define("pop", Object::new_builtin_function(1, pop));
+    globals.define("deque", Object::new_builtin_function(0, deque));
+    globals.define("pushFront", Object::new_builtin_function(2, push_front));
+    globals.define("pushBack", Object::new_builtin_function(2, push_back));
+    globals.define("popFront", Object::new_builtin_function(1, pop_front));
+    globals.define("popBack", Object::new_builtin_function(1, pop_back));
+}
+
+fn sorted_map_error(paren: &Token) -> RuntimeError {
+    RuntimeError::new(
+        paren.clone(),
+        String::from("Argument must be a sorted map."),
+    )
+}
+
+/// `sortedMap()` creates an empty map which keeps its keys in order: nil, then
+/// booleans, then numbers, then strings.
+fn sorted_map(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_sorted_map())
+}
+
+/// `put(map, key, value)` sets `key` to `value` in a map or sorted map.
+fn put(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let key = check_key(paren, &args[1])?;
+    match &mut *args[0].write().unwrap() {
+        Object::Map(map) => map.insert(key, args[2].clone()),
+        Object::SortedMap(map) => {
+            map.insert(key, args[2].clone());
+        }
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only put entries into maps."),
+            ))
+        }
+    }
+    Ok(Object::nil())
+}
+
+/// `remove(map, key)` removes `key` from a map or sorted map, returning its
+/// value, or nil if it wasn't there.
+fn remove(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let key = check_key(paren, &args[1])?;
+    let removed = match &mut *args[0].write().unwrap() {
+        Object::Map(map) => map.remove(&key),
+        Object::SortedMap(map) => map.remove(&key),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only remove entries from maps."),
+            ))
+        }
+    };
+    Ok(removed.unwrap_or_else(Object::nil))
+}
+
+/// `keys(map)` lists the keys of a map in the order they were first put in,
+/// or of a sorted or persistent map in order.
+fn keys(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let keys = match &*args[0].read().unwrap() {
+        Object::Map(map) => map.iter().map(|(key, _)| key.to_object()).collect(),
+        Object::SortedMap(map) => map.keys().map(MapKey::to_object).collect(),
+        Object::PersistentMap(map) => map.keys().map(MapKey::to_object).collect(),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only list the keys of maps."),
+            ))
+        }
+    };
+    Ok(Object::new_list(keys))
+}
+
+/// `firstKey(map)` is the smallest key in a sorted map, or nil if it's empty.
+fn first_key(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &*args[0].read().unwrap() {
+        Object::SortedMap(map) => Ok(map
+            .keys()
+            .next()
+            .map_or_else(Object::nil, |key| key.to_object())),
+        _ => Err(sorted_map_error(paren)),
+    }
+}
+
+/// `lastKey(map)` is the largest key in a sorted map, or nil if it's empty.
+fn last_key(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &*args[0].read().unwrap() {
+        Object::SortedMap(map) => Ok(map
+            .keys()
+            .next_back()
+            .map_or_else(Object::nil, |key| key.to_object())),
+        _ => Err(sorted_map_error(paren)),
+    }
+}
+
+/// `keysBetween(map, from, to)` lists the keys of a sorted map from `from` up
+/// to, but not including, `to`, in order.
+fn keys_between(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let from = check_key(paren, &args[1])?;
+    let to = check_key(paren, &args[2])?;
+    match &*args[0].read().unwrap() {
+        Object::SortedMap(_) if from > to => Ok(Object::new_list(vec![])),
+        Object::SortedMap(map) => Ok(Object::new_list(
+            map.range(from..to)
+                .map(|(key, _)| key.to_object())
+                .collect(),
+        )),
+        _ => Err(sorted_map_error(paren)),
+    }
+}
+
+/// `heap()` creates an empty min-heap of numbers or of strings.
+fn heap(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_heap(None))
+}
+
+/// `heapBy(less)` creates an empty min-heap ordered by `less(a, b)`, which
+/// returns whether `a` should be popped before `b`.
+fn heap_by(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    if args[0].read().unwrap().arity() != 2 {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Heap order must be a function taking two arguments."),
+        ));
+    }
+    Ok(Object::new_heap(Some(args[0].clone())))
+}
+
+/// Whether `a` comes before `b` in `heap`.
+fn less(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    heap: &LoxHeap,
+    a: &LoxObject,
+    b: &LoxObject,
+) -> Result<bool, RuntimeError> {
+    if let Some(less) = &heap.less {
+        let result = less
+            .read()
+            .unwrap()
+            .call(interpreter, paren, vec![a.clone(), b.clone()])?;
+        let less = result.read().unwrap().as_bool();
+        return Ok(less);
+    }
+
+    match (&*a.read().unwrap(), &*b.read().unwrap()) {
+        (Object::Number(a), Object::Number(b)) => Ok(a < b),
+        (Object::String(a), Object::String(b)) => Ok(a < b),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Heap items must be all numbers or all strings."),
+        )),
+    }
+}
+
+/// Takes the items out of a heap, so `less` can run without the heap locked.
+fn take_heap(object: &LoxObject) -> Option<LoxHeap> {
+    match &mut *object.write().unwrap() {
+        Object::Heap(heap) => Some(LoxHeap {
+            items: std::mem::take(&mut heap.items),
+            less: heap.less.clone(),
+        }),
+        _ => None,
+    }
+}
+
+fn put_back(object: &LoxObject, heap: LoxHeap) {
+    if let Object::Heap(h) = &mut *object.write().unwrap() {
+        h.items = heap.items;
+    }
+}
+
+fn sift_up(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    heap: &mut LoxHeap,
+    mut i: usize,
+) -> Result<(), RuntimeError> {
+    while i > 0 {
+        let parent = (i - 1) / 2;
+        if !less(
+            interpreter,
+            paren,
+            heap,
+            &heap.items[i],
+            &heap.items[parent],
+        )? {
+            break;
+        }
+        heap.items.swap(i, parent);
+        i = parent;
+    }
+    Ok(())
+}
+
+fn sift_down(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    heap: &mut LoxHeap,
+    mut i: usize,
+) -> Result<(), RuntimeError> {
+    loop {
+        let mut first = i;
+        for child in [2 * i + 1, 2 * i + 2] {
+            if child < heap.items.len()
+                && less(
+                    interpreter,
+                    paren,
+                    heap,
+                    &heap.items[child],
+                    &heap.items[first],
+                )?
+            {
+                first = child;
+            }
+        }
+        if first == i {
+            return Ok(());
+        }
+        heap.items.swap(i, first);
+        i = first;
+    }
+}
+
+/// `push(collection, value)` adds a value to a heap, or to the end of a list.
+fn push(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    if let Object::List(values) = &mut *args[0].write().unwrap() {
+        values.push(args[1].clone());
+        return Ok(Object::nil());
+    }
+    let mut heap = take_heap(&args[0]).ok_or_else(|| {
+        RuntimeError::new(
+            paren.clone(),
+            String::from("Can only push onto heaps and lists."),
+        )
+    })?;
+    heap.items.push(args[1].clone());
+    let last = heap.items.len() - 1;
+    let result = sift_up(interpreter, paren, &mut heap, last);
+    put_back(&args[0], heap);
+    result.map(|_| Object::nil())
+}
+
+/// `pop(collection)` removes and returns the first value in a heap, or the
+/// last in a list, or nil if it's empty.
+fn pop(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    if let Object::List(values) = &mut *args[0].write().unwrap() {
+        return Ok(values.pop().unwrap_or_else(Object::nil));
+    }
+    let mut heap = take_heap(&args[0]).ok_or_else(|| {
+        RuntimeError::new(
+            paren.clone(),
+            String::from("Can only pop from heaps and lists."),
+        )
+    })?;
+    if heap.items.is_empty() {
+        put_back(&args[0], heap);
+        return Ok(Object::nil());
+    }
+    let first = heap.items.swap_remove(0);
+    let result = sift_down(interpreter, paren, &mut heap, 0);
+    put_back(&args[0], heap);
+    result.map(|_| first)
+}
+
+/// `deque()` creates an empty double-ended queue.
+fn deque(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_deque())
+}
+
+fn deque_error(paren: &Token) -> RuntimeError {
+    RuntimeError::new(paren.clone(), String::from("Argument must be a deque."))
+}
+
+/// `pushFront(deque, value)` adds a value to the front of a deque.
+fn push_front(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &mut *args[0].write().unwrap() {
+        Object::Deque(values) => values.push_front(args[1].clone()),
+        _ => return Err(deque_error(paren)),
+    }
+    Ok(Object::nil())
+}
+
+/// `pushBack(deque, value)` adds a value to the back of a deque.
+fn push_back(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &mut *args[0].write().unwrap() {
+        Object::Deque(values) => values.push_back(args[1].clone()),
+        _ => return Err(deque_error(paren)),
+    }
+    Ok(Object::nil())
+}
+
+/// `popFront(deque)` removes and returns the value at the front of a deque,
+/// or nil if it's empty.
+fn pop_front(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &mut *args[0].write().unwrap() {
+        Object::Deque(values) => Ok(values.pop_front().unwrap_or_else(Object::nil)),
+        _ => Err(deque_error(paren)),
+    }
+}
+
+/// `popBack(deque)` removes and returns the value at the back of a deque, or
+/// nil if it's empty.
+fn pop_back(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &mut *args[0].write().unwrap() {
+        Object::Deque(values) => Ok(values.pop_back().unwrap_or_else(Object::nil)),
+        _ => Err(deque_error(paren)),
+    }
+}
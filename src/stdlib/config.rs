@@ -0,0 +1,89 @@
+use yaml_rust::{Yaml, YamlLoader};
+
+use super::expect_string;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxMap, LoxObject, MapKey, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("tomlParse", Object::new_builtin_function(1, toml_parse));
+    globals.define("yamlParse", Object::new_builtin_function(1, yaml_parse));
+}
+
+fn from_toml(value: toml::Value) -> LoxObject {
+    match value {
+        toml::Value::String(s) => Object::new_string(s),
+        toml::Value::Integer(i) => Object::new_number(i as f64),
+        toml::Value::Float(f) => Object::new_number(f),
+        toml::Value::Boolean(b) => Object::new_bool(b),
+        toml::Value::Datetime(d) => Object::new_string(d.to_string()),
+        toml::Value::Array(values) => Object::new_list(values.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => {
+            let mut map = LoxMap::new();
+            for (key, value) in table {
+                map.insert(MapKey::String(key), from_toml(value));
+            }
+            Object::new_map(map)
+        }
+    }
+}
+
+/// `tomlParse(text)` returns the document as a map. Tables become maps,
+/// arrays become lists and dates become strings.
+fn toml_parse(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let text = expect_string(paren, &args[0], "Can only parse TOML from a string.")?;
+    text.parse::<toml::Value>()
+        .map(from_toml)
+        .map_err(|e| RuntimeError::new(paren.clone(), format!("Invalid TOML: {}.", e)))
+}
+
+fn yaml_key(key: &Yaml) -> Option<MapKey> {
+    match key {
+        Yaml::String(s) => Some(MapKey::String(s.clone())),
+        Yaml::Boolean(b) => Some(MapKey::Bool(*b)),
+        Yaml::Null => Some(MapKey::Nil),
+        _ => MapKey::from_object(&from_yaml(key).read().unwrap()),
+    }
+}
+
+fn from_yaml(value: &Yaml) -> LoxObject {
+    match value {
+        Yaml::String(s) => Object::new_string(s.clone()),
+        Yaml::Integer(i) => Object::new_number(*i as f64),
+        Yaml::Real(_) => Object::new_number(value.as_f64().unwrap_or(f64::NAN)),
+        Yaml::Boolean(b) => Object::new_bool(*b),
+        Yaml::Array(values) => Object::new_list(values.iter().map(from_yaml).collect()),
+        Yaml::Hash(hash) => {
+            let mut map = LoxMap::new();
+            for (key, value) in hash {
+                if let Some(key) = yaml_key(key) {
+                    map.insert(key, from_yaml(value));
+                }
+            }
+            Object::new_map(map)
+        }
+        Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => Object::nil(),
+    }
+}
+
+/// `yamlParse(text)` returns the first document in the text, or nil if there
+/// is none. Mappings become maps and sequences become lists; mapping keys
+/// which can't be map keys, such as lists, are skipped.
+fn yaml_parse(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let text = expect_string(paren, &args[0], "Can only parse YAML from a string.")?;
+    let documents = YamlLoader::load_from_str(&text)
+        .map_err(|e| RuntimeError::new(paren.clone(), format!("Invalid YAML: {}.", e)))?;
+    Ok(documents.first().map(from_yaml).unwrap_or_else(Object::nil))
+}
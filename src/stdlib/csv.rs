@@ -0,0 +1,213 @@
+use super::expect_string;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxMap, LoxObject, MapKey, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("csvParse", Object::new_builtin_function(1, csv_parse));
+    globals.define(
+        "csvParseRecords",
+        Object::new_builtin_function(1, csv_parse_records),
+    );
+    globals.define("csvWrite", Object::new_builtin_function(1, csv_write));
+}
+
+/// Splits CSV text into rows of fields, following RFC 4180: fields may be
+/// quoted, quoted fields may contain commas, newlines and doubled quotes, and
+/// rows end with either `\n` or `\r\n`.
+fn parse(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    // Whether the current row has anything in it, so a trailing newline
+    // doesn't produce an empty last row.
+    let mut started = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() => {
+                started = true;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.push(c);
+                        }
+                        None => return Err(format!("Unterminated quoted field on line {}.", line)),
+                    }
+                }
+                match chars.peek() {
+                    None | Some(',') | Some('\n') | Some('\r') => {}
+                    Some(_) => {
+                        return Err(format!(
+                            "Unexpected character after quoted field on line {}.",
+                            line
+                        ))
+                    }
+                }
+            }
+            ',' => {
+                started = true;
+                row.push(std::mem::take(&mut field));
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                if started {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                    started = false;
+                }
+            }
+            c => {
+                started = true;
+                field.push(c);
+            }
+        }
+    }
+
+    if started {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn strings_to_list(fields: Vec<String>) -> LoxObject {
+    Object::new_list(fields.into_iter().map(Object::new_string).collect())
+}
+
+/// `csvParse(text)` returns a list of rows, each a list of string fields.
+fn csv_parse(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let text = expect_string(paren, &args[0], "Can only parse CSV from a string.")?;
+    let rows = parse(&text).map_err(|message| RuntimeError::new(paren.clone(), message))?;
+    Ok(Object::new_list(
+        rows.into_iter().map(strings_to_list).collect(),
+    ))
+}
+
+/// `csvParseRecords(text)` treats the first row as a header, and returns the
+/// remaining rows as maps from column name to field.
+fn csv_parse_records(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let text = expect_string(paren, &args[0], "Can only parse CSV from a string.")?;
+    let mut rows = parse(&text)
+        .map_err(|message| RuntimeError::new(paren.clone(), message))?
+        .into_iter();
+    let header = rows.next().unwrap_or_default();
+
+    let mut records = vec![];
+    for (i, row) in rows.enumerate() {
+        if row.len() != header.len() {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                format!(
+                    "Record {} has {} fields but the header has {}.",
+                    i + 1,
+                    row.len(),
+                    header.len()
+                ),
+            ));
+        }
+
+        let mut record = LoxMap::new();
+        for (name, field) in header.iter().zip(row) {
+            record.insert(MapKey::from(name.as_str()), Object::new_string(field));
+        }
+        records.push(Object::new_map(record));
+    }
+    Ok(Object::new_list(records))
+}
+
+fn write_field(out: &mut String, field: &str) {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        out.push('"');
+        out.push_str(&field.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(field);
+    }
+}
+
+fn write_row<'a>(out: &mut String, fields: impl Iterator<Item = String> + 'a) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_field(out, &field);
+    }
+    out.push('\n');
+}
+
+/// `csvWrite(rows)` formats a list of lists, or a list of maps, as CSV text.
+/// For maps, the keys of the first map become the header row.
+fn csv_write(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let bad_rows = || {
+        RuntimeError::new(
+            paren.clone(),
+            String::from("Can only write a list of lists or a list of maps as CSV."),
+        )
+    };
+
+    let rows = match &*args[0].read().unwrap() {
+        Object::List(rows) => rows.clone(),
+        _ => return Err(bad_rows()),
+    };
+
+    let mut out = String::new();
+    let mut header: Option<Vec<MapKey>> = None;
+    for row in rows {
+        match &*row.read().unwrap() {
+            Object::List(fields) => write_row(
+                &mut out,
+                fields.iter().map(|f| f.read().unwrap().to_string()),
+            ),
+            Object::Map(map) => {
+                let columns = header.get_or_insert_with(|| {
+                    let columns = map.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>();
+                    write_row(
+                        &mut out,
+                        columns
+                            .iter()
+                            .map(|k| k.to_object().read().unwrap().to_string()),
+                    );
+                    columns
+                });
+                write_row(
+                    &mut out,
+                    columns.iter().map(|k| match map.get(k) {
+                        Some(v) => v.read().unwrap().to_string(),
+                        None => String::new(),
+                    }),
+                );
+            }
+            _ => return Err(bad_rows()),
+        }
+    }
+    Ok(Object::new_string(out))
+}
@@ -0,0 +1,178 @@
+use super::{bytes, expect_bytes, expect_string};
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("utf8Encode", Object::new_builtin_function(1, utf8_encode));
+    globals.define("utf8Decode", Object::new_builtin_function(1, utf8_decode));
+    globals.define(
+        "base64Encode",
+        Object::new_builtin_function(1, base64_encode),
+    );
+    globals.define(
+        "base64Decode",
+        Object::new_builtin_function(1, base64_decode),
+    );
+    globals.define("hexEncode", Object::new_builtin_function(1, hex_encode));
+    globals.define("hexDecode", Object::new_builtin_function(1, hex_decode));
+}
+
+/// `utf8Encode(string)` is `encode(string, "utf-8")`.
+fn utf8_encode(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    mut args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    args.push(Object::new_string(String::from("utf-8")));
+    bytes::encode(interpreter, paren, args)
+}
+
+/// `utf8Decode(bytes)` is `decode(bytes, "utf-8")`.
+fn utf8_decode(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    mut args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    args.push(Object::new_string(String::from("utf-8")));
+    bytes::decode(interpreter, paren, args)
+}
+
+/// `base64Encode(bytes)` encodes with the standard alphabet and padding.
+fn base64_encode(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let bytes = expect_bytes(paren, &args[0], "Can only base64 encode bytes.")?;
+    Ok(Object::new_string(base64(&bytes)))
+}
+
+/// Encodes `bytes` as `base64Encode` does.
+pub fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// `base64Decode(string)` accepts padded or unpadded standard base64.
+fn base64_decode(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let string = expect_string(paren, &args[0], "Can only base64 decode strings.")?;
+    let invalid = || RuntimeError::new(paren.clone(), String::from("Invalid base64."));
+
+    let digits = string
+        .trim_end_matches('=')
+        .bytes()
+        .map(|c| {
+            BASE64_ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .map(|d| d as u32)
+                .ok_or_else(invalid)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut decoded = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(invalid());
+        }
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &d)| acc | d << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            decoded.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    Ok(Object::new_bytes(decoded))
+}
+
+/// `hexEncode(bytes)` produces two lowercase hex digits per byte.
+fn hex_encode(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let bytes = expect_bytes(paren, &args[0], "Can only hex encode bytes.")?;
+    Ok(Object::new_string(
+        bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+    ))
+}
+
+fn hex_decode(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let string = expect_string(paren, &args[0], "Can only hex decode strings.")?;
+    let invalid = || RuntimeError::new(paren.clone(), String::from("Invalid hex."));
+
+    // Checked up front, since `from_str_radix` also accepts a leading `+`.
+    if string.len() % 2 != 0 || !string.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    (0..string.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&string[i..i + 2], 16).map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Object::new_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_lox::{Outcome, TestLox};
+
+    fn error(source: &str) -> String {
+        match TestLox::new().run(source).outcome {
+            Outcome::RuntimeError(error) => error.to_string(),
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn decodes_hex() {
+        let result = TestLox::new().run("print hexDecode(\"00fF7a\");");
+        assert_eq!(result.stdout, "b\"\\x00\\xffz\"\n");
+        for digits in ["+f", "0g", "abc", "\u{e9}1"] {
+            let message = error(&format!("hexDecode(\"{}\");", digits));
+            assert!(message.contains("Invalid hex."), "{}", message);
+        }
+    }
+
+    #[test]
+    fn utf8_natives_match_encode_and_decode() {
+        let result = TestLox::new().run(
+            "var s = \"h\u{e9}llo \u{1f980}\";
+            print utf8Encode(s) == encode(s, \"utf-8\");
+            print utf8Decode(utf8Encode(s)) == s;",
+        );
+        assert_eq!(result.stdout, "true\ntrue\n");
+        assert!(error("utf8Decode(hexDecode(\"68ff\"));").contains("Invalid utf-8 at byte 1."));
+        assert!(error("utf8Encode(1);").contains("Can only encode strings."));
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use super::expect_string;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("glob", Object::new_builtin_function(1, glob));
+    globals.define("pathJoin", Object::new_builtin_function(2, path_join));
+    globals.define("basename", Object::new_builtin_function(1, basename));
+    globals.define("dirname", Object::new_builtin_function(1, dirname));
+    globals.define("extension", Object::new_builtin_function(1, extension));
+    globals.define(
+        "canonicalize",
+        Object::new_builtin_function(1, canonicalize),
+    );
+}
+
+fn expect_path(paren: &Token, arg: &LoxObject) -> Result<String, RuntimeError> {
+    expect_string(paren, arg, "Path must be a string.")
+}
+
+/// A path part as a Lox string, or nil if there isn't one.
+fn new_part(part: Option<&std::ffi::OsStr>) -> LoxObject {
+    match part {
+        Some(part) => Object::new_string(part.to_string_lossy().into_owned()),
+        None => Object::nil(),
+    }
+}
+
+/// `glob(pattern)` is the sorted list of paths matching a pattern like
+/// `"src/**/*.lox"`, where `*` and `?` match within one path component and
+/// `**` matches any number of directories. Paths that can't be read are
+/// skipped.
+fn glob(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let pattern = expect_string(paren, &args[0], "Glob pattern must be a string.")?;
+    let paths = ::glob::glob(&pattern).map_err(|e| {
+        RuntimeError::new(paren.clone(), format!("Invalid glob pattern: {}.", e.msg))
+    })?;
+    Ok(Object::new_list(
+        paths
+            .filter_map(Result::ok)
+            .map(|path| Object::new_string(path.to_string_lossy().into_owned()))
+            .collect(),
+    ))
+}
+
+/// `pathJoin(a, b)` is `b` appended to `a` with a path separator between, or
+/// just `b` if it is absolute.
+fn path_join(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let a = expect_path(paren, &args[0])?;
+    let b = expect_path(paren, &args[1])?;
+    let joined = Path::new(&a).join(b);
+    Ok(Object::new_string(joined.to_string_lossy().into_owned()))
+}
+
+/// `basename(path)` is the last component of a path, or nil if it ends in
+/// `..` or is a root.
+fn basename(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let path = expect_path(paren, &args[0])?;
+    Ok(new_part(Path::new(&path).file_name()))
+}
+
+/// `dirname(path)` is a path without its last component, which is `""` for a
+/// relative path with one component, or nil for a root.
+fn dirname(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let path = expect_path(paren, &args[0])?;
+    Ok(new_part(Path::new(&path).parent().map(Path::as_os_str)))
+}
+
+/// `extension(path)` is the extension of a path's last component, without
+/// the dot, or nil if it has none.
+fn extension(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let path = expect_path(paren, &args[0])?;
+    Ok(new_part(Path::new(&path).extension()))
+}
+
+/// `canonicalize(path)` is the absolute form of a path which exists, with
+/// symbolic links, `.` and `..` resolved.
+fn canonicalize(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let path = expect_path(paren, &args[0])?;
+    let canonical = std::fs::canonicalize(&path).map_err(|e| {
+        RuntimeError::new(
+            paren.clone(),
+            format!("Could not canonicalize '{}': {}.", path, e),
+        )
+    })?;
+    Ok(Object::new_string(canonical.to_string_lossy().into_owned()))
+}
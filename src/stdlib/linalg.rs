@@ -0,0 +1,193 @@
+use super::expect_number;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("vec2", Object::new_builtin_function(2, vec2));
+    globals.define("vec3", Object::new_builtin_function(3, vec3));
+    globals.define("mat2", Object::new_builtin_function(2, mat2));
+    globals.define("mat3", Object::new_builtin_function(3, mat3));
+    globals.define("dot", Object::new_builtin_function(2, dot));
+    globals.define("cross", Object::new_builtin_function(2, cross));
+    globals.define("matMul", Object::new_builtin_function(2, mat_mul));
+}
+
+fn vector_error(paren: &Token) -> RuntimeError {
+    RuntimeError::new(
+        paren.clone(),
+        String::from("Vectors must be lists of numbers."),
+    )
+}
+
+fn matrix_error(paren: &Token) -> RuntimeError {
+    RuntimeError::new(
+        paren.clone(),
+        String::from("Matrices must be lists of rows of numbers, all the same length."),
+    )
+}
+
+/// Reads a list of numbers.
+fn expect_vector(paren: &Token, arg: &LoxObject) -> Result<Vec<f64>, RuntimeError> {
+    match &*arg.read().unwrap() {
+        Object::List(values) => values
+            .iter()
+            .map(|v| match *v.read().unwrap() {
+                Object::Number(n) => Ok(n),
+                _ => Err(vector_error(paren)),
+            })
+            .collect(),
+        _ => Err(vector_error(paren)),
+    }
+}
+
+/// Reads a list of rows, each a list of numbers of the same length.
+fn expect_matrix(paren: &Token, arg: &LoxObject) -> Result<Vec<Vec<f64>>, RuntimeError> {
+    let rows = match &*arg.read().unwrap() {
+        Object::List(rows) if !rows.is_empty() => rows
+            .iter()
+            .map(|row| expect_vector(paren, row).map_err(|_| matrix_error(paren)))
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err(matrix_error(paren)),
+    };
+    if rows.iter().any(|row| row.len() != rows[0].len()) {
+        return Err(matrix_error(paren));
+    }
+    Ok(rows)
+}
+
+fn new_vector(values: impl IntoIterator<Item = f64>) -> LoxObject {
+    Object::new_list(values.into_iter().map(Object::new_number).collect())
+}
+
+/// `vec2(x, y)` is the list `[x, y]`. Vectors are plain lists of numbers, so
+/// they can be indexed and printed like any other list.
+fn vec2(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let values = args
+        .iter()
+        .map(|a| expect_number(paren, a, "Vector components must be numbers."))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(new_vector(values))
+}
+
+/// `vec3(x, y, z)` is the list `[x, y, z]`.
+fn vec3(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    vec2(interpreter, paren, args)
+}
+
+/// `mat2(row0, row1)` is the 2x2 matrix with the given rows, as a list of
+/// rows.
+fn mat2(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let matrix = Object::new_list(args);
+    match expect_matrix(paren, &matrix)? {
+        rows if rows[0].len() == rows.len() => Ok(matrix),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Matrix rows must be vectors as long as the matrix is high."),
+        )),
+    }
+}
+
+/// `mat3(row0, row1, row2)` is the 3x3 matrix with the given rows.
+fn mat3(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    mat2(interpreter, paren, args)
+}
+
+/// `dot(a, b)` is the dot product of two vectors of the same length.
+fn dot(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let a = expect_vector(paren, &args[0])?;
+    let b = expect_vector(paren, &args[1])?;
+    if a.len() != b.len() {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only take the dot product of vectors of the same length."),
+        ));
+    }
+    Ok(Object::new_number(
+        a.iter().zip(&b).map(|(x, y)| x * y).sum(),
+    ))
+}
+
+/// `cross(a, b)` is the cross product of two 3-vectors.
+fn cross(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let a = expect_vector(paren, &args[0])?;
+    let b = expect_vector(paren, &args[1])?;
+    match (a.as_slice(), b.as_slice()) {
+        ([ax, ay, az], [bx, by, bz]) => Ok(new_vector([
+            ay * bz - az * by,
+            az * bx - ax * bz,
+            ax * by - ay * bx,
+        ])),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only take the cross product of 3-vectors."),
+        )),
+    }
+}
+
+/// `matMul(a, b)` multiplies two matrices, given as lists of rows. If `b` is a
+/// vector instead, it is taken as a column and the result is a vector, so
+/// `matMul(transform, point)` transforms a point.
+fn mat_mul(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let a = expect_matrix(paren, &args[0])?;
+    let shape_error = || {
+        RuntimeError::new(
+            paren.clone(),
+            String::from("Can only multiply matrices whose inner dimensions match."),
+        )
+    };
+
+    if let Ok(v) = expect_vector(paren, &args[1]) {
+        if a[0].len() != v.len() {
+            return Err(shape_error());
+        }
+        return Ok(new_vector(
+            a.iter()
+                .map(|row| row.iter().zip(&v).map(|(x, y)| x * y).sum()),
+        ));
+    }
+
+    let b = expect_matrix(paren, &args[1])?;
+    if a[0].len() != b.len() {
+        return Err(shape_error());
+    }
+    let rows = a
+        .iter()
+        .map(|row| {
+            new_vector((0..b[0].len()).map(|j| row.iter().zip(&b).map(|(x, r)| x * r[j]).sum()))
+        })
+        .collect();
+    Ok(Object::new_list(rows))
+}
@@ -0,0 +1,58 @@
+use icu_collator::{Collator, CollatorOptions};
+use icu_locid::Locale;
+
+use super::{
+    expect_string,
+    sort::{put_back, take_list},
+};
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("sortLocale", Object::new_builtin_function(2, sort_locale));
+}
+
+/// `sortLocale(list, locale)` sorts a list of strings in place the way
+/// `locale`, like `"en"` or `"sv-SE"`, orders words in a dictionary.
+fn sort_locale(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let name = expect_string(paren, &args[1], "Locale must be a string.")?;
+    let collator = name
+        .parse::<Locale>()
+        .ok()
+        .and_then(|locale| Collator::try_new(&locale.into(), CollatorOptions::new()).ok())
+        .ok_or_else(|| RuntimeError::new(paren.clone(), format!("Unknown locale '{}'.", name)))?;
+
+    let mut values = take_list(paren, &args[0])?;
+    let strings = values
+        .iter()
+        .map(|v| match &*v.read().unwrap() {
+            Object::String(s) => Some(s.clone()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    let strings = match strings {
+        Some(strings) => strings,
+        None => {
+            put_back(&args[0], values);
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only sort lists of strings by locale."),
+            ));
+        }
+    };
+
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| collator.compare(&strings[a], &strings[b]));
+    values = order.into_iter().map(|i| values[i].clone()).collect();
+    put_back(&args[0], values);
+    Ok(Object::nil())
+}
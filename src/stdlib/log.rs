@@ -0,0 +1,153 @@
+use super::expect_string;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("logDebug", Object::new_builtin_function(1, log_debug));
+    globals.define("logInfo", Object::new_builtin_function(1, log_info));
+    globals.define("logWarn", Object::new_builtin_function(1, log_warn));
+    globals.define("logError", Object::new_builtin_function(1, log_error));
+    globals.define("logLevel", Object::new_builtin_function(1, log_level));
+    globals.define(
+        "logTimestamps",
+        Object::new_builtin_function(1, log_timestamps),
+    );
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+/// Settings for the `log*` natives. Messages below `level` are dropped.
+#[derive(Debug, Clone)]
+pub struct Logger {
+    pub level: Level,
+    pub timestamps: bool,
+}
+
+impl Logger {
+    pub fn new() -> Self {
+        Self {
+            level: Level::Info,
+            timestamps: false,
+        }
+    }
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn log(
+    interpreter: &mut Interpreter,
+    level: Level,
+    message: &LoxObject,
+) -> Result<LoxObject, RuntimeError> {
+    if level < interpreter.logger.level {
+        return Ok(Object::nil());
+    }
+
+    let mut line = String::new();
+    if interpreter.logger.timestamps {
+        line.push_str(
+            &chrono::Local::now()
+                .format("%Y-%m-%d %H:%M:%S%.3f ")
+                .to_string(),
+        );
+    }
+    line.push_str(&format!("{:<5} {}", level.name(), message.read().unwrap()));
+
+    // Logging is best-effort; a closed stderr shouldn't stop the script.
+    let _ = writeln!(interpreter.stderr.lock().unwrap(), "{}", line);
+    Ok(Object::nil())
+}
+
+fn log_debug(
+    interpreter: &mut Interpreter,
+    _paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    log(interpreter, Level::Debug, &args[0])
+}
+
+fn log_info(
+    interpreter: &mut Interpreter,
+    _paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    log(interpreter, Level::Info, &args[0])
+}
+
+fn log_warn(
+    interpreter: &mut Interpreter,
+    _paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    log(interpreter, Level::Warn, &args[0])
+}
+
+fn log_error(
+    interpreter: &mut Interpreter,
+    _paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    log(interpreter, Level::Error, &args[0])
+}
+
+/// `logLevel(name)` sets the least severe level which is logged: "debug",
+/// "info", "warn" or "error". The default is "info".
+fn log_level(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let name = expect_string(paren, &args[0], "Log level must be a string.")?;
+    interpreter.logger.level = match name.to_ascii_lowercase().as_str() {
+        "debug" => Level::Debug,
+        "info" => Level::Info,
+        "warn" => Level::Warn,
+        "error" => Level::Error,
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                format!(
+                    "Unknown log level '{}'; expected 'debug', 'info', 'warn' or 'error'.",
+                    name
+                ),
+            ))
+        }
+    };
+    Ok(Object::nil())
+}
+
+/// `logTimestamps(enabled)` turns local timestamps on log lines on or off.
+fn log_timestamps(
+    interpreter: &mut Interpreter,
+    _paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    interpreter.logger.timestamps = args[0].read().unwrap().as_bool();
+    Ok(Object::nil())
+}
@@ -0,0 +1,215 @@
+mod bytes;
+mod collections;
+#[cfg(feature = "config")]
+mod config;
+mod csv;
+pub mod encoding;
+mod fs;
+mod linalg;
+#[cfg(feature = "locale")]
+mod locale;
+pub mod log;
+#[cfg(feature = "net")]
+mod net;
+mod numeric;
+mod persistent;
+mod progress;
+mod prompt;
+pub mod random;
+mod sort;
+mod strings;
+mod sync;
+mod table;
+pub mod temp;
+mod template;
+mod term;
+mod thread;
+pub mod timer;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::{
+    environment::Environment,
+    heap_dump::HeapGraph,
+    object::{LoxMap, LoxObject, MapKey, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+    version,
+};
+
+/// The natives behind each feature `hasFeature` knows. A feature is there
+/// if all of its natives are: a build without its Cargo feature leaves them
+/// out, and `--sandbox` removes them.
+const FEATURES: &[(&str, &[&str])] = &[
+    ("config", &["tomlParse", "yamlParse"]),
+    ("fs", &["glob", "canonicalize", "tempFile", "tempDir"]),
+    ("locale", &["sortLocale"]),
+    (
+        "net",
+        &[
+            "tcpConnect",
+            "tcpListen",
+            "tcpAccept",
+            "tcpRead",
+            "tcpWrite",
+            "tcpClose",
+        ],
+    ),
+    ("threads", &["spawn", "pmap"]),
+];
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define(
+        "clock",
+        Object::new_builtin_function(0, |interpreter, paren, _args| {
+            let now = interpreter
+                .recorder
+                .lock()
+                .unwrap()
+                .number("clock", || interpreter.clock.now())
+                .map_err(|message| RuntimeError::new(paren.clone(), message))?;
+            Ok(Object::new_number(now))
+        }),
+    );
+
+    globals.define(
+        "heapDump",
+        Object::new_builtin_function(1, |interpreter, paren, args| {
+            let path = expect_string(paren, &args[0], "Heap dump path must be a string.")?;
+            HeapGraph::collect(interpreter.roots())
+                .write(&path)
+                .map_err(|e| {
+                    RuntimeError::new(paren.clone(), format!("Could not write heap dump: {}.", e))
+                })?;
+            Ok(Object::nil())
+        }),
+    );
+
+    // `version()` returns a map of `version`, `commit`, `date` and
+    // `features`, a map from each optional feature to whether it's built in.
+    globals.define(
+        "version",
+        Object::new_builtin_function(0, |_interpreter, _paren, _args| {
+            let mut features = LoxMap::new();
+            for &(name, enabled) in &version::FEATURES {
+                features.insert(MapKey::String(name.to_string()), Object::new_bool(enabled));
+            }
+            let mut map = LoxMap::new();
+            for (key, value) in [
+                ("version", Object::new_string(version::VERSION.to_string())),
+                ("commit", Object::new_string(version::COMMIT.to_string())),
+                ("date", Object::new_string(version::BUILD_DATE.to_string())),
+                ("features", Object::new_map(features)),
+            ] {
+                map.insert(MapKey::String(key.to_string()), value);
+            }
+            Ok(Object::new_map(map))
+        }),
+    );
+
+    // `hasFeature(name)` says whether a feature in `FEATURES` can be used,
+    // so a script can do without it rather than fail on an undefined native.
+    // Features it doesn't know are never there.
+    globals.define(
+        "hasFeature",
+        Object::new_builtin_function(1, |interpreter, paren, args| {
+            let name = expect_string(paren, &args[0], "Feature name must be a string.")?;
+            let globals = interpreter.globals.read().unwrap();
+            let defined = |native: &str| {
+                globals.values().any(|(n, value)| {
+                    n == native && matches!(*value.read().unwrap(), Object::BuiltinFunction(..))
+                })
+            };
+            let has = FEATURES
+                .iter()
+                .find(|(feature, _)| *feature == name)
+                .is_some_and(|(_, natives)| natives.iter().all(|n| defined(n)));
+            Ok(Object::new_bool(has))
+        }),
+    );
+
+    bytes::define_natives(globals);
+    collections::define_natives(globals);
+    #[cfg(feature = "config")]
+    config::define_natives(globals);
+    csv::define_natives(globals);
+    encoding::define_natives(globals);
+    fs::define_natives(globals);
+    linalg::define_natives(globals);
+    #[cfg(feature = "locale")]
+    locale::define_natives(globals);
+    log::define_natives(globals);
+    #[cfg(feature = "net")]
+    net::define_natives(globals);
+    numeric::define_natives(globals);
+    persistent::define_natives(globals);
+    progress::define_natives(globals);
+    prompt::define_natives(globals);
+    random::define_natives(globals);
+    sort::define_natives(globals);
+    strings::define_natives(globals);
+    sync::define_natives(globals);
+    table::define_natives(globals);
+    temp::define_natives(globals);
+    term::define_natives(globals);
+    template::define_natives(globals);
+    thread::define_natives(globals);
+    timer::define_natives(globals);
+}
+
+/// Where `clock` gets the time from: the system, or a fake clock which only
+/// moves when it's set, so tests see the same times on every run. Forked
+/// interpreters share their parent's.
+#[derive(Clone, Default)]
+pub struct Clock(Option<Arc<Mutex<f64>>>);
+
+impl Clock {
+    pub fn fake(start: f64) -> Self {
+        Clock(Some(Arc::new(Mutex::new(start))))
+    }
+
+    /// Moves a fake clock to `now`, in seconds. The system clock can't be set.
+    pub fn set(&self, now: f64) {
+        if let Some(time) = &self.0 {
+            *time.lock().unwrap() = now;
+        }
+    }
+
+    /// Seconds since the Unix epoch, or since whenever a fake clock started.
+    pub fn now(&self) -> f64 {
+        match &self.0 {
+            Some(time) => *time.lock().unwrap(),
+            None => SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs_f64(),
+        }
+    }
+}
+
+/// Reads a string argument, failing with `message` if it is anything else.
+fn expect_string(paren: &Token, arg: &LoxObject, message: &str) -> Result<String, RuntimeError> {
+    match &*arg.read().unwrap() {
+        Object::String(s) => Ok(s.clone()),
+        _ => Err(RuntimeError::new(paren.clone(), String::from(message))),
+    }
+}
+
+/// Reads a bytes argument, failing with `message` if it is anything else.
+fn expect_bytes(paren: &Token, arg: &LoxObject, message: &str) -> Result<Vec<u8>, RuntimeError> {
+    match &*arg.read().unwrap() {
+        Object::Bytes(b) => Ok(b.clone()),
+        _ => Err(RuntimeError::new(paren.clone(), String::from(message))),
+    }
+}
+
+/// Reads a number argument, failing with `message` if it is anything else.
+fn expect_number(paren: &Token, arg: &LoxObject, message: &str) -> Result<f64, RuntimeError> {
+    match &*arg.read().unwrap() {
+        Object::Number(n) => Ok(*n),
+        _ => Err(RuntimeError::new(paren.clone(), String::from(message))),
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+use super::{expect_number, expect_string};
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("tcpConnect", Object::new_builtin_function(2, tcp_connect));
+    globals.define("tcpListen", Object::new_builtin_function(1, tcp_listen));
+    globals.define("tcpAccept", Object::new_builtin_function(1, tcp_accept));
+    globals.define("tcpRead", Object::new_builtin_function(2, tcp_read));
+    globals.define("tcpWrite", Object::new_builtin_function(2, tcp_write));
+    globals.define("tcpClose", Object::new_builtin_function(1, tcp_close));
+}
+
+/// The most `tcpRead` reads at once, whatever size it's asked for, so a
+/// script can't allocate an arbitrarily large buffer.
+const MAX_READ: usize = 64 << 10;
+
+/// How long `tcpAccept` and `tcpRead` wait at a time before checking whether
+/// the script has been interrupted.
+const POLL: Duration = Duration::from_millis(50);
+
+/// Whether `error` only means that nothing arrived in time.
+fn timed_out(error: &std::io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Sockets and listeners are `None` once they have been closed.
+type Socket = Option<TcpStream>;
+type Listener = Option<TcpListener>;
+
+fn io_error(paren: &Token, error: std::io::Error) -> RuntimeError {
+    RuntimeError::new(paren.clone(), format!("Network error: {}.", error))
+}
+
+fn expect_port(paren: &Token, arg: &LoxObject) -> Result<u16, RuntimeError> {
+    let port = expect_number(paren, arg, "Port must be a number.")?;
+    if port.fract() != 0.0 || !(0.0..=65535.0).contains(&port) {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Port must be an integer between 0 and 65535."),
+        ));
+    }
+    Ok(port as u16)
+}
+
+/// Runs `f` on the open socket in `arg`.
+fn with_socket<T>(
+    paren: &Token,
+    arg: &LoxObject,
+    f: impl FnOnce(&mut TcpStream) -> std::io::Result<T>,
+) -> Result<T, RuntimeError> {
+    match &mut *arg.write().unwrap() {
+        Object::Native(n) => match n.value.downcast_mut::<Socket>() {
+            Some(Some(stream)) => f(stream).map_err(|e| io_error(paren, e)),
+            Some(None) => Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Socket is closed."),
+            )),
+            None => Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Expected a socket."),
+            )),
+        },
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Expected a socket."),
+        )),
+    }
+}
+
+/// `tcpConnect(host, port)` opens a connection and returns its socket.
+fn tcp_connect(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let host = expect_string(paren, &args[0], "Host must be a string.")?;
+    let port = expect_port(paren, &args[1])?;
+    let stream = TcpStream::connect((host.as_str(), port)).map_err(|e| io_error(paren, e))?;
+    Ok(Object::new_native::<Socket>("socket", Some(stream)))
+}
+
+/// `tcpListen(port)` listens on every interface and returns a listener for
+/// `tcpAccept`.
+fn tcp_listen(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let port = expect_port(paren, &args[0])?;
+    let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|e| io_error(paren, e))?;
+    Ok(Object::new_native::<Listener>("listener", Some(listener)))
+}
+
+/// `tcpAccept(listener)` blocks until a client connects and returns its socket.
+fn tcp_accept(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    // The listener is polled, so waiting for a client can be interrupted.
+    let stream = loop {
+        let accepted = match &*args[0].read().unwrap() {
+            Object::Native(n) => match n.value.downcast_ref::<Listener>() {
+                Some(Some(listener)) => listener
+                    .set_nonblocking(true)
+                    .and_then(|_| listener.accept()),
+                Some(None) => {
+                    return Err(RuntimeError::new(
+                        paren.clone(),
+                        String::from("Listener is closed."),
+                    ))
+                }
+                None => {
+                    return Err(RuntimeError::new(
+                        paren.clone(),
+                        String::from("Expected a listener."),
+                    ))
+                }
+            },
+            _ => {
+                return Err(RuntimeError::new(
+                    paren.clone(),
+                    String::from("Expected a listener."),
+                ))
+            }
+        };
+        match accepted {
+            Ok((stream, _)) => break stream,
+            Err(e) if timed_out(&e) => {
+                interpreter.interrupt.sleep(POLL);
+                interpreter.step(paren)?;
+            }
+            Err(e) => return Err(io_error(paren, e)),
+        }
+    };
+
+    stream
+        .set_nonblocking(false)
+        .map_err(|e| io_error(paren, e))?;
+    Ok(Object::new_native::<Socket>("socket", Some(stream)))
+}
+
+/// `tcpRead(socket, max)` reads at most `max` bytes, and no more than 64 KiB,
+/// blocking until some arrive. Empty bytes mean the other end closed the
+/// connection.
+fn tcp_read(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let max = expect_number(paren, &args[1], "Read size must be a number.")?;
+    if max.fract() != 0.0 || max < 0.0 {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Read size must be a non-negative integer."),
+        ));
+    }
+
+    let mut buffer = vec![0; (max as usize).min(MAX_READ)];
+    // Read in slices of `POLL`, so waiting for data can be interrupted.
+    let read = loop {
+        let attempt = with_socket(paren, &args[0], |stream| {
+            stream.set_read_timeout(Some(POLL))?;
+            match stream.read(&mut buffer) {
+                Err(e) if timed_out(&e) => Ok(None),
+                result => result.map(Some),
+            }
+        })?;
+        match attempt {
+            Some(read) => break read,
+            None => interpreter.step(paren)?,
+        }
+    };
+    buffer.truncate(read);
+    Ok(Object::new_bytes(buffer))
+}
+
+/// `tcpWrite(socket, data)` writes all of a string or bytes to the socket.
+fn tcp_write(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let data = match &*args[1].read().unwrap() {
+        Object::Bytes(b) => b.clone(),
+        Object::String(s) => s.clone().into_bytes(),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only write strings and bytes."),
+            ))
+        }
+    };
+
+    with_socket(paren, &args[0], |stream| stream.write_all(&data))?;
+    Ok(Object::nil())
+}
+
+/// `tcpClose(handle)` closes a socket or listener. Closing twice is harmless.
+fn tcp_close(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    if let Object::Native(n) = &mut *args[0].write().unwrap() {
+        if let Some(socket) = n.value.downcast_mut::<Socket>() {
+            *socket = None;
+            return Ok(Object::nil());
+        }
+        if let Some(listener) = n.value.downcast_mut::<Listener>() {
+            *listener = None;
+            return Ok(Object::nil());
+        }
+    }
+
+    Err(RuntimeError::new(
+        paren.clone(),
+        String::from("Can only close sockets and listeners."),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_lox::{Outcome, TestLox};
+
+    #[test]
+    fn rejects_bad_read_sizes() {
+        for size in ["-1", "1.5", "0/0"] {
+            let source = format!("tcpRead(nil, {});", size);
+            match TestLox::new().run(&source).outcome {
+                Outcome::RuntimeError(error) => assert!(error
+                    .to_string()
+                    .contains("Read size must be a non-negative integer.")),
+                outcome => panic!("unexpected outcome {:?}", outcome),
+            }
+        }
+    }
+
+    #[test]
+    fn caps_the_read_size() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(&vec![b'x'; MAX_READ * 2]).unwrap();
+        });
+
+        let result = TestLox::new().run(&format!(
+            "var socket = tcpConnect(\"127.0.0.1\", {});
+            print len(tcpRead(socket, 1e12)) <= {};",
+            port, MAX_READ
+        ));
+        server.join().unwrap();
+        assert_eq!(result.stdout, "true\n");
+    }
+
+    /// Runs `source`, raising the interrupt after 100ms, and returns the
+    /// error it stopped with.
+    fn interrupted(source: &str) -> String {
+        let mut lox = TestLox::new();
+        let interrupt = lox.interpreter().interrupt.clone();
+        let raiser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            interrupt.raise("timeout");
+        });
+        let outcome = lox.run(source).outcome;
+        raiser.join().unwrap();
+        match outcome {
+            Outcome::RuntimeError(error) => error.to_string(),
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn interrupts_an_accept_no_client_makes() {
+        let error = interrupted("tcpAccept(tcpListen(0));");
+        assert!(error.contains("Interrupted (timeout)."), "{}", error);
+    }
+
+    #[test]
+    fn interrupts_a_read_nothing_answers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let error = interrupted(&format!(
+            "tcpRead(tcpConnect(\"127.0.0.1\", {}), 10);",
+            port
+        ));
+        assert!(error.contains("Interrupted (timeout)."), "{}", error);
+        drop(listener);
+    }
+}
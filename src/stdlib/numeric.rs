@@ -0,0 +1,161 @@
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_traits::Signed;
+use rust_decimal::Decimal;
+
+use super::expect_number;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("bigint", Object::new_builtin_function(1, bigint));
+    globals.define("decimal", Object::new_builtin_function(1, decimal));
+    globals.define("complex", Object::new_builtin_function(2, complex));
+    globals.define("re", Object::new_builtin_function(1, re));
+    globals.define("im", Object::new_builtin_function(1, im));
+    globals.define("abs", Object::new_builtin_function(1, abs));
+    globals.define("arg", Object::new_builtin_function(1, arg));
+    globals.define("polar", Object::new_builtin_function(2, polar));
+    globals.define("toPolar", Object::new_builtin_function(1, to_polar));
+}
+
+/// Reads a complex argument, taking a number as a complex number with no
+/// imaginary part.
+fn expect_complex(paren: &Token, arg: &LoxObject) -> Result<Complex64, RuntimeError> {
+    arg.read().unwrap().as_complex().ok_or_else(|| {
+        RuntimeError::new(
+            paren.clone(),
+            String::from("Argument must be a complex number or a number."),
+        )
+    })
+}
+
+/// `bigint(x)` converts an integer, or a string of decimal digits with an
+/// optional sign, to a bigint.
+fn bigint(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let value = match &*args[0].read().unwrap() {
+        Object::String(s) => s.trim().parse::<BigInt>().ok(),
+        value => value.as_bigint(),
+    };
+    value.map(Object::new_bigint).ok_or_else(|| {
+        RuntimeError::new(
+            paren.clone(),
+            String::from("Can only convert integers and strings of digits to bigints."),
+        )
+    })
+}
+
+/// `decimal(x)` converts a string like `"0.1"`, or a number as it prints, to
+/// a decimal, which adds, subtracts, multiplies and divides without rounding
+/// to binary. Decimals hold 28 significant digits.
+fn decimal(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let value = match &*args[0].read().unwrap() {
+        Object::String(s) => s.trim().parse::<Decimal>().ok(),
+        Object::Number(n) => n.to_string().parse::<Decimal>().ok(),
+        value => value.as_decimal(),
+    };
+    value.map(Object::new_decimal).ok_or_else(|| {
+        RuntimeError::new(
+            paren.clone(),
+            String::from("Can only convert numbers and numeric strings to decimals."),
+        )
+    })
+}
+
+/// `complex(re, im)` makes the complex number `re + im*i`. Complex numbers
+/// mix with numbers in arithmetic, but can't be ordered.
+fn complex(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let re = expect_number(paren, &args[0], "Real part must be a number.")?;
+    let im = expect_number(paren, &args[1], "Imaginary part must be a number.")?;
+    Ok(Object::new_complex(Complex64::new(re, im)))
+}
+
+/// `re(z)` is the real part of a complex number.
+fn re(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_number(expect_complex(paren, &args[0])?.re))
+}
+
+/// `im(z)` is the imaginary part of a complex number.
+fn im(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_number(expect_complex(paren, &args[0])?.im))
+}
+
+/// `abs(x)` is the absolute value of a number, bigint or decimal, or the
+/// magnitude of a complex number.
+fn abs(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &*args[0].read().unwrap() {
+        Object::Number(n) => Ok(Object::new_number(n.abs())),
+        Object::BigInt(b) => Ok(Object::new_bigint(b.abs())),
+        Object::Decimal(d) => Ok(Object::new_decimal(d.abs())),
+        Object::Complex(z) => Ok(Object::new_number(z.norm())),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only take the absolute value of numbers."),
+        )),
+    }
+}
+
+/// `arg(z)` is the angle of a complex number from the positive real axis, in
+/// radians between -pi and pi.
+fn arg(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_number(expect_complex(paren, &args[0])?.arg()))
+}
+
+/// `polar(r, theta)` makes the complex number with magnitude `r` and angle
+/// `theta`.
+fn polar(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let r = expect_number(paren, &args[0], "Magnitude must be a number.")?;
+    let theta = expect_number(paren, &args[1], "Angle must be a number.")?;
+    Ok(Object::new_complex(Complex64::from_polar(r, theta)))
+}
+
+/// `toPolar(z)` is the list `[r, theta]` of a complex number's magnitude and
+/// angle.
+fn to_polar(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let (r, theta) = expect_complex(paren, &args[0])?.to_polar();
+    Ok(Object::new_list(vec![
+        Object::new_number(r),
+        Object::new_number(theta),
+    ]))
+}
@@ -0,0 +1,94 @@
+use crate::{
+    environment::Environment,
+    interpreter::{check_index, check_key, Interpreter},
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+// Persistent collections never change once made. `with` and `without` return
+// a new collection sharing most of its structure with the old one, so an
+// update costs O(log n) rather than a copy. They're indexed like lists and
+// maps, and a persistent map iterates its keys in order.
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("pvec", Object::new_builtin_function(0, pvec));
+    // `pmap` is the parallel map in `thread`.
+    globals.define("pdict", Object::new_builtin_function(0, pdict));
+    globals.define("with", Object::new_builtin_function(3, with));
+    globals.define("without", Object::new_builtin_function(2, without));
+}
+
+/// `pvec()` creates an empty persistent vector.
+fn pvec(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_persistent_vector(im::Vector::new()))
+}
+
+/// `pdict()` creates an empty persistent map.
+fn pdict(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_persistent_map(im::OrdMap::new()))
+}
+
+/// `with(collection, key, value)` returns a copy of the collection with `key`
+/// set to `value`. A vector's key is an index, and the index just past the end
+/// appends.
+fn with(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let value = args[2].clone();
+    match &*args[0].read().unwrap() {
+        Object::PersistentVector(values) => {
+            let i = check_index(paren, &args[1], values.len() + 1)?;
+            let mut values = values.clone();
+            if i == values.len() {
+                values.push_back(value);
+            } else {
+                values.set(i, value);
+            }
+            Ok(Object::new_persistent_vector(values))
+        }
+        Object::PersistentMap(map) => {
+            let key = check_key(paren, &args[1])?;
+            Ok(Object::new_persistent_map(map.update(key, value)))
+        }
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only update persistent vectors and maps."),
+        )),
+    }
+}
+
+/// `without(collection, key)` returns a copy of the collection with `key`
+/// removed. Removing from a vector shifts the elements after it down.
+fn without(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &*args[0].read().unwrap() {
+        Object::PersistentVector(values) => {
+            let i = check_index(paren, &args[1], values.len())?;
+            let mut values = values.clone();
+            values.remove(i);
+            Ok(Object::new_persistent_vector(values))
+        }
+        Object::PersistentMap(map) => {
+            let key = check_key(paren, &args[1])?;
+            Ok(Object::new_persistent_map(map.without(&key)))
+        }
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only update persistent vectors and maps."),
+        )),
+    }
+}
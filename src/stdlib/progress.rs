@@ -0,0 +1,119 @@
+use super::expect_number;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("progress", Object::new_builtin_function(1, progress));
+    globals.define("tick", Object::new_builtin_function(1, tick));
+    globals.define("finish", Object::new_builtin_function(1, finish));
+}
+
+/// How many characters wide the bar itself is.
+const WIDTH: u64 = 30;
+
+struct ProgressBar {
+    total: u64,
+    done: u64,
+    finished: bool,
+}
+
+impl ProgressBar {
+    /// The bar as one line, like `[#######-------] 5/10  50%`. Ticks past the
+    /// total are counted but don't overfill it.
+    fn line(&self) -> String {
+        let done = self.done.min(self.total);
+        let (filled, percent) = match self.total {
+            0 => (WIDTH, 100),
+            total => (done * WIDTH / total, done * 100 / total),
+        };
+        format!(
+            "[{}{}] {}/{} {:>3}%",
+            "#".repeat(filled as usize),
+            "-".repeat((WIDTH - filled) as usize),
+            self.done,
+            self.total,
+            percent
+        )
+    }
+}
+
+/// Runs `f` on the unfinished progress bar in `arg`, then redraws it over its
+/// last line on the interpreter's stderr.
+fn update(
+    interpreter: &Interpreter,
+    paren: &Token,
+    arg: &LoxObject,
+    f: impl FnOnce(&mut ProgressBar),
+) -> Result<LoxObject, RuntimeError> {
+    let mut object = arg.write().unwrap();
+    let bar = match &mut *object {
+        Object::Native(n) => n.value.downcast_mut::<ProgressBar>(),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::new(paren.clone(), String::from("Expected a progress bar.")))?;
+    if bar.finished {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Progress bar is already finished."),
+        ));
+    }
+
+    f(bar);
+    let end = if bar.finished { "\n" } else { "" };
+    let mut stderr = interpreter.stderr.lock().unwrap();
+    // Progress is best-effort; a closed stderr shouldn't stop the script.
+    let _ = write!(stderr, "\r{}{}", bar.line(), end);
+    let _ = stderr.flush();
+    Ok(Object::nil())
+}
+
+/// `progress(total)` draws an empty progress bar for `total` steps on stderr
+/// and returns it, to be advanced with `tick` and ended with `finish`.
+fn progress(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let total = expect_number(paren, &args[0], "Progress total must be a number.")?;
+    if total.fract() != 0.0 || total < 0.0 {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Progress total must be a non-negative integer."),
+        ));
+    }
+
+    let bar = Object::new_native(
+        "progress bar",
+        ProgressBar {
+            total: total as u64,
+            done: 0,
+            finished: false,
+        },
+    );
+    update(interpreter, paren, &bar, |_| {})?;
+    Ok(bar)
+}
+
+/// `tick(bar)` advances a progress bar by one step and redraws it.
+fn tick(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    update(interpreter, paren, &args[0], |bar| bar.done += 1)
+}
+
+/// `finish(bar)` draws a progress bar one last time and moves to the next
+/// line. The bar can't be ticked after that.
+fn finish(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    update(interpreter, paren, &args[0], |bar| bar.finished = true)
+}
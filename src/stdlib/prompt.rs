@@ -0,0 +1,96 @@
+use std::io::{BufRead, Write};
+
+use super::expect_string;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("prompt", Object::new_builtin_function(1, prompt));
+    globals.define("confirm", Object::new_builtin_function(1, confirm));
+    globals.define(
+        "passwordPrompt",
+        Object::new_builtin_function(1, password_prompt),
+    );
+}
+
+/// Shows `message` and reads a line of input with `read`, which returns the
+/// line with its newline, or nothing at the end of input. Input is recorded
+/// and replayed like any other nondeterministic result.
+fn ask(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    source: &str,
+    read: impl FnOnce() -> Option<String>,
+) -> Result<Option<String>, RuntimeError> {
+    let line = interpreter
+        .recorder
+        .lock()
+        .unwrap()
+        .bytes(source, || read().unwrap_or_default().into_bytes())
+        .map_err(|message| RuntimeError::new(paren.clone(), message))?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let line = String::from_utf8_lossy(&line);
+    Ok(Some(line.trim_end_matches(&['\r', '\n'][..]).to_owned()))
+}
+
+fn read_line(message: &str) -> Option<String> {
+    print!("{}", message);
+    std::io::stdout().flush().ok()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line).ok()?;
+    Some(line)
+}
+
+/// `prompt(message)` shows a message and returns the line the user types, or
+/// nil at the end of input.
+fn prompt(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let message = expect_string(paren, &args[0], "Prompt must be a string.")?;
+    let line = ask(interpreter, paren, "prompt", || read_line(&message))?;
+    Ok(line.map_or_else(Object::nil, Object::new_string))
+}
+
+/// `confirm(message)` asks a yes or no question until the user answers `y`,
+/// `yes`, `n` or `no`. The end of input counts as no.
+fn confirm(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let message = expect_string(paren, &args[0], "Prompt must be a string.")?;
+    let message = format!("{} [y/n] ", message);
+    loop {
+        let line = ask(interpreter, paren, "confirm", || read_line(&message))?;
+        match line.map(|l| l.trim().to_lowercase()).as_deref() {
+            Some("y") | Some("yes") => return Ok(Object::new_bool(true)),
+            Some("n") | Some("no") | None => return Ok(Object::new_bool(false)),
+            Some(_) => {}
+        }
+    }
+}
+
+/// `passwordPrompt(message)` shows a message and returns the line the user
+/// types without echoing it, or nil if there's no terminal to read from.
+/// Like other input, it is written to a trace when recording.
+fn password_prompt(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let message = expect_string(paren, &args[0], "Prompt must be a string.")?;
+    let line = ask(interpreter, paren, "passwordPrompt", || {
+        // Added back so an empty password isn't taken for the end of input.
+        rpassword::prompt_password(&message).ok().map(|p| p + "\n")
+    })?;
+    Ok(line.map_or_else(Object::nil, Object::new_string))
+}
@@ -0,0 +1,52 @@
+use std::sync::{Arc, Mutex};
+
+use rand::{rngs::StdRng, Rng as _, SeedableRng};
+
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("random", Object::new_builtin_function(0, random));
+}
+
+/// The random number generator behind `random`, shared with forked
+/// interpreters. It is seeded from the system unless a fixed seed is given,
+/// as tests do.
+#[derive(Clone)]
+pub struct Rng(Arc<Mutex<StdRng>>);
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        Rng(Arc::new(Mutex::new(StdRng::seed_from_u64(seed))))
+    }
+
+    fn next(&self) -> f64 {
+        self.0.lock().unwrap().gen()
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng(Arc::new(Mutex::new(StdRng::from_entropy())))
+    }
+}
+
+/// `random()` is a number from 0 up to but not including 1.
+fn random(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let n = interpreter
+        .recorder
+        .lock()
+        .unwrap()
+        .number("random", || interpreter.rng.next())
+        .map_err(|message| RuntimeError::new(paren.clone(), message))?;
+    Ok(Object::new_number(n))
+}
@@ -0,0 +1,202 @@
+use std::cmp::Ordering;
+
+use super::expect_string;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("sort", Object::new_builtin_function(1, sort));
+    globals.define("sortBy", Object::new_builtin_function(2, sort_by));
+    globals.define(
+        "compareNatural",
+        Object::new_builtin_function(2, compare_natural),
+    );
+}
+
+fn list_error(paren: &Token) -> RuntimeError {
+    RuntimeError::new(paren.clone(), String::from("Can only sort lists."))
+}
+
+/// Takes the values out of a list, so a comparator can run without the list
+/// locked.
+pub(super) fn take_list(paren: &Token, object: &LoxObject) -> Result<Vec<LoxObject>, RuntimeError> {
+    match &mut *object.write().unwrap() {
+        Object::List(values) => Ok(std::mem::take(values)),
+        _ => Err(list_error(paren)),
+    }
+}
+
+pub(super) fn put_back(object: &LoxObject, values: Vec<LoxObject>) {
+    if let Object::List(v) = &mut *object.write().unwrap() {
+        *v = values;
+    }
+}
+
+/// A stable merge sort with a comparator that can fail. Unlike `sort_by`, it
+/// doesn't mind a comparator that isn't a total order.
+fn merge_sort<F>(values: Vec<LoxObject>, compare: &mut F) -> Result<Vec<LoxObject>, RuntimeError>
+where
+    F: FnMut(&LoxObject, &LoxObject) -> Result<Ordering, RuntimeError>,
+{
+    if values.len() <= 1 {
+        return Ok(values);
+    }
+    let mut left = values;
+    let right = left.split_off(left.len() / 2);
+    let left = merge_sort(left, compare)?;
+    let right = merge_sort(right, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        if compare(r, l)? == Ordering::Less {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+/// `sort(list)` sorts a list of numbers or a list of strings in place.
+/// Strings are compared by their characters' code points.
+fn sort(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let mut values = take_list(paren, &args[0])?;
+    let numbers = values
+        .iter()
+        .all(|v| matches!(*v.read().unwrap(), Object::Number(_)));
+    let strings = values
+        .iter()
+        .all(|v| matches!(*v.read().unwrap(), Object::String(_)));
+    if !numbers && !strings {
+        put_back(&args[0], values);
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only sort lists of all numbers or all strings."),
+        ));
+    }
+
+    values.sort_by(|a, b| match (&*a.read().unwrap(), &*b.read().unwrap()) {
+        (Object::Number(a), Object::Number(b)) => a.total_cmp(b),
+        (Object::String(a), Object::String(b)) => a.cmp(b),
+        _ => Ordering::Equal,
+    });
+    put_back(&args[0], values);
+    Ok(Object::nil())
+}
+
+/// `sortBy(list, compare)` sorts a list in place, stably, by `compare(a, b)`,
+/// which returns a negative number if `a` comes first, a positive number if
+/// `b` does, or zero if either may.
+fn sort_by(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    if args[1].read().unwrap().arity() != 2 {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Comparator must be a function taking two arguments."),
+        ));
+    }
+    let values = take_list(paren, &args[0])?;
+    let original = values.clone();
+    let compare = &args[1];
+
+    let sorted = merge_sort(values, &mut |a, b| {
+        let result =
+            compare
+                .read()
+                .unwrap()
+                .call(interpreter, paren, vec![a.clone(), b.clone()])?;
+        let order = match *result.read().unwrap() {
+            Object::Number(n) => Ok(n.partial_cmp(&0.0).unwrap_or(Ordering::Equal)),
+            _ => Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Comparator must return a number."),
+            )),
+        };
+        order
+    });
+
+    match sorted {
+        Ok(sorted) => {
+            put_back(&args[0], sorted);
+            Ok(Object::nil())
+        }
+        Err(error) => {
+            put_back(&args[0], original);
+            Err(error)
+        }
+    }
+}
+
+/// `compareNatural(a, b)` compares two strings the way a person would sort
+/// them, with runs of digits compared as numbers, so `"file2"` comes before
+/// `"file10"`. It returns -1, 0 or 1, to be passed to `sortBy`.
+fn compare_natural(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let message = "Can only compare strings naturally.";
+    let a = expect_string(paren, &args[0], message)?;
+    let b = expect_string(paren, &args[1], message)?;
+    let order = match natural_order(&a, &b) {
+        Ordering::Less => -1.0,
+        Ordering::Equal => 0.0,
+        Ordering::Greater => 1.0,
+    };
+    Ok(Object::new_number(order))
+}
+
+fn natural_order(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek(), b_chars.peek()) {
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = digit_run(&mut a_chars);
+                let y = digit_run(&mut b_chars);
+                let x = x.trim_start_matches('0');
+                let y = y.trim_start_matches('0');
+                let order = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let order = x.cmp(y);
+                if order != Ordering::Equal {
+                    return order;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+            // Equal apart from leading zeros, like "a01" and "a1".
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+        }
+    }
+}
+
+fn digit_run(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut run = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        run.push(c);
+    }
+    run
+}
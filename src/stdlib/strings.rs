@@ -0,0 +1,62 @@
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define(
+        "stringBuilder",
+        Object::new_builtin_function(0, string_builder),
+    );
+    globals.define("append", Object::new_builtin_function(2, append));
+    globals.define("toString", Object::new_builtin_function(1, to_string));
+}
+
+/// `stringBuilder()` creates an empty string builder. Appending to one copies
+/// only what's appended, where building a string with `+` copies the whole
+/// string each time.
+fn string_builder(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_string_builder())
+}
+
+/// `append(builder, value)` adds a value to the end of a string builder, as
+/// `print` would show it.
+fn append(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    // Released before taking the builder's lock, in case they're the same.
+    let value = args[1].read().unwrap().to_string();
+    match &mut *args[0].write().unwrap() {
+        Object::StringBuilder(s) => s.push_str(&value),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only append to string builders."),
+            ))
+        }
+    }
+    Ok(Object::nil())
+}
+
+/// `toString(value)` is the text a string builder has built, or any other
+/// value as `print` would show it.
+fn to_string(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let string = match &*args[0].read().unwrap() {
+        Object::StringBuilder(s) => s.clone(),
+        value => value.to_string(),
+    };
+    Ok(Object::new_string(string))
+}
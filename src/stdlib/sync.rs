@@ -0,0 +1,120 @@
+use std::sync::atomic::Ordering;
+
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("mutex", Object::new_builtin_function(0, mutex));
+    globals.define("withLock", Object::new_builtin_function(2, with_lock));
+    globals.define("atomic", Object::new_builtin_function(1, atomic));
+    globals.define("atomicAdd", Object::new_builtin_function(2, atomic_add));
+    globals.define("atomicGet", Object::new_builtin_function(1, atomic_get));
+}
+
+fn mutex(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_mutex())
+}
+
+/// `withLock(mutex, fn)` calls a function taking no arguments while holding
+/// the mutex, and returns its result. Mutexes aren't reentrant, so locking
+/// the same mutex again from inside `fn` deadlocks.
+fn with_lock(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    {
+        let function = args[1].read().unwrap();
+        if !function.is_callable() || function.arity() != 0 {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only lock around functions that take no arguments."),
+            ));
+        }
+    }
+
+    match &*args[0].read().unwrap() {
+        Object::Mutex(mutex) => {
+            // A thread that failed while holding the lock reported its own
+            // error already, so the poison flag carries no extra information.
+            let _guard = mutex.lock().unwrap_or_else(|e| e.into_inner());
+            args[1].read().unwrap().call(interpreter, paren, vec![])
+        }
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only lock mutexes."),
+        )),
+    }
+}
+
+/// `atomic(n)` creates a number cell which threads can update without a mutex.
+fn atomic(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &*args[0].read().unwrap() {
+        Object::Number(n) => Ok(Object::new_atomic(*n)),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Atomic value must be a number."),
+        )),
+    }
+}
+
+/// `atomicAdd(atomic, n)` adds `n` to the atomic and returns the new value.
+fn atomic_add(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let amount = match &*args[1].read().unwrap() {
+        Object::Number(n) => *n,
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only add numbers to atomics."),
+            ))
+        }
+    };
+
+    match &*args[0].read().unwrap() {
+        Object::Atomic(atomic) => {
+            let previous = atomic
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |bits| {
+                    Some((f64::from_bits(bits) + amount).to_bits())
+                })
+                .unwrap();
+            Ok(Object::new_number(f64::from_bits(previous) + amount))
+        }
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only add to atomics."),
+        )),
+    }
+}
+
+fn atomic_get(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &*args[0].read().unwrap() {
+        Object::Atomic(atomic) => Ok(Object::new_number(f64::from_bits(
+            atomic.load(Ordering::SeqCst),
+        ))),
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only read atomics."),
+        )),
+    }
+}
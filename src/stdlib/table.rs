@@ -0,0 +1,115 @@
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, MapKey, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("printTable", Object::new_builtin_function(1, print_table));
+}
+
+fn separator(widths: &[usize]) -> String {
+    let mut line = String::from("+");
+    for width in widths {
+        line.push_str(&"-".repeat(width + 2));
+        line.push('+');
+    }
+    line
+}
+
+/// Formats one row of cells. Numbers are right-aligned, everything else is
+/// left-aligned.
+fn row(cells: &[(String, bool)], widths: &[usize]) -> String {
+    let mut line = String::from("|");
+    for ((cell, numeric), width) in cells.iter().zip(widths) {
+        if *numeric {
+            line.push_str(&format!(" {:>width$} |", cell, width = width));
+        } else {
+            line.push_str(&format!(" {:<width$} |", cell, width = width));
+        }
+    }
+    line
+}
+
+/// `printTable(rows)` prints a list of maps as an aligned table. The columns
+/// are every key in the maps, in the order they first appear, and missing
+/// entries are left blank.
+fn print_table(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let bad_rows = || {
+        RuntimeError::new(
+            paren.clone(),
+            String::from("Can only print a list of maps as a table."),
+        )
+    };
+
+    let rows = match &*args[0].read().unwrap() {
+        Object::List(rows) => rows.clone(),
+        _ => return Err(bad_rows()),
+    };
+
+    let mut columns: Vec<MapKey> = vec![];
+    for r in &rows {
+        match &*r.read().unwrap() {
+            Object::Map(map) => {
+                for (key, _) in map.iter() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+            _ => return Err(bad_rows()),
+        }
+    }
+
+    let header = columns
+        .iter()
+        .map(|k| (k.to_object().read().unwrap().to_string(), false))
+        .collect::<Vec<_>>();
+    let cells = rows
+        .iter()
+        .map(|r| match &*r.read().unwrap() {
+            Object::Map(map) => columns
+                .iter()
+                .map(|k| match map.get(k) {
+                    Some(v) => {
+                        let v = v.read().unwrap();
+                        (v.to_string(), v.is_number())
+                    }
+                    None => (String::new(), false),
+                })
+                .collect::<Vec<_>>(),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+
+    let widths = (0..columns.len())
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(&cells)
+                .map(|r| r[i].0.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let mut lines = vec![
+        separator(&widths),
+        row(&header, &widths),
+        separator(&widths),
+    ];
+    lines.extend(cells.iter().map(|r| row(r, &widths)));
+    if !cells.is_empty() {
+        lines.push(separator(&widths));
+    }
+    let mut stdout = interpreter.stdout.lock().unwrap();
+    for line in lines {
+        let _ = writeln!(stdout, "{}", line);
+    }
+    Ok(Object::nil())
+}
@@ -0,0 +1,108 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::SystemTime,
+};
+
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("tempFile", Object::new_builtin_function(0, temp_file));
+    globals.define("tempDir", Object::new_builtin_function(0, temp_dir));
+}
+
+/// Temporary files and directories created by a script, to be removed when
+/// the interpreter shuts down. Forked interpreters share their parent's.
+#[derive(Clone, Default)]
+pub struct TempPaths(Arc<Mutex<Vec<PathBuf>>>);
+
+impl TempPaths {
+    /// Removes every path created so far, ignoring any already gone.
+    pub fn clean_up(&self) {
+        for path in self.0.lock().unwrap().drain(..) {
+            if path.is_dir() {
+                let _ = std::fs::remove_dir_all(&path);
+            } else {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+
+    /// Creates a new path in the system's temporary directory with `create`,
+    /// trying new names until one isn't taken.
+    fn create(&self, create: impl Fn(&PathBuf) -> std::io::Result<()>) -> std::io::Result<PathBuf> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let nanos = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .subsec_nanos();
+            let name = format!(
+                "rustlox-{}-{}-{}",
+                std::process::id(),
+                COUNT.fetch_add(1, Ordering::Relaxed),
+                nanos
+            );
+            let path = std::env::temp_dir().join(name);
+            match create(&path) {
+                Ok(()) => {
+                    self.0.lock().unwrap().push(path.clone());
+                    return Ok(path);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+fn new_temp_path(
+    interpreter: &Interpreter,
+    paren: &Token,
+    create: impl Fn(&PathBuf) -> std::io::Result<()>,
+) -> Result<LoxObject, RuntimeError> {
+    let path = interpreter.temp_paths.create(create).map_err(|e| {
+        RuntimeError::new(
+            paren.clone(),
+            format!("Could not create temporary path: {}.", e),
+        )
+    })?;
+    Ok(Object::new_string(path.to_string_lossy().into_owned()))
+}
+
+/// `tempFile()` creates an empty file with a unique name in the system's
+/// temporary directory and returns its path. It is removed when the script
+/// finishes.
+fn temp_file(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    new_temp_path(interpreter, paren, |path| {
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map(drop)
+    })
+}
+
+/// `tempDir()` creates an empty directory with a unique name in the system's
+/// temporary directory and returns its path. It is removed, with everything
+/// in it, when the script finishes.
+fn temp_dir(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    new_temp_path(interpreter, paren, |path| std::fs::create_dir(path))
+}
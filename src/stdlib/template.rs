@@ -0,0 +1,163 @@
+use super::expect_string;
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, MapKey, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("render", Object::new_builtin_function(2, render));
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Value(String),
+    Each(String, Vec<Node>),
+    If(String, Vec<Node>, Vec<Node>),
+}
+
+/// Parses template text into nodes, stopping at the closing tag of the block
+/// being parsed, if any. Returns the nodes and the tag which ended them.
+fn parse<'a>(
+    template: &mut &'a str,
+    block: Option<&str>,
+) -> Result<(Vec<Node>, Option<&'a str>), String> {
+    let mut nodes = vec![];
+
+    loop {
+        let open = match template.find("{{") {
+            Some(open) => open,
+            None => {
+                if !template.is_empty() {
+                    nodes.push(Node::Text(template.to_string()));
+                }
+                *template = "";
+                return match block {
+                    Some(block) => Err(format!("Unclosed '{{{{#{}}}}}' block.", block)),
+                    None => Ok((nodes, None)),
+                };
+            }
+        };
+        if open > 0 {
+            nodes.push(Node::Text(template[..open].to_string()));
+        }
+
+        let close = template[open..]
+            .find("}}")
+            .ok_or_else(|| String::from("Unclosed '{{' tag."))?;
+        let tag = template[open + 2..open + close].trim();
+        *template = &template[open + close + 2..];
+
+        if let Some(path) = tag.strip_prefix("#each ") {
+            let (body, _) = parse(template, Some("each"))?;
+            nodes.push(Node::Each(path.trim().to_string(), body));
+        } else if let Some(path) = tag.strip_prefix("#if ") {
+            let (then_branch, end) = parse(template, Some("if"))?;
+            let else_branch = if end == Some("else") {
+                parse(template, Some("if"))?.0
+            } else {
+                vec![]
+            };
+            nodes.push(Node::If(path.trim().to_string(), then_branch, else_branch));
+        } else if tag == "else" && block == Some("if") {
+            return Ok((nodes, Some(tag)));
+        } else if let Some(name) = tag.strip_prefix('/') {
+            return if block == Some(name.trim()) {
+                Ok((nodes, Some(tag)))
+            } else {
+                Err(format!("Unexpected '{{{{{}}}}}'.", tag))
+            };
+        } else if tag.starts_with('#') || tag == "else" {
+            return Err(format!("Unknown block '{{{{{}}}}}'.", tag));
+        } else {
+            nodes.push(Node::Value(tag.to_string()));
+        }
+    }
+}
+
+/// Looks `path` up in the innermost context which has its first segment.
+/// `this` is the innermost context itself, and `a.b` looks `b` up in `a`.
+fn lookup(contexts: &[LoxObject], path: &str) -> Option<LoxObject> {
+    let mut segments = path.split('.');
+    let first = segments.next()?;
+
+    let mut value = if first == "this" {
+        contexts.last().cloned()
+    } else {
+        contexts
+            .iter()
+            .rev()
+            .find_map(|c| match &*c.read().unwrap() {
+                Object::Map(map) => map.get(&MapKey::from(first)).cloned(),
+                _ => None,
+            })
+    }?;
+
+    for segment in segments {
+        let next = match &*value.read().unwrap() {
+            Object::Map(map) => map.get(&MapKey::from(segment)).cloned(),
+            _ => None,
+        }?;
+        value = next;
+    }
+    Some(value)
+}
+
+fn render_nodes(nodes: &[Node], contexts: &mut Vec<LoxObject>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value(path) => {
+                if let Some(value) = lookup(contexts, path) {
+                    out.push_str(&value.read().unwrap().to_string());
+                }
+            }
+            Node::Each(path, body) => {
+                let items = match lookup(contexts, path) {
+                    Some(value) => match &*value.read().unwrap() {
+                        Object::List(items) => items.clone(),
+                        _ => vec![],
+                    },
+                    None => vec![],
+                };
+                for item in items {
+                    contexts.push(item);
+                    render_nodes(body, contexts, out);
+                    contexts.pop();
+                }
+            }
+            Node::If(path, then_branch, else_branch) => {
+                let condition = lookup(contexts, path)
+                    .map(|v| v.read().unwrap().as_bool())
+                    .unwrap_or(false);
+                if condition {
+                    render_nodes(then_branch, contexts, out);
+                } else {
+                    render_nodes(else_branch, contexts, out);
+                }
+            }
+        }
+    }
+}
+
+/// `render(template, map)` fills in a template with values from a map.
+/// `{{key}}` inserts a value, `{{#each key}}...{{/each}}` repeats its body for
+/// every element of a list, and `{{#if key}}...{{else}}...{{/if}}` renders one
+/// branch depending on whether the value is truthy. Missing keys render as
+/// nothing.
+fn render(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let template = expect_string(paren, &args[0], "Template must be a string.")?;
+    let (nodes, _) = parse(&mut template.as_str(), None)
+        .map_err(|message| RuntimeError::new(paren.clone(), message))?;
+
+    let mut out = String::new();
+    render_nodes(&nodes, &mut vec![args[1].clone()], &mut out);
+    Ok(Object::new_string(out))
+}
@@ -0,0 +1,118 @@
+use std::io::{IsTerminal, Write};
+
+use super::{expect_number, expect_string};
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("termWidth", Object::new_builtin_function(0, term_width));
+    globals.define("clearScreen", Object::new_builtin_function(0, clear_screen));
+    globals.define("cursorTo", Object::new_builtin_function(2, cursor_to));
+    globals.define("style", Object::new_builtin_function(2, style));
+}
+
+/// The SGR parameter for each word `style` accepts.
+const STYLES: &[(&str, u8)] = &[
+    ("bold", 1),
+    ("dim", 2),
+    ("italic", 3),
+    ("underline", 4),
+    ("inverse", 7),
+    ("black", 30),
+    ("red", 31),
+    ("green", 32),
+    ("yellow", 33),
+    ("blue", 34),
+    ("magenta", 35),
+    ("cyan", 36),
+    ("white", 37),
+    ("gray", 90),
+];
+
+/// Writes an escape sequence to stdout, unless it isn't a terminal.
+fn control(sequence: &str) {
+    let mut stdout = std::io::stdout();
+    if stdout.is_terminal() {
+        let _ = write!(stdout, "{}", sequence);
+        let _ = stdout.flush();
+    }
+}
+
+/// `termWidth()` is the width of the terminal in columns, or 80 if stdout
+/// isn't a terminal.
+fn term_width(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let width = terminal_size::terminal_size_of(std::io::stdout())
+        .map_or(80, |(terminal_size::Width(width), _)| width);
+    Ok(Object::new_number(width.into()))
+}
+
+/// `clearScreen()` clears the terminal and moves the cursor to the top left.
+fn clear_screen(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    control("\x1b[2J\x1b[H");
+    Ok(Object::nil())
+}
+
+/// `cursorTo(x, y)` moves the cursor to column `x` of row `y`, counting from
+/// 0 at the top left.
+fn cursor_to(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let message = "Cursor position must be a non-negative integer.";
+    let x = expect_number(paren, &args[0], message)?;
+    let y = expect_number(paren, &args[1], message)?;
+    if [x, y].iter().any(|n| n.fract() != 0.0 || *n < 0.0) {
+        return Err(RuntimeError::new(paren.clone(), String::from(message)));
+    }
+    control(&format!("\x1b[{};{}H", y + 1.0, x + 1.0));
+    Ok(Object::nil())
+}
+
+/// `style(text, styles)` is `text` with the space-separated styles applied,
+/// like `"red bold"`: a color from `black`, `red`, `green`, `yellow`, `blue`,
+/// `magenta`, `cyan`, `white` and `gray`, and any of `bold`, `dim`,
+/// `italic`, `underline` and `inverse`. If stdout isn't a terminal, it is
+/// just `text`.
+fn style(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let text = expect_string(paren, &args[0], "Can only style strings.")?;
+    let styles = expect_string(paren, &args[1], "Styles must be a string.")?;
+    let codes = styles
+        .split_whitespace()
+        .map(|word| {
+            STYLES
+                .iter()
+                .find(|(name, _)| *name == word)
+                .map(|(_, code)| code.to_string())
+                .ok_or_else(|| {
+                    RuntimeError::new(paren.clone(), format!("Unknown style '{}'.", word))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if codes.is_empty() || !std::io::stdout().is_terminal() {
+        return Ok(Object::new_string(text));
+    }
+    Ok(Object::new_string(format!(
+        "\x1b[{}m{}\x1b[0m",
+        codes.join(";"),
+        text
+    )))
+}
@@ -0,0 +1,268 @@
+use std::{sync::mpsc::RecvTimeoutError, time::Duration};
+
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxObject, Object},
+    purity::PurityChecker,
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("spawn", Object::new_builtin_function(1, spawn));
+    globals.define("join", Object::new_builtin_function(1, join));
+    globals.define("channel", Object::new_builtin_function(0, channel));
+    globals.define("send", Object::new_builtin_function(2, send));
+    globals.define("receive", Object::new_builtin_function(1, receive));
+    globals.define("pmap", Object::new_builtin_function(2, pmap));
+}
+
+/// `spawn(fn)` runs a function taking no arguments on a new thread and
+/// returns a handle which can be passed to `join`.
+///
+/// Any value may cross to another thread, whether captured by `fn` or sent
+/// on a channel. Values are shared by reference behind their own locks, and
+/// thread handles included, as only one `join` can take the thread.
+fn spawn(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let function = args[0].clone();
+    {
+        let function = function.read().unwrap();
+        if !function.is_callable() || function.arity() != 0 {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only spawn functions that take no arguments."),
+            ));
+        }
+    }
+
+    let mut interpreter = interpreter.fork();
+    let paren = paren.clone();
+    let handle = std::thread::spawn(move || {
+        let result = function
+            .read()
+            .unwrap()
+            .call(&mut interpreter, &paren, vec![]);
+        result.unwrap_or_else(|e| {
+            crate::runtime_error(e);
+            Object::nil()
+        })
+    });
+
+    Ok(Object::new_thread(handle))
+}
+
+/// `join(thread)` waits for a spawned thread to finish and returns its result.
+fn join(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let handle = match &mut *args[0].write().unwrap() {
+        Object::Thread(handle) => handle.take(),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only join threads."),
+            ))
+        }
+    };
+
+    match handle {
+        Some(handle) => handle
+            .join()
+            .map_err(|_| RuntimeError::new(paren.clone(), String::from("Joined thread panicked."))),
+        None => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Thread has already been joined."),
+        )),
+    }
+}
+
+/// `channel()` creates a channel which any thread can `send` values into and
+/// `receive` values from.
+fn channel(
+    _interpreter: &mut Interpreter,
+    _paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    Ok(Object::new_channel())
+}
+
+fn send(
+    _interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &*args[0].read().unwrap() {
+        Object::Channel(channel) => {
+            // The channel holds its own receiver, so sending can't fail.
+            channel.sender.send(args[1].clone()).unwrap();
+            Ok(Object::nil())
+        }
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only send values into channels."),
+        )),
+    }
+}
+
+/// How long `receive` waits at a time before checking whether the script has
+/// been interrupted.
+const RECEIVE_POLL: Duration = Duration::from_millis(50);
+
+/// `receive(channel)` blocks until a value is sent into the channel.
+fn receive(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let channel = args[0].read().unwrap();
+    let receiver = match &*channel {
+        Object::Channel(channel) => channel.receiver.lock().unwrap(),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only receive values from channels."),
+            ))
+        }
+    };
+
+    // The channel holds its own sender, so nothing may ever be sent, and the
+    // wait has to stop if the script is interrupted.
+    loop {
+        match receiver.recv_timeout(RECEIVE_POLL) {
+            Ok(value) => return Ok(value),
+            Err(RecvTimeoutError::Timeout) => interpreter.step(paren)?,
+            Err(RecvTimeoutError::Disconnected) => return Ok(Object::nil()),
+        }
+    }
+}
+
+/// `pmap(list, fn)` calls a pure function taking one argument on each item of
+/// a list, splitting the list between a worker thread per CPU, and returns the
+/// results in order. Each worker runs its own fork of the interpreter.
+fn pmap(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let items = match &*args[0].read().unwrap() {
+        Object::List(items) => items.clone(),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Can only pmap over lists."),
+            ))
+        }
+    };
+    let function = args[1].clone();
+    if !function.read().unwrap().is_callable() || function.read().unwrap().arity() != 1 {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Can only pmap functions that take one argument."),
+        ));
+    }
+    PurityChecker::check(&function, &interpreter.globals.read().unwrap()).map_err(|reason| {
+        RuntimeError::new(
+            paren.clone(),
+            format!("Can only pmap pure functions, but {}.", reason),
+        )
+    })?;
+
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = items.len().div_ceil(workers).max(1);
+    let results = std::thread::scope(|scope| {
+        let handles = items
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let mut interpreter = interpreter.fork();
+                let function = &function;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|item| {
+                            function.read().unwrap().call(
+                                &mut interpreter,
+                                paren,
+                                vec![item.clone()],
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    Ok(Object::new_list(results.concat()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::test_lox::{Outcome, TestLox};
+
+    #[test]
+    fn interrupts_a_receive_nothing_will_answer() {
+        let mut lox = TestLox::new();
+        let interrupt = lox.interpreter().interrupt.clone();
+        let raiser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            interrupt.raise("timeout");
+        });
+        let outcome = lox.run("receive(channel());").outcome;
+        raiser.join().unwrap();
+        match outcome {
+            Outcome::RuntimeError(error) => {
+                assert!(error.to_string().contains("Interrupted (timeout)."))
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn receives_from_another_thread() {
+        let result = TestLox::new().run(
+            "var c = channel();
+            var t = spawn(fun () { send(c, 42); });
+            print receive(c);
+            join(t);",
+        );
+        assert_eq!(result.stdout, "42\n");
+    }
+
+    #[test]
+    fn joins_a_thread_handle_sent_to_another_thread() {
+        let result = TestLox::new().run(
+            "var c = channel();
+            var worker = spawn(fun () { return 1; });
+            var joiner = spawn(fun () { return join(receive(c)) + 1; });
+            send(c, worker);
+            print join(joiner);",
+        );
+        assert_eq!(result.stdout, "2\n");
+
+        let result = TestLox::new().run(
+            "var t = spawn(fun () {});
+            join(spawn(fun () { join(t); }));
+            join(t);",
+        );
+        match result.outcome {
+            Outcome::RuntimeError(error) => {
+                assert!(error
+                    .to_string()
+                    .contains("Thread has already been joined."))
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    environment::Environment,
+    interpreter::Interpreter,
+    object::{LoxFunction, LoxObject, Object},
+    runtime_error::RuntimeError,
+    token::Token,
+};
+
+pub fn define_natives(globals: &mut Environment) {
+    globals.define("setTimeout", Object::new_builtin_function(2, set_timeout));
+    globals.define("setInterval", Object::new_builtin_function(2, set_interval));
+    globals.define("clearTimer", Object::new_builtin_function(1, clear_timer));
+    globals.define("runLoop", Object::new_builtin_function(0, run_loop));
+}
+
+#[derive(Clone)]
+enum Job {
+    Callback(LoxObject),
+    /// The body of an `async` function, which settles `task` once it has run.
+    Task {
+        function: Box<LoxFunction>,
+        arguments: Vec<LoxObject>,
+        task: LoxObject,
+    },
+}
+
+struct Timer {
+    id: usize,
+    due: Instant,
+    interval: Option<Duration>,
+    job: Job,
+}
+
+/// The event queue driven by `runLoop` and `await`. Timers fire in order of
+/// their due time, and timers due at the same time fire in the order they
+/// were set. Calls to `async` functions are queued as timers due immediately.
+pub struct TimerQueue {
+    timers: Vec<Timer>,
+    next_id: usize,
+}
+
+impl TimerQueue {
+    pub fn new() -> Self {
+        Self {
+            timers: vec![],
+            next_id: 1,
+        }
+    }
+
+    fn schedule(&mut self, job: Job, due: Instant, interval: Option<Duration>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.timers.push(Timer {
+            id,
+            due,
+            interval,
+            job,
+        });
+        id
+    }
+
+    /// Queues a call to an `async` function and returns its pending task.
+    pub fn schedule_task(&mut self, function: LoxFunction, arguments: Vec<LoxObject>) -> LoxObject {
+        let task = Object::new_task();
+        self.schedule(
+            Job::Task {
+                function: Box::new(function),
+                arguments,
+                task: task.clone(),
+            },
+            Instant::now(),
+            None,
+        );
+        task
+    }
+
+    fn cancel(&mut self, id: usize) {
+        self.timers.retain(|t| t.id != id);
+    }
+
+    /// Removes the next timer to fire, rescheduling it first if it repeats.
+    fn pop(&mut self) -> Option<(Instant, Job)> {
+        let index =
+            (0..self.timers.len()).min_by_key(|&i| (self.timers[i].due, self.timers[i].id))?;
+        let due = self.timers[index].due;
+        let job = self.timers[index].job.clone();
+        // A repeat too far off to represent would never come due anyway.
+        match self.timers[index]
+            .interval
+            .and_then(|interval| due.checked_add(interval))
+        {
+            Some(next) => self.timers[index].due = next,
+            None => {
+                self.timers.remove(index);
+            }
+        }
+        Some((due, job))
+    }
+}
+
+impl Default for TimerQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Waits for the next timer to come due and runs it. Returns `false` if there
+/// were no timers left to run.
+pub fn run_next(interpreter: &mut Interpreter, token: &Token) -> Result<bool, RuntimeError> {
+    let (due, job) = match interpreter.timers.pop() {
+        Some(next) => next,
+        None => return Ok(false),
+    };
+
+    let now = Instant::now();
+    if due > now {
+        interpreter.interrupt.sleep(due - now);
+    }
+    // Callbacks run straight from here rather than from a loop or call in
+    // the script, so this is where the event loop can be stopped.
+    interpreter.step(token)?;
+
+    match job {
+        Job::Callback(callback) => {
+            callback.read().unwrap().call(interpreter, token, vec![])?;
+        }
+        Job::Task {
+            function,
+            arguments,
+            task,
+        } => {
+            let result = function.invoke(interpreter, token, arguments)?;
+            *task.write().unwrap() = Object::Task(Some(result));
+        }
+    }
+    Ok(true)
+}
+
+/// Checks a timer's callback and delay, returning the delay and when the
+/// timer is first due.
+fn check_timer_args(
+    paren: &Token,
+    args: &[LoxObject],
+) -> Result<(Duration, Instant), RuntimeError> {
+    let function = args[0].read().unwrap();
+    if !function.is_callable() || function.arity() != 0 {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Timer callback must be a function that takes no arguments."),
+        ));
+    }
+
+    let delay = match &*args[1].read().unwrap() {
+        Object::Number(ms) if *ms >= 0.0 => Duration::try_from_secs_f64(ms / 1000.0).ok(),
+        _ => {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                String::from("Timer delay must be a non-negative number of milliseconds."),
+            ))
+        }
+    };
+    match delay.and_then(|delay| Some((delay, Instant::now().checked_add(delay)?))) {
+        Some(timing) => Ok(timing),
+        None => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Timer delay is too long."),
+        )),
+    }
+}
+
+/// `setTimeout(fn, ms)` schedules `fn` to be called once, `ms` milliseconds
+/// into `runLoop`, and returns an id which can be passed to `clearTimer`.
+fn set_timeout(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let (_, due) = check_timer_args(paren, &args)?;
+    let id = interpreter
+        .timers
+        .schedule(Job::Callback(args[0].clone()), due, None);
+    Ok(Object::new_number(id as f64))
+}
+
+/// `setInterval(fn, ms)` schedules `fn` to be called every `ms` milliseconds
+/// until it is cleared with `clearTimer`.
+fn set_interval(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    let (delay, due) = check_timer_args(paren, &args)?;
+    if delay == Duration::from_secs(0) {
+        return Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Interval must be greater than zero."),
+        ));
+    }
+    let id = interpreter
+        .timers
+        .schedule(Job::Callback(args[0].clone()), due, Some(delay));
+    Ok(Object::new_number(id as f64))
+}
+
+fn clear_timer(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    match &*args[0].read().unwrap() {
+        Object::Number(id) => {
+            interpreter.timers.cancel(*id as usize);
+            Ok(Object::nil())
+        }
+        _ => Err(RuntimeError::new(
+            paren.clone(),
+            String::from("Timer id must be a number."),
+        )),
+    }
+}
+
+/// `runLoop()` fires timers as they come due until none are left.
+fn run_loop(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    _args: Vec<LoxObject>,
+) -> Result<LoxObject, RuntimeError> {
+    while run_next(interpreter, paren)? {}
+    Ok(Object::nil())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        sandbox::Limits,
+        test_lox::{Outcome, TestLox},
+    };
+
+    /// Runs `source`, raising the interrupt after 100ms, and returns the
+    /// error it stopped with.
+    fn interrupted(source: &str) -> String {
+        let mut lox = TestLox::new();
+        let interrupt = lox.interpreter().interrupt.clone();
+        let raiser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            interrupt.raise("timeout");
+        });
+        let start = Instant::now();
+        let outcome = lox.run(source).outcome;
+        raiser.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        match outcome {
+            Outcome::RuntimeError(error) => error.to_string(),
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn fires_timers_in_order() {
+        let result = TestLox::new().run(
+            "setTimeout(fun () { print 2; }, 2); setTimeout(fun () { print 1; }, 1); runLoop();",
+        );
+        assert_eq!(result.stdout, "1\n2\n");
+    }
+
+    #[test]
+    fn rejects_delays_too_long_to_represent() {
+        for delay in ["1e300", "1/0"] {
+            let source = format!("setTimeout(fun () {{}}, {});", delay);
+            match TestLox::new().run(&source).outcome {
+                Outcome::RuntimeError(error) => {
+                    assert!(error.to_string().contains("Timer delay is too long."))
+                }
+                outcome => panic!("unexpected outcome {:?}", outcome),
+            }
+        }
+    }
+
+    #[test]
+    fn interrupts_a_waiting_event_loop() {
+        let error = interrupted("setTimeout(fun () {}, 100000); runLoop();");
+        assert!(error.contains("Interrupted (timeout)."), "{}", error);
+    }
+
+    #[test]
+    fn interrupts_an_endless_interval() {
+        let error = interrupted("setInterval(fun () {}, 1); runLoop();");
+        assert!(error.contains("Interrupted (timeout)."), "{}", error);
+    }
+
+    #[test]
+    fn counts_timer_callbacks_as_steps() {
+        let mut lox = TestLox::new();
+        lox.interpreter().limits = Limits {
+            steps: Some(1000),
+            ..Limits::default()
+        };
+        match lox.run("setInterval(fun () {}, 1); runLoop();").outcome {
+            Outcome::RuntimeError(error) => {
+                assert!(error.to_string().contains("Interrupted (step limit)."))
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+}
@@ -1,55 +1,107 @@
-use crate::{expr::Expr, token::Token};
-
-pub trait Visitor<T> {
-    fn visit_block_stmt(&mut self, stmt: &Block) -> T;
-    fn visit_expression_stmt(&mut self, stmt: &Expression) -> T;
-    fn visit_function_stmt(&mut self, stmt: &Function) -> T;
-    fn visit_if_stmt(&mut self, stmt: &If) -> T;
-    fn visit_print_stmt(&mut self, stmt: &Print) -> T;
-    fn visit_var_stmt(&mut self, stmt: &Var) -> T;
-    fn visit_while_stmt(&mut self, stmt: &While) -> T;
-}
+use rustlox_derive::Visitor;
 
-#[derive(Debug, Clone)]
+use crate::{
+    expr::{Expr, Variable},
+    node::{Meta, NodeId},
+    token::{Span, Token},
+};
+
+#[derive(Debug, Clone, Visitor)]
 pub enum Stmt {
     Block(Block),
+    Break(Break),
+    Class(Class),
+    Continue(Continue),
     Expression(Expression),
     Function(Function),
     If(If),
     Print(Print),
+    Return(Return),
     Var(Var),
     While(While),
 }
 
 impl Stmt {
-    pub fn accept<T>(&self, visitor: &mut impl Visitor<T>) -> T {
+    pub fn meta(&self) -> Meta {
         match self {
-            Stmt::Block(b) => visitor.visit_block_stmt(b),
-            Stmt::Expression(e) => visitor.visit_expression_stmt(e),
-            Stmt::Function(f) => visitor.visit_function_stmt(f),
-            Stmt::If(i) => visitor.visit_if_stmt(i),
-            Stmt::Print(p) => visitor.visit_print_stmt(p),
-            Stmt::Var(v) => visitor.visit_var_stmt(v),
-            Stmt::While(w) => visitor.visit_while_stmt(w),
+            Stmt::Block(node) => node.meta,
+            Stmt::Break(node) => node.meta,
+            Stmt::Class(node) => node.meta,
+            Stmt::Continue(node) => node.meta,
+            Stmt::Expression(node) => node.meta,
+            Stmt::Function(node) => node.meta,
+            Stmt::If(node) => node.meta,
+            Stmt::Print(node) => node.meta,
+            Stmt::Return(node) => node.meta,
+            Stmt::Var(node) => node.meta,
+            Stmt::While(node) => node.meta,
         }
     }
+
+    pub fn id(&self) -> NodeId {
+        self.meta().id
+    }
+
+    pub fn span(&self) -> Span {
+        self.meta().span
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Block {
     pub statements: Vec<Stmt>,
+    pub meta: Meta,
+}
+
+#[derive(Debug, Clone)]
+pub struct Break {
+    pub keyword: Token,
+    pub meta: Meta,
+}
+
+/// `class Name < Superclass { methods }`, where the superclass is optional.
+/// Methods declared with a `class` prefix are called on the class itself.
+#[derive(Debug, Clone)]
+pub struct Class {
+    pub name: Token,
+    pub superclass: Option<Variable>,
+    pub methods: Vec<Function>,
+    pub class_methods: Vec<Function>,
+    pub meta: Meta,
+}
+
+#[derive(Debug, Clone)]
+pub struct Continue {
+    pub keyword: Token,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
 pub struct Expression {
     pub expression: Expr,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
 pub struct Function {
     pub name: Token,
-    pub params: Vec<Token>,
+    pub is_async: bool,
+    /// Whether it's a method declared without a parameter list, which runs
+    /// when it's accessed.
+    pub is_getter: bool,
+    pub params: Vec<Param>,
+    /// The type after `->`, if any.
+    pub return_annotation: Option<Token>,
     pub body: Vec<Stmt>,
+    pub meta: Meta,
+}
+
+/// A parameter of a function or lambda.
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: Token,
+    /// The type after `:`, if any.
+    pub annotation: Option<Token>,
 }
 
 #[derive(Debug, Clone)]
@@ -57,21 +109,38 @@ pub struct If {
     pub condition: Expr,
     pub then_branch: Box<Stmt>,
     pub else_branch: Option<Box<Stmt>>,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
 pub struct Print {
     pub expression: Expr,
+    pub meta: Meta,
+}
+
+#[derive(Debug, Clone)]
+pub struct Return {
+    pub keyword: Token,
+    pub value: Option<Expr>,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
 pub struct Var {
     pub name: Token,
+    /// The type after `:`, if any.
+    pub annotation: Option<Token>,
     pub initializer: Option<Expr>,
+    pub meta: Meta,
 }
 
 #[derive(Debug, Clone)]
 pub struct While {
+    pub keyword: Token,
     pub condition: Expr,
     pub body: Box<Stmt>,
+    /// The increment of a `for` loop, run after the body even if it
+    /// `continue`s.
+    pub increment: Option<Expr>,
+    pub meta: Meta,
 }
@@ -0,0 +1,268 @@
+use std::collections::HashSet;
+
+use crate::{
+    expr,
+    stmt::{self, Param, Stmt},
+    token::Token,
+};
+
+/// Reports every use of a variable which can't have been declared, before the
+/// script runs. At the top level a global must be declared before the line
+/// that uses it; inside a function it may be declared anywhere at the top
+/// level, since the function can only run once the script has reached it.
+///
+/// Functions also see the variables declared before them in the blocks and
+/// functions around them, which they capture.
+pub struct GlobalChecker {
+    /// Globals declared so far, starting with those already defined.
+    globals: HashSet<String>,
+    /// Every global declared anywhere at the top level of the script.
+    all_globals: HashSet<String>,
+    scopes: Vec<HashSet<String>>,
+    in_function: bool,
+    errors: Vec<(Token, String)>,
+}
+
+impl GlobalChecker {
+    /// Checks a script, reporting errors as syntax errors. `globals` are the
+    /// names already defined, such as natives and earlier REPL lines.
+    pub fn check(statements: &[Stmt], globals: HashSet<String>) {
+        for (name, message) in GlobalChecker::errors(statements, globals) {
+            crate::error_at_token(&name, &message);
+        }
+    }
+
+    /// Checks a script, returning each error with the name at fault.
+    pub fn errors(statements: &[Stmt], globals: HashSet<String>) -> Vec<(Token, String)> {
+        let mut all_globals = globals.clone();
+        for statement in statements {
+            match statement {
+                Stmt::Var(v) => all_globals.insert(v.name.lexeme.to_string()),
+                Stmt::Function(f) => all_globals.insert(f.name.lexeme.to_string()),
+                Stmt::Class(c) => all_globals.insert(c.name.lexeme.to_string()),
+                _ => false,
+            };
+        }
+
+        let mut checker = GlobalChecker {
+            globals,
+            all_globals,
+            scopes: vec![],
+            in_function: false,
+            errors: vec![],
+        };
+        for statement in statements {
+            statement.accept(&mut checker);
+        }
+        checker.errors
+    }
+
+    fn declare(&mut self, name: &Token) {
+        match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.lexeme.to_string()),
+            None => self.globals.insert(name.lexeme.to_string()),
+        };
+    }
+
+    fn check_name(&mut self, name: &Token) {
+        let declared = self.scopes.iter().any(|s| s.contains(&*name.lexeme))
+            || self.globals.contains(&*name.lexeme)
+            || (self.in_function && self.all_globals.contains(&*name.lexeme));
+        if !declared {
+            self.errors.push((
+                name.clone(),
+                format!("Undefined variable '{}'.", name.lexeme),
+            ));
+        }
+    }
+
+    fn check_function(&mut self, params: &[Param], body: &[Stmt]) {
+        let in_function = std::mem::replace(&mut self.in_function, true);
+        self.scopes
+            .push(params.iter().map(|p| p.name.lexeme.to_string()).collect());
+        self.check_block(body);
+        self.scopes.pop();
+        self.in_function = in_function;
+    }
+
+    fn check_block(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashSet::new());
+        statements.iter().for_each(|s| s.accept(self));
+        self.scopes.pop();
+    }
+}
+
+impl expr::Visitor<()> for GlobalChecker {
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) {
+        expr.value.accept(self);
+        self.check_name(&expr.name);
+    }
+
+    fn visit_await_expr(&mut self, expr: &expr::Await) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &expr::Binary) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_call_expr(&mut self, expr: &expr::Call) {
+        expr.callee.accept(self);
+        expr.arguments.iter().for_each(|a| a.accept(self));
+    }
+
+    fn visit_get_expr(&mut self, expr: &expr::Get) {
+        expr.object.accept(self);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_index_expr(&mut self, expr: &expr::Index) {
+        expr.object.accept(self);
+        expr.index.accept(self);
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) {
+        expr.value.accept(self);
+        expr.object.accept(self);
+        expr.index.accept(self);
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) {
+        self.check_function(&expr.params, &expr.body);
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) {
+        expr.elements.iter().for_each(|e| e.accept(self));
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &expr::Literal) {}
+
+    fn visit_logical_expr(&mut self, expr: &expr::Logical) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_map_expr(&mut self, expr: &expr::Map) {
+        for (key, value) in &expr.entries {
+            key.accept(self);
+            value.accept(self);
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) {
+        expr.object.accept(self);
+        expr.value.accept(self);
+    }
+
+    fn visit_super_expr(&mut self, _expr: &expr::Super) {}
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) {
+        expr.condition.accept(self);
+        expr.then_branch.accept(self);
+        expr.else_branch.accept(self);
+    }
+
+    fn visit_this_expr(&mut self, _expr: &expr::This) {}
+
+    fn visit_unary_expr(&mut self, expr: &expr::Unary) {
+        expr.right.accept(self);
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) {
+        self.check_name(&expr.name);
+    }
+}
+
+impl stmt::Visitor<()> for GlobalChecker {
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) {
+        self.check_block(&stmt.statements);
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &stmt::Break) {}
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        if let Some(superclass) = &stmt.superclass {
+            self.check_name(&superclass.name);
+        }
+        self.declare(&stmt.name);
+        for method in stmt.methods.iter().chain(&stmt.class_methods) {
+            self.check_function(&method.params, &method.body);
+        }
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &stmt::Continue) {}
+
+    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        self.declare(&stmt.name);
+        self.check_function(&stmt.params, &stmt.body);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &stmt::If) {
+        stmt.condition.accept(self);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &stmt::Print) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &stmt::Return) {
+        if let Some(value) = &stmt.value {
+            value.accept(self);
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) {
+        if let Some(initializer) = &stmt.initializer {
+            initializer.accept(self);
+        }
+        self.declare(&stmt.name);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &stmt::While) {
+        stmt.condition.accept(self);
+        stmt.body.accept(self);
+        if let Some(increment) = &stmt.increment {
+            increment.accept(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn errors(source: &str) -> Vec<String> {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap();
+        GlobalChecker::errors(&statements, HashSet::new())
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    #[test]
+    fn reports_globals_used_before_their_declaration() {
+        assert_eq!(errors("print a; var a = 1;"), ["Undefined variable 'a'."]);
+        assert!(errors("var a = 1; print a;").is_empty());
+    }
+
+    #[test]
+    fn functions_see_globals_declared_after_them() {
+        assert!(errors("fun f() { return g(); } fun g() {}").is_empty());
+        assert!(errors("class A { m() { return B(); } } class B {}").is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+//! Runs Lox code in a fresh interpreter with everything it could observe from
+//! outside pinned down, so tests of Lox code, the crate's own or an
+//! embedder's, see the same results on every run.
+//!
+//! ```text
+//! let result = TestLox::new().run("print 1 + 2;");
+//! assert_eq!(result.stdout, "3\n");
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    interpreter::Interpreter,
+    object::LoxObject,
+    parser::Parser,
+    runtime_error::RuntimeError,
+    scanner::Scanner,
+    stdlib::{random::Rng, Clock},
+    token::{Token, TokenKind},
+};
+
+/// How a run of `TestLox::run` ended.
+#[derive(Debug)]
+pub enum Outcome {
+    /// The code ran to the end. Holds the value of its last statement if
+    /// that was an expression statement, as the REPL would show it.
+    Finished(Option<LoxObject>),
+    /// The code didn't run. Each error is worded as the command line reports
+    /// it, like `[line 1] Error at ';': Expect expression.`
+    SyntaxErrors(Vec<String>),
+    RuntimeError(RuntimeError),
+}
+
+/// What a run of `TestLox::run` printed, and how it ended.
+#[derive(Debug)]
+pub struct TestResult {
+    pub stdout: String,
+    pub stderr: String,
+    pub outcome: Outcome,
+}
+
+/// A fresh interpreter whose output is captured rather than printed, whose
+/// clock stands still at 0 until it's set, and whose random numbers come from
+/// a fixed seed. Globals persist from one `run` to the next, as in the REPL.
+pub struct TestLox {
+    interpreter: Interpreter,
+    stdout: Arc<Mutex<Vec<u8>>>,
+    stderr: Arc<Mutex<Vec<u8>>>,
+}
+
+impl TestLox {
+    pub fn new() -> Self {
+        let stdout = Arc::new(Mutex::new(vec![]));
+        let stderr = Arc::new(Mutex::new(vec![]));
+        let mut interpreter = Interpreter::new();
+        interpreter.stdout = stdout.clone();
+        interpreter.stderr = stderr.clone();
+        interpreter.clock = Clock::fake(0.0);
+        interpreter.rng = Rng::seeded(0);
+        Self {
+            interpreter,
+            stdout,
+            stderr,
+        }
+    }
+
+    /// Seeds `random` with `seed` instead of 0.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.interpreter.rng = Rng::seeded(seed);
+        self
+    }
+
+    /// Moves the clock `clock` reads to `now`, in seconds.
+    pub fn set_clock(&self, now: f64) {
+        self.interpreter.clock.set(now);
+    }
+
+    /// The interpreter, to define natives or inspect globals with.
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    /// Scans, parses and runs `source`, returning everything it printed since
+    /// the last run along with how it ended.
+    pub fn run(&mut self, source: &str) -> TestResult {
+        let outcome = self.outcome(source);
+        TestResult {
+            stdout: take_output(&self.stdout),
+            stderr: take_output(&self.stderr),
+            outcome,
+        }
+    }
+
+    fn outcome(&mut self, source: &str) -> Outcome {
+        let (tokens, lex_errors) = Scanner::new(source).scan_all();
+        let (statements, parse_errors) = Parser::new(tokens).parse_all();
+
+        let errors = lex_errors
+            .iter()
+            .map(|e| report(e.line, &format!(" at '{}'", e.lexeme), &e.message))
+            .chain(
+                parse_errors
+                    .iter()
+                    .map(|(token, message)| report(token.line, &whence(token), message)),
+            )
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Outcome::SyntaxErrors(errors);
+        }
+
+        match self.interpreter.run(&statements) {
+            Ok(value) => Outcome::Finished(value),
+            Err(error) => Outcome::RuntimeError(error),
+        }
+    }
+}
+
+impl Default for TestLox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn take_output(sink: &Mutex<Vec<u8>>) -> String {
+    let bytes = std::mem::take(&mut *sink.lock().unwrap());
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn whence(token: &Token) -> String {
+    if token.kind == TokenKind::Eof {
+        String::from(" at end")
+    } else {
+        format!(" at '{}'", token.lexeme)
+    }
+}
+
+fn report(line: usize, whence: &str, message: &str) -> String {
+    format!("[line {}] Error{}: {}", line, whence, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_output_and_the_last_value() {
+        let result = TestLox::new().run("print 1 + 2; 4 * 5;");
+        assert_eq!(result.stdout, "3\n");
+        assert_eq!(result.stderr, "");
+        match result.outcome {
+            Outcome::Finished(Some(value)) => assert_eq!(value.read().unwrap().to_string(), "20"),
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn reports_syntax_errors_without_running() {
+        let result = TestLox::new().run("print 1;\nprint ;");
+        assert_eq!(result.stdout, "");
+        match result.outcome {
+            Outcome::SyntaxErrors(errors) => {
+                assert_eq!(errors, ["[line 2] Error at ';': Expect expression."])
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn returns_runtime_errors() {
+        let result = TestLox::new().run("print \"a\"; -\"b\";");
+        assert_eq!(result.stdout, "a\n");
+        match result.outcome {
+            Outcome::RuntimeError(error) => assert!(error.to_string().contains("Operand must be")),
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn keeps_globals_between_runs() {
+        let mut lox = TestLox::new();
+        lox.run("var a = 1;");
+        assert_eq!(lox.run("print a + 1;").stdout, "2\n");
+    }
+
+    #[test]
+    fn pins_the_clock_and_random_numbers() {
+        let mut lox = TestLox::new();
+        assert_eq!(lox.run("print clock();").stdout, "0\n");
+        lox.set_clock(2.5);
+        assert_eq!(lox.run("print clock();").stdout, "2.5\n");
+
+        let first = TestLox::new().with_seed(7).run("print random();").stdout;
+        let second = TestLox::new().with_seed(7).run("print random();").stdout;
+        assert_eq!(first, second);
+    }
+}
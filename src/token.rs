@@ -1,20 +1,36 @@
-use std::fmt::Display;
+use std::{
+    fmt::{Debug, Display},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+};
+
+use enum_map::{enum_map, Enum, EnumMap};
+use lazy_static::lazy_static;
 
 use crate::object::LoxObject;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Enum)]
 pub enum TokenKind {
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
     Comma,
     Dot,
     Minus,
+    MinusMinus,
     Plus,
+    PlusPlus,
     Semicolon,
     Slash,
     Star,
+    StarStar,
+    Question,
+    Colon,
+    Arrow,
 
     Bang,
     BangEqual,
@@ -27,10 +43,15 @@ pub enum TokenKind {
 
     Identifier,
     String,
+    Bytes,
     Number,
 
     And,
+    Async,
+    Await,
+    Break,
     Class,
+    Continue,
     Else,
     False,
     For,
@@ -49,25 +70,138 @@ pub enum TokenKind {
     Eof,
 }
 
+/// Where a token's text is in the source, as byte offsets from its start.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// The span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+/// A token's text, as a view into the source it was scanned from. Tokens from
+/// one script all share its text, so scanning doesn't allocate a string per
+/// token.
+#[derive(Clone)]
+pub struct Lexeme {
+    source: Arc<str>,
+    span: Span,
+}
+
+impl Lexeme {
+    pub fn new(source: Arc<str>, span: Span) -> Self {
+        Self { source, span }
+    }
+}
+
+impl From<&str> for Lexeme {
+    fn from(text: &str) -> Self {
+        let span = Span {
+            start: 0,
+            end: text.len(),
+        };
+        Self::new(Arc::from(text), span)
+    }
+}
+
+impl Deref for Lexeme {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.source[self.span.start..self.span.end]
+    }
+}
+
+impl PartialEq for Lexeme {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Lexeme {}
+
+impl PartialEq<str> for Lexeme {
+    fn eq(&self, other: &str) -> bool {
+        &**self == other
+    }
+}
+
+impl PartialEq<&str> for Lexeme {
+    fn eq(&self, other: &&str) -> bool {
+        &**self == *other
+    }
+}
+
+impl PartialEq<String> for Lexeme {
+    fn eq(&self, other: &String) -> bool {
+        **self == **other
+    }
+}
+
+impl Hash for Lexeme {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl Debug for Lexeme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", &**self)
+    }
+}
+
+impl Display for Lexeme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", &**self)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub kind: TokenKind,
-    pub lexeme: String,
+    pub lexeme: Lexeme,
     pub literal: LoxObject,
+    /// The line the token ends on.
     pub line: usize,
+    /// The column the token starts at, counting characters from 1.
+    pub column: usize,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(kind: TokenKind, lexeme: String, literal: LoxObject, line: usize) -> Self {
+    pub fn new(
+        kind: TokenKind,
+        lexeme: Lexeme,
+        literal: LoxObject,
+        line: usize,
+        column: usize,
+        span: Span,
+    ) -> Self {
         Self {
             kind,
             lexeme,
             literal,
             line,
+            column,
+            span,
         }
     }
 }
 
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", TOKEN_INFO[*self].name)
+    }
+}
+
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -79,3 +213,108 @@ impl Display for Token {
         )
     }
 }
+
+/// What's known about a kind of token before any source is scanned.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenInfo {
+    /// How the kind is described in messages, like `'('` or `identifier`.
+    pub name: &'static str,
+    /// The source text of every token of this kind, if it's always the same.
+    pub lexeme: Option<&'static str>,
+    /// Whether the kind is a reserved word, which can't be an identifier.
+    pub is_keyword: bool,
+    /// How tightly the kind binds as an infix operator, from 0 for `,` up to
+    /// 10 for `**`, with 1 left for assignment, 2 for the `?` of a conditional
+    /// and 9 for prefix operators. `None` if it isn't one.
+    pub precedence: Option<u8>,
+}
+
+fn named(name: &'static str) -> TokenInfo {
+    TokenInfo {
+        name,
+        lexeme: None,
+        is_keyword: false,
+        precedence: None,
+    }
+}
+
+fn fixed(name: &'static str, lexeme: &'static str) -> TokenInfo {
+    TokenInfo {
+        lexeme: Some(lexeme),
+        ..named(name)
+    }
+}
+
+fn operator(name: &'static str, lexeme: &'static str, precedence: u8) -> TokenInfo {
+    TokenInfo {
+        precedence: Some(precedence),
+        ..fixed(name, lexeme)
+    }
+}
+
+fn keyword(name: &'static str, lexeme: &'static str, precedence: Option<u8>) -> TokenInfo {
+    TokenInfo {
+        is_keyword: true,
+        precedence,
+        ..fixed(name, lexeme)
+    }
+}
+
+lazy_static! {
+    /// Properties of every kind of token, so tools like highlighters and
+    /// formatters don't need their own copies.
+    pub static ref TOKEN_INFO: EnumMap<TokenKind, TokenInfo> = enum_map! {
+        TokenKind::LParen => fixed("'('", "("),
+        TokenKind::RParen => fixed("')'", ")"),
+        TokenKind::LBrace => fixed("'{'", "{"),
+        TokenKind::RBrace => fixed("'}'", "}"),
+        TokenKind::LBracket => fixed("'['", "["),
+        TokenKind::RBracket => fixed("']'", "]"),
+        TokenKind::Comma => operator("','", ",", 0),
+        TokenKind::Dot => fixed("'.'", "."),
+        TokenKind::Minus => operator("'-'", "-", 7),
+        TokenKind::MinusMinus => fixed("'--'", "--"),
+        TokenKind::Plus => operator("'+'", "+", 7),
+        TokenKind::PlusPlus => fixed("'++'", "++"),
+        TokenKind::Semicolon => fixed("';'", ";"),
+        TokenKind::Slash => operator("'/'", "/", 8),
+        TokenKind::Star => operator("'*'", "*", 8),
+        TokenKind::StarStar => operator("'**'", "**", 10),
+        TokenKind::Question => operator("'?'", "?", 2),
+        TokenKind::Colon => fixed("':'", ":"),
+        TokenKind::Arrow => fixed("'->'", "->"),
+        TokenKind::Bang => fixed("'!'", "!"),
+        TokenKind::BangEqual => operator("'!='", "!=", 5),
+        TokenKind::Equal => fixed("'='", "="),
+        TokenKind::EqualEqual => operator("'=='", "==", 5),
+        TokenKind::Greater => operator("'>'", ">", 6),
+        TokenKind::GreaterEqual => operator("'>='", ">=", 6),
+        TokenKind::Less => operator("'<'", "<", 6),
+        TokenKind::LessEqual => operator("'<='", "<=", 6),
+        TokenKind::Identifier => named("identifier"),
+        TokenKind::String => named("string literal"),
+        TokenKind::Bytes => named("bytes literal"),
+        TokenKind::Number => named("number literal"),
+        TokenKind::And => keyword("'and'", "and", Some(4)),
+        TokenKind::Async => keyword("'async'", "async", None),
+        TokenKind::Await => keyword("'await'", "await", None),
+        TokenKind::Break => keyword("'break'", "break", None),
+        TokenKind::Class => keyword("'class'", "class", None),
+        TokenKind::Continue => keyword("'continue'", "continue", None),
+        TokenKind::Else => keyword("'else'", "else", None),
+        TokenKind::False => keyword("'false'", "false", None),
+        TokenKind::For => keyword("'for'", "for", None),
+        TokenKind::Fun => keyword("'fun'", "fun", None),
+        TokenKind::If => keyword("'if'", "if", None),
+        TokenKind::Nil => keyword("'nil'", "nil", None),
+        TokenKind::Or => keyword("'or'", "or", Some(3)),
+        TokenKind::Print => keyword("'print'", "print", None),
+        TokenKind::Return => keyword("'return'", "return", None),
+        TokenKind::Super => keyword("'super'", "super", None),
+        TokenKind::This => keyword("'this'", "this", None),
+        TokenKind::True => keyword("'true'", "true", None),
+        TokenKind::Var => keyword("'var'", "var", None),
+        TokenKind::While => keyword("'while'", "while", None),
+        TokenKind::Eof => named("end of file"),
+    };
+}
@@ -0,0 +1,580 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    expr::{self, Expr},
+    object::Object,
+    stmt::{self, Param, Stmt},
+    token::{Span, Token, TokenKind},
+};
+
+/// A type an annotation can name, like the `number` in `var x: number = 1;`.
+/// Values of other kinds, like threads, are only ever `any`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Any,
+    Nil,
+    Boolean,
+    Number,
+    BigInt,
+    Decimal,
+    Complex,
+    String,
+    Bytes,
+    Function,
+    List,
+    Map,
+}
+
+impl Type {
+    /// The type an annotation names, which is the name `Object::type_name`
+    /// gives its values.
+    pub fn from_name(name: &str) -> Option<Type> {
+        Some(match name {
+            "any" => Type::Any,
+            "nil" => Type::Nil,
+            "boolean" => Type::Boolean,
+            "number" => Type::Number,
+            "bigint" => Type::BigInt,
+            "decimal" => Type::Decimal,
+            "complex" => Type::Complex,
+            "string" => Type::String,
+            "bytes" => Type::Bytes,
+            "function" => Type::Function,
+            "list" => Type::List,
+            "map" => Type::Map,
+            _ => return None,
+        })
+    }
+
+    /// The type of a literal.
+    fn of(value: &Object) -> Type {
+        match value {
+            Object::Nil => Type::Nil,
+            Object::Bool(_) => Type::Boolean,
+            Object::Number(_) => Type::Number,
+            Object::BigInt(_) => Type::BigInt,
+            Object::Decimal(_) => Type::Decimal,
+            Object::Complex(_) => Type::Complex,
+            Object::String(_) => Type::String,
+            Object::Bytes(_) => Type::Bytes,
+            _ => Type::Any,
+        }
+    }
+
+    /// Whether `value` is of this type, for checking annotations at runtime.
+    pub fn admits(self, value: &Object) -> bool {
+        match self {
+            Type::Any => true,
+            Type::Function => matches!(value, Object::Function(_) | Object::BuiltinFunction(..)),
+            Type::List => matches!(value, Object::List(_)),
+            Type::Map => matches!(value, Object::Map(_)),
+            _ => Type::of(value) == self,
+        }
+    }
+
+    /// Whether a value of this type may be used where `expected` is. Either
+    /// being `any` means the checker doesn't know, so it lets it pass.
+    fn fits(self, expected: Type) -> bool {
+        self == Type::Any || expected == Type::Any || self == expected
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            Type::Number | Type::BigInt | Type::Decimal | Type::Complex
+        )
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Type::Any => "any",
+            Type::Nil => "nil",
+            Type::Boolean => "boolean",
+            Type::Number => "number",
+            Type::BigInt => "bigint",
+            Type::Decimal => "decimal",
+            Type::Complex => "complex",
+            Type::String => "string",
+            Type::Bytes => "bytes",
+            Type::Function => "function",
+            Type::List => "list",
+            Type::Map => "map",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The type of an annotation, or `any` if there isn't one or it names no
+/// type. Unknown names are reported where they're declared.
+fn annotation_type(annotation: &Option<Token>) -> Type {
+    annotation
+        .as_ref()
+        .and_then(|a| Type::from_name(&a.lexeme))
+        .unwrap_or(Type::Any)
+}
+
+/// The annotated types of a function's parameters, by name, and of what it
+/// returns.
+#[derive(Debug, Clone)]
+struct Signature {
+    params: Vec<(String, Type)>,
+    returns: Type,
+}
+
+impl Signature {
+    fn new(params: &[Param], return_annotation: &Option<Token>) -> Self {
+        Signature {
+            params: params
+                .iter()
+                .map(|p| (p.name.lexeme.to_string(), annotation_type(&p.annotation)))
+                .collect(),
+            returns: annotation_type(return_annotation),
+        }
+    }
+}
+
+/// What the checker knows about a value or a variable.
+#[derive(Debug, Clone)]
+struct Typed {
+    ty: Type,
+    /// The function's signature, if it's a function the checker has seen
+    /// declared.
+    signature: Option<Signature>,
+}
+
+impl Typed {
+    fn new(ty: Type) -> Self {
+        Typed {
+            ty,
+            signature: None,
+        }
+    }
+}
+
+/// Checks type annotations before a script runs, for `rustlox typecheck`.
+/// The interpreter ignores them.
+///
+/// Checking is gradual: anything without an annotation, and any expression
+/// whose type the checker can't work out, is `any`, which fits everywhere. So
+/// an unannotated script always passes, and annotations only catch values
+/// which are certainly of the wrong type, such as a string passed for a
+/// `number` parameter.
+pub struct TypeChecker {
+    /// The globals, then one scope per enclosing block or function.
+    scopes: Vec<HashMap<String, Typed>>,
+    /// The return type of each enclosing function.
+    returns: Vec<Type>,
+    errors: Vec<(Span, String)>,
+}
+
+impl TypeChecker {
+    /// Checks a script, reporting errors as syntax errors.
+    pub fn check(statements: &[Stmt]) {
+        for (span, message) in TypeChecker::errors(statements) {
+            crate::error_at_span(span, &message);
+        }
+    }
+
+    /// Checks a script, returning each error with the source at fault.
+    pub fn errors(statements: &[Stmt]) -> Vec<(Span, String)> {
+        let mut checker = TypeChecker {
+            scopes: vec![HashMap::new()],
+            returns: vec![],
+            errors: vec![],
+        };
+        // Functions may call global functions declared after them.
+        for statement in statements {
+            if let Stmt::Function(f) = statement {
+                checker.declare(&f.name, function(&f.params, &f.return_annotation));
+            }
+        }
+        for statement in statements {
+            statement.accept(&mut checker);
+        }
+        checker.errors
+    }
+
+    fn declare(&mut self, name: &Token, typed: Typed) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.lexeme.to_string(), typed);
+    }
+
+    fn lookup(&mut self, name: &Token) -> Option<&mut Typed> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|s| s.get_mut(&*name.lexeme))
+    }
+
+    /// Reports an annotation which names no type.
+    fn check_annotation(&mut self, annotation: &Option<Token>) {
+        if let Some(annotation) = annotation {
+            if Type::from_name(&annotation.lexeme).is_none() {
+                self.errors.push((
+                    annotation.span,
+                    format!("Unknown type '{}'.", annotation.lexeme),
+                ));
+            }
+        }
+    }
+
+    /// Reports `found` if it doesn't fit `expected`. `what` names what it was
+    /// given for, like "'x'".
+    fn expect(&mut self, expr: &Expr, found: Type, expected: Type, what: &str) {
+        if !found.fits(expected) {
+            self.errors.push((
+                expr.span(),
+                format!("Expected {} for {}, found {}.", expected, what, found),
+            ));
+        }
+    }
+
+    fn check_function(
+        &mut self,
+        params: &[Param],
+        return_annotation: &Option<Token>,
+        body: &[Stmt],
+    ) {
+        params
+            .iter()
+            .for_each(|p| self.check_annotation(&p.annotation));
+        self.check_annotation(return_annotation);
+
+        self.scopes.push(HashMap::new());
+        for param in params {
+            self.declare(&param.name, Typed::new(annotation_type(&param.annotation)));
+        }
+        self.returns.push(annotation_type(return_annotation));
+        self.check_block(body);
+        self.returns.pop();
+        self.scopes.pop();
+    }
+
+    fn check_block(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        statements.iter().for_each(|s| s.accept(self));
+        self.scopes.pop();
+    }
+}
+
+/// What the checker knows about a function with these annotations.
+fn function(params: &[Param], return_annotation: &Option<Token>) -> Typed {
+    Typed {
+        ty: Type::Function,
+        signature: Some(Signature::new(params, return_annotation)),
+    }
+}
+
+/// The type both sides have, if they have the same one.
+fn common(left: Type, right: Type) -> Type {
+    if left == right {
+        left
+    } else {
+        Type::Any
+    }
+}
+
+impl expr::Visitor<Typed> for TypeChecker {
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) -> Typed {
+        let value = expr.value.accept(self);
+        let declared = match self.lookup(&expr.name) {
+            Some(variable) => {
+                // Which function it holds now is only known until the next
+                // assignment.
+                variable.signature = None;
+                variable.ty
+            }
+            None => Type::Any,
+        };
+        let what = format!("'{}'", expr.name.lexeme);
+        self.expect(&expr.value, value.ty, declared, &what);
+        value
+    }
+
+    fn visit_await_expr(&mut self, expr: &expr::Await) -> Typed {
+        expr.expression.accept(self);
+        Typed::new(Type::Any)
+    }
+
+    fn visit_binary_expr(&mut self, expr: &expr::Binary) -> Typed {
+        let left = expr.left.accept(self).ty;
+        let right = expr.right.accept(self);
+        let ty = match expr.operator.kind {
+            TokenKind::Comma => return right,
+            TokenKind::EqualEqual
+            | TokenKind::BangEqual
+            | TokenKind::Greater
+            | TokenKind::GreaterEqual
+            | TokenKind::Less
+            | TokenKind::LessEqual => Type::Boolean,
+            TokenKind::Plus if matches!(left, Type::String | Type::Bytes) => common(left, right.ty),
+            _ if left.is_numeric() => common(left, right.ty),
+            _ => Type::Any,
+        };
+        Typed::new(ty)
+    }
+
+    fn visit_call_expr(&mut self, expr: &expr::Call) -> Typed {
+        let callee = expr.callee.accept(self);
+        let arguments = expr
+            .arguments
+            .iter()
+            .map(|a| a.accept(self).ty)
+            .collect::<Vec<_>>();
+
+        if !callee.ty.fits(Type::Function) {
+            self.errors.push((
+                expr.paren.span,
+                String::from("Can only call functions and classes."),
+            ));
+            return Typed::new(Type::Any);
+        }
+        let signature = match callee.signature {
+            Some(signature) => signature,
+            None => return Typed::new(Type::Any),
+        };
+        if arguments.len() != signature.params.len() {
+            self.errors.push((
+                expr.paren.span,
+                format!(
+                    "Expected {} arguments but got {}.",
+                    signature.params.len(),
+                    arguments.len()
+                ),
+            ));
+        } else {
+            for ((argument, found), (name, expected)) in
+                expr.arguments.iter().zip(arguments).zip(&signature.params)
+            {
+                let what = format!("parameter '{}'", name);
+                self.expect(argument, found, *expected, &what);
+            }
+        }
+        Typed::new(signature.returns)
+    }
+
+    fn visit_get_expr(&mut self, expr: &expr::Get) -> Typed {
+        expr.object.accept(self);
+        Typed::new(Type::Any)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) -> Typed {
+        expr.expression.accept(self)
+    }
+
+    fn visit_index_expr(&mut self, expr: &expr::Index) -> Typed {
+        expr.object.accept(self);
+        expr.index.accept(self);
+        Typed::new(Type::Any)
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) -> Typed {
+        expr.object.accept(self);
+        expr.index.accept(self);
+        expr.value.accept(self)
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) -> Typed {
+        self.check_function(&expr.params, &expr.return_annotation, &expr.body);
+        function(&expr.params, &expr.return_annotation)
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) -> Typed {
+        expr.elements.iter().for_each(|e| {
+            e.accept(self);
+        });
+        Typed::new(Type::List)
+    }
+
+    fn visit_literal_expr(&mut self, expr: &expr::Literal) -> Typed {
+        Typed::new(Type::of(&expr.value.read().unwrap()))
+    }
+
+    fn visit_logical_expr(&mut self, expr: &expr::Logical) -> Typed {
+        let left = expr.left.accept(self).ty;
+        let right = expr.right.accept(self).ty;
+        Typed::new(common(left, right))
+    }
+
+    fn visit_map_expr(&mut self, expr: &expr::Map) -> Typed {
+        for (key, value) in &expr.entries {
+            key.accept(self);
+            value.accept(self);
+        }
+        Typed::new(Type::Map)
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) -> Typed {
+        expr.object.accept(self);
+        expr.value.accept(self)
+    }
+
+    fn visit_super_expr(&mut self, _expr: &expr::Super) -> Typed {
+        Typed::new(Type::Any)
+    }
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) -> Typed {
+        expr.condition.accept(self);
+        let then_branch = expr.then_branch.accept(self).ty;
+        let else_branch = expr.else_branch.accept(self).ty;
+        Typed::new(common(then_branch, else_branch))
+    }
+
+    fn visit_this_expr(&mut self, _expr: &expr::This) -> Typed {
+        Typed::new(Type::Any)
+    }
+
+    fn visit_unary_expr(&mut self, expr: &expr::Unary) -> Typed {
+        let right = expr.right.accept(self).ty;
+        match expr.operator.kind {
+            TokenKind::Bang => Typed::new(Type::Boolean),
+            _ if right.is_numeric() => Typed::new(right),
+            _ => Typed::new(Type::Any),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &expr::Variable) -> Typed {
+        self.lookup(&expr.name)
+            .cloned()
+            .unwrap_or_else(|| Typed::new(Type::Any))
+    }
+}
+
+impl stmt::Visitor<()> for TypeChecker {
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) {
+        self.check_block(&stmt.statements);
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &stmt::Break) {}
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        self.declare(&stmt.name, Typed::new(Type::Any));
+        for method in stmt.methods.iter().chain(&stmt.class_methods) {
+            self.check_function(&method.params, &method.return_annotation, &method.body);
+        }
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &stmt::Continue) {}
+
+    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        // Declared first, so the function can call itself.
+        self.declare(&stmt.name, function(&stmt.params, &stmt.return_annotation));
+        self.check_function(&stmt.params, &stmt.return_annotation, &stmt.body);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &stmt::If) {
+        stmt.condition.accept(self);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &stmt::Print) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &stmt::Return) {
+        let expected = *self.returns.last().unwrap();
+        match &stmt.value {
+            Some(value) => {
+                let found = value.accept(self).ty;
+                self.expect(value, found, expected, "the return value");
+            }
+            None if !Type::Nil.fits(expected) => self.errors.push((
+                stmt.keyword.span,
+                format!("Expected {} for the return value, found nil.", expected),
+            )),
+            None => {}
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) {
+        self.check_annotation(&stmt.annotation);
+        let declared = annotation_type(&stmt.annotation);
+        let mut typed = match &stmt.initializer {
+            Some(initializer) => {
+                let value = initializer.accept(self);
+                let what = format!("'{}'", stmt.name.lexeme);
+                self.expect(initializer, value.ty, declared, &what);
+                value
+            }
+            None => Typed::new(Type::Nil),
+        };
+        // An unannotated variable may be assigned anything later.
+        typed.ty = declared;
+        self.declare(&stmt.name, typed);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &stmt::While) {
+        stmt.condition.accept(self);
+        stmt.body.accept(self);
+        if let Some(increment) = &stmt.increment {
+            increment.accept(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn errors(source: &str) -> Vec<String> {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap();
+        TypeChecker::errors(&statements)
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    #[test]
+    fn reports_arguments_of_the_wrong_type() {
+        assert_eq!(
+            errors("fun double(x: number) { return x * 2; } double(\"two\");"),
+            ["Expected number for parameter 'x', found string."]
+        );
+        assert!(errors("fun double(x: number) { return x * 2; } double(2);").is_empty());
+    }
+
+    #[test]
+    fn reports_return_values_of_the_wrong_type() {
+        assert_eq!(
+            errors("fun name() -> string { return 1; }"),
+            ["Expected string for the return value, found number."]
+        );
+        assert_eq!(
+            errors("fun name() -> string { return; }"),
+            ["Expected string for the return value, found nil."]
+        );
+    }
+
+    #[test]
+    fn reports_variables_given_the_wrong_type() {
+        assert_eq!(
+            errors("var x: number = \"one\";"),
+            ["Expected number for 'x', found string."]
+        );
+        assert_eq!(
+            errors("var x: number = 1; x = true;"),
+            ["Expected number for 'x', found boolean."]
+        );
+    }
+
+    #[test]
+    fn any_fits_everywhere() {
+        assert!(errors("var x: any = 1; x = \"one\"; x = nil;").is_empty());
+        assert!(errors("fun f(x: number) {} var y: any = \"one\"; f(y);").is_empty());
+        assert!(errors("fun f() -> any { return 1; } var s: string = f();").is_empty());
+    }
+}
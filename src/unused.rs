@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use crate::{
+    call_graph::{declarations, CallGraph, Declaration},
+    stmt::Stmt,
+};
+
+/// Finds functions and classes which can never be used: those not mentioned
+/// by top-level code, or by any function or class which can itself be used.
+/// Declarations which only refer to each other are unused together. They're
+/// matched by name, so one is counted as used if anything by the same name
+/// is.
+pub fn unused_declarations(statements: &[Stmt], graph: &CallGraph) -> Vec<Declaration> {
+    let mut used = HashSet::new();
+    let mut pending = vec![String::from("<script>")];
+    while let Some(name) = pending.pop() {
+        for (caller, references) in &graph.references {
+            if *caller == name {
+                for reference in references {
+                    if used.insert(reference.clone()) {
+                        pending.push(reference.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut found = declarations(statements);
+    found.retain(|declaration| !used.contains(&*declaration.name.lexeme));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn unused(source: &str) -> Vec<String> {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap();
+        let graph = CallGraph::build(&statements, &HashSet::new());
+        unused_declarations(&statements, &graph)
+            .into_iter()
+            .map(|d| format!("{} {}", d.kind, d.name.lexeme))
+            .collect()
+    }
+
+    #[test]
+    fn reports_unused_functions_and_classes() {
+        let found = unused(
+            "class Used < Base { init() { helper(); } }
+            class Unused { go() { fun nested() {} } }
+            class Base {}
+            fun helper() {}
+            fun pingA() { pingB(); }
+            fun pingB() { pingA(); }
+            var run = fun () { fun inLambda() {} };
+            Used();",
+        );
+        assert_eq!(
+            found,
+            [
+                "Class Unused",
+                "Function nested",
+                "Function pingA",
+                "Function pingB",
+                "Function inLambda"
+            ]
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! What this build of the interpreter is and can do, for `--version` and
+//! the `version()` native.
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The short hash of the commit built from, or "unknown" outside a git
+/// checkout.
+pub const COMMIT: &str = env!("RUSTLOX_COMMIT");
+/// The UTC date of the build, like 2024-01-31.
+pub const BUILD_DATE: &str = env!("RUSTLOX_BUILD_DATE");
+
+/// Each optional Cargo feature and whether this build has it.
+pub const FEATURES: [(&str, bool); 3] = [
+    ("config", cfg!(feature = "config")),
+    ("locale", cfg!(feature = "locale")),
+    ("net", cfg!(feature = "net")),
+];
+
+/// `rustlox --version`.
+pub fn short() -> String {
+    format!("rustlox {}", VERSION)
+}
+
+/// `rustlox --version --verbose`.
+pub fn verbose() -> String {
+    let features = FEATURES
+        .iter()
+        .map(|&(name, enabled)| format!("{}{}", if enabled { '+' } else { '-' }, name))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{}\ncommit: {}\nbuilt: {}\nfeatures: {}",
+        short(),
+        COMMIT,
+        BUILD_DATE,
+        features
+    )
+}
@@ -0,0 +1,305 @@
+//! A visitor which walks a whole tree by default, for analyses which only
+//! care about a few kinds of node.
+//!
+//! Each `visit_*` method of `Walk` calls the `walk_*` function of the same
+//! name, which visits the node's children. An analysis overrides the methods
+//! for the nodes it cares about, calling the `walk_*` function itself if it
+//! still wants the children visited:
+//!
+//! ```text
+//! impl Walk for CountAssignments {
+//!     fn visit_assign_expr(&mut self, expr: &expr::Assign) {
+//!         self.count += 1;
+//!         walk_assign_expr(self, expr);
+//!     }
+//! }
+//! ```
+
+use crate::{
+    expr::{self, Expr},
+    stmt::{self, Stmt},
+};
+
+pub trait Walk: Sized {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &stmt::Block) {
+        walk_block_stmt(self, stmt);
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &stmt::Break) {}
+
+    fn visit_class_stmt(&mut self, stmt: &stmt::Class) {
+        walk_class_stmt(self, stmt);
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &stmt::Continue) {}
+
+    fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) {
+        walk_expression_stmt(self, stmt);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::Function) {
+        walk_function_stmt(self, stmt);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &stmt::If) {
+        walk_if_stmt(self, stmt);
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &stmt::Print) {
+        walk_print_stmt(self, stmt);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &stmt::Return) {
+        walk_return_stmt(self, stmt);
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &stmt::Var) {
+        walk_var_stmt(self, stmt);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &stmt::While) {
+        walk_while_stmt(self, stmt);
+    }
+
+    fn visit_assign_expr(&mut self, expr: &expr::Assign) {
+        walk_assign_expr(self, expr);
+    }
+
+    fn visit_await_expr(&mut self, expr: &expr::Await) {
+        walk_await_expr(self, expr);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &expr::Binary) {
+        walk_binary_expr(self, expr);
+    }
+
+    fn visit_call_expr(&mut self, expr: &expr::Call) {
+        walk_call_expr(self, expr);
+    }
+
+    fn visit_get_expr(&mut self, expr: &expr::Get) {
+        walk_get_expr(self, expr);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &expr::Grouping) {
+        walk_grouping_expr(self, expr);
+    }
+
+    fn visit_index_expr(&mut self, expr: &expr::Index) {
+        walk_index_expr(self, expr);
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &expr::IndexSet) {
+        walk_index_set_expr(self, expr);
+    }
+
+    fn visit_lambda_expr(&mut self, expr: &expr::Lambda) {
+        walk_lambda_expr(self, expr);
+    }
+
+    fn visit_list_expr(&mut self, expr: &expr::List) {
+        walk_list_expr(self, expr);
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &expr::Literal) {}
+
+    fn visit_logical_expr(&mut self, expr: &expr::Logical) {
+        walk_logical_expr(self, expr);
+    }
+
+    fn visit_map_expr(&mut self, expr: &expr::Map) {
+        walk_map_expr(self, expr);
+    }
+
+    fn visit_set_expr(&mut self, expr: &expr::Set) {
+        walk_set_expr(self, expr);
+    }
+
+    fn visit_super_expr(&mut self, _expr: &expr::Super) {}
+
+    fn visit_ternary_expr(&mut self, expr: &expr::Ternary) {
+        walk_ternary_expr(self, expr);
+    }
+
+    fn visit_this_expr(&mut self, _expr: &expr::This) {}
+
+    fn visit_unary_expr(&mut self, expr: &expr::Unary) {
+        walk_unary_expr(self, expr);
+    }
+
+    fn visit_variable_expr(&mut self, _expr: &expr::Variable) {}
+}
+
+pub fn walk_stmt(walker: &mut impl Walk, stmt: &Stmt) {
+    match stmt {
+        Stmt::Block(b) => walker.visit_block_stmt(b),
+        Stmt::Break(b) => walker.visit_break_stmt(b),
+        Stmt::Class(c) => walker.visit_class_stmt(c),
+        Stmt::Continue(c) => walker.visit_continue_stmt(c),
+        Stmt::Expression(e) => walker.visit_expression_stmt(e),
+        Stmt::Function(f) => walker.visit_function_stmt(f),
+        Stmt::If(i) => walker.visit_if_stmt(i),
+        Stmt::Print(p) => walker.visit_print_stmt(p),
+        Stmt::Return(r) => walker.visit_return_stmt(r),
+        Stmt::Var(v) => walker.visit_var_stmt(v),
+        Stmt::While(w) => walker.visit_while_stmt(w),
+    }
+}
+
+pub fn walk_expr(walker: &mut impl Walk, expr: &Expr) {
+    match expr {
+        Expr::Assign(a) => walker.visit_assign_expr(a),
+        Expr::Await(a) => walker.visit_await_expr(a),
+        Expr::Binary(b) => walker.visit_binary_expr(b),
+        Expr::Call(c) => walker.visit_call_expr(c),
+        Expr::Get(g) => walker.visit_get_expr(g),
+        Expr::Grouping(g) => walker.visit_grouping_expr(g),
+        Expr::Index(i) => walker.visit_index_expr(i),
+        Expr::IndexSet(i) => walker.visit_index_set_expr(i),
+        Expr::Lambda(l) => walker.visit_lambda_expr(l),
+        Expr::List(l) => walker.visit_list_expr(l),
+        Expr::Literal(l) => walker.visit_literal_expr(l),
+        Expr::Logical(l) => walker.visit_logical_expr(l),
+        Expr::Map(m) => walker.visit_map_expr(m),
+        Expr::Set(s) => walker.visit_set_expr(s),
+        Expr::Super(s) => walker.visit_super_expr(s),
+        Expr::Ternary(t) => walker.visit_ternary_expr(t),
+        Expr::This(t) => walker.visit_this_expr(t),
+        Expr::Unary(u) => walker.visit_unary_expr(u),
+        Expr::Variable(v) => walker.visit_variable_expr(v),
+    }
+}
+
+pub fn walk_block_stmt(walker: &mut impl Walk, stmt: &stmt::Block) {
+    stmt.statements.iter().for_each(|s| walker.visit_stmt(s));
+}
+
+pub fn walk_class_stmt(walker: &mut impl Walk, stmt: &stmt::Class) {
+    if let Some(superclass) = &stmt.superclass {
+        walker.visit_variable_expr(superclass);
+    }
+    stmt.methods
+        .iter()
+        .chain(&stmt.class_methods)
+        .for_each(|m| walker.visit_function_stmt(m));
+}
+
+pub fn walk_expression_stmt(walker: &mut impl Walk, stmt: &stmt::Expression) {
+    walker.visit_expr(&stmt.expression);
+}
+
+pub fn walk_function_stmt(walker: &mut impl Walk, stmt: &stmt::Function) {
+    stmt.body.iter().for_each(|s| walker.visit_stmt(s));
+}
+
+pub fn walk_if_stmt(walker: &mut impl Walk, stmt: &stmt::If) {
+    walker.visit_expr(&stmt.condition);
+    walker.visit_stmt(&stmt.then_branch);
+    if let Some(else_branch) = &stmt.else_branch {
+        walker.visit_stmt(else_branch);
+    }
+}
+
+pub fn walk_print_stmt(walker: &mut impl Walk, stmt: &stmt::Print) {
+    walker.visit_expr(&stmt.expression);
+}
+
+pub fn walk_return_stmt(walker: &mut impl Walk, stmt: &stmt::Return) {
+    if let Some(value) = &stmt.value {
+        walker.visit_expr(value);
+    }
+}
+
+pub fn walk_var_stmt(walker: &mut impl Walk, stmt: &stmt::Var) {
+    if let Some(initializer) = &stmt.initializer {
+        walker.visit_expr(initializer);
+    }
+}
+
+pub fn walk_while_stmt(walker: &mut impl Walk, stmt: &stmt::While) {
+    walker.visit_expr(&stmt.condition);
+    walker.visit_stmt(&stmt.body);
+    if let Some(increment) = &stmt.increment {
+        walker.visit_expr(increment);
+    }
+}
+
+pub fn walk_assign_expr(walker: &mut impl Walk, expr: &expr::Assign) {
+    walker.visit_expr(&expr.value);
+}
+
+pub fn walk_await_expr(walker: &mut impl Walk, expr: &expr::Await) {
+    walker.visit_expr(&expr.expression);
+}
+
+pub fn walk_binary_expr(walker: &mut impl Walk, expr: &expr::Binary) {
+    walker.visit_expr(&expr.left);
+    walker.visit_expr(&expr.right);
+}
+
+pub fn walk_call_expr(walker: &mut impl Walk, expr: &expr::Call) {
+    walker.visit_expr(&expr.callee);
+    expr.arguments.iter().for_each(|a| walker.visit_expr(a));
+}
+
+pub fn walk_get_expr(walker: &mut impl Walk, expr: &expr::Get) {
+    walker.visit_expr(&expr.object);
+}
+
+pub fn walk_grouping_expr(walker: &mut impl Walk, expr: &expr::Grouping) {
+    walker.visit_expr(&expr.expression);
+}
+
+pub fn walk_index_expr(walker: &mut impl Walk, expr: &expr::Index) {
+    walker.visit_expr(&expr.object);
+    walker.visit_expr(&expr.index);
+}
+
+pub fn walk_index_set_expr(walker: &mut impl Walk, expr: &expr::IndexSet) {
+    walker.visit_expr(&expr.object);
+    walker.visit_expr(&expr.index);
+    walker.visit_expr(&expr.value);
+}
+
+pub fn walk_lambda_expr(walker: &mut impl Walk, expr: &expr::Lambda) {
+    expr.body.iter().for_each(|s| walker.visit_stmt(s));
+}
+
+pub fn walk_list_expr(walker: &mut impl Walk, expr: &expr::List) {
+    expr.elements.iter().for_each(|e| walker.visit_expr(e));
+}
+
+pub fn walk_logical_expr(walker: &mut impl Walk, expr: &expr::Logical) {
+    walker.visit_expr(&expr.left);
+    walker.visit_expr(&expr.right);
+}
+
+pub fn walk_map_expr(walker: &mut impl Walk, expr: &expr::Map) {
+    for (key, value) in &expr.entries {
+        walker.visit_expr(key);
+        walker.visit_expr(value);
+    }
+}
+
+pub fn walk_set_expr(walker: &mut impl Walk, expr: &expr::Set) {
+    walker.visit_expr(&expr.object);
+    walker.visit_expr(&expr.value);
+}
+
+pub fn walk_ternary_expr(walker: &mut impl Walk, expr: &expr::Ternary) {
+    walker.visit_expr(&expr.condition);
+    walker.visit_expr(&expr.then_branch);
+    walker.visit_expr(&expr.else_branch);
+}
+
+pub fn walk_unary_expr(walker: &mut impl Walk, expr: &expr::Unary) {
+    walker.visit_expr(&expr.right);
+}